@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.13;
+
+import "forge-std/Script.sol";
+import {ToyNFT} from "src/ToyNFT.sol";
+import {Marketplace} from "src/Marketplace.sol";
+
+// Deploys the contracts to a local anvil node and emits every Marketplace event,
+// so that the graphql-backend indexer has something to index:
+// forge script script/MarketplaceEvents.s.sol --rpc-url http://127.0.0.1:8545 --broadcast
+contract MarketplaceEventsScript is Script {
+    // the first two default anvil accounts
+    uint256 sellerKey = 0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80;
+    uint256 buyerKey = 0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d;
+
+    function run() public {
+        address seller = vm.addr(sellerKey);
+
+        vm.startBroadcast(sellerKey);
+        Marketplace marketPlace = new Marketplace();
+        ToyNFT toyFactory = new ToyNFT("Deez NFTs", "DEEZ-NFTS");
+
+        uint256 soldTokenId = toyFactory.mintTo{value: 0.08 ether}(seller, "my_url_to_meta_data");
+        toyFactory.approve(address(marketPlace), soldTokenId);
+        marketPlace.listItem(address(toyFactory), soldTokenId, 0.5 ether);
+        marketPlace.updateListing(address(toyFactory), soldTokenId, 1 ether);
+
+        uint256 canceledTokenId = toyFactory.mintTo{value: 0.08 ether}(seller, "my_url_to_meta_data");
+        toyFactory.approve(address(marketPlace), canceledTokenId);
+        marketPlace.listItem(address(toyFactory), canceledTokenId, 0.5 ether);
+        marketPlace.cancelListing(address(toyFactory), canceledTokenId);
+        vm.stopBroadcast();
+
+        vm.startBroadcast(buyerKey);
+        marketPlace.buyItem{value: 1 ether}(address(toyFactory), soldTokenId);
+        vm.stopBroadcast();
+
+        console.log("Marketplace:", address(marketPlace));
+        console.log("ToyNFT:", address(toyFactory));
+    }
+}
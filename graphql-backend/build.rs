@@ -0,0 +1,4 @@
+// the migrations are embedded in the binary, so it is rebuilt when they change
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+alter table lots drop constraint for_sale_requires_asking_price_chk;
+drop index lots_asking_price_idx;
+alter table lots drop column quantity;
+alter table lots drop column currency_symbol;
+alter table lots drop column asking_price;
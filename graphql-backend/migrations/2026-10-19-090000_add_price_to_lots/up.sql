@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE lots ADD COLUMN asking_price NUMERIC CHECK (asking_price >= 0);
+ALTER TABLE lots ADD COLUMN currency_symbol TEXT REFERENCES currencies (symbol) DEFAULT 'USD' NOT NULL;
+ALTER TABLE lots ADD COLUMN quantity INTEGER DEFAULT 1 NOT NULL CHECK (quantity > 0);
+
+CREATE INDEX lots_asking_price_idx ON lots (asking_price);
+
+-- a lot cannot be listed for sale without a price
+-- this should be caught in application logic and return a 422, but if it doesn't...
+-- NOT VALID leaves lots that were listed before prices existed alone
+ALTER TABLE lots ADD CONSTRAINT for_sale_requires_asking_price_chk CHECK (status != 'for sale' OR asking_price IS NOT NULL) NOT VALID;
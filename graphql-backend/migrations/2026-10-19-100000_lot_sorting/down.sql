@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP FUNCTION IF EXISTS lot_relevance(TEXT, TEXT, TEXT, TEXT[]);
+DROP INDEX lots_title_idx;
+DROP INDEX lots_updated_at_idx;
+DROP INDEX lots_created_at_idx;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE INDEX lots_created_at_idx ON lots (created_at, id);
+CREATE INDEX lots_updated_at_idx ON lots (updated_at, id);
+CREATE INDEX lots_title_idx ON lots (lower(title), id);
+
+-- Scores how well a lot matches lowercase search terms, used to sort by relevance.
+-- Each term counts 4 for an exact external id, 2 when found in the title and 1 in the description.
+CREATE OR REPLACE FUNCTION lot_relevance(title TEXT, description TEXT, external_id TEXT, terms TEXT[]) RETURNS BIGINT AS $$
+    SELECT COALESCE(SUM(
+        CASE WHEN external_id = term THEN 4 ELSE 0 END +
+        CASE WHEN lower(title) LIKE '%' || term || '%' THEN 2 ELSE 0 END +
+        CASE WHEN lower(description) LIKE '%' || term || '%' THEN 1 ELSE 0 END
+    ), 0)
+    FROM unnest(terms) AS term;
+$$ LANGUAGE SQL IMMUTABLE;
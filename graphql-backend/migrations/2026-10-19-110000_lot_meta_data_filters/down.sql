@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DROP FUNCTION IF EXISTS lot_meta_data_matches(JSONB, TEXT, JSONB);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Checks lot meta data against a JSON path, where values are passed in as variables.
+-- The path arrives as text, since there is no way to bind a jsonpath parameter from diesel.
+CREATE OR REPLACE FUNCTION lot_meta_data_matches(meta_data JSONB, path TEXT, vars JSONB) RETURNS BOOLEAN AS $$
+    SELECT COALESCE(jsonb_path_exists(meta_data, path::jsonpath, vars, true), false);
+$$ LANGUAGE SQL IMMUTABLE;
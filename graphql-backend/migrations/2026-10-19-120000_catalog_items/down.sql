@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX lots_catalog_item_id_idx;
+ALTER TABLE lots DROP COLUMN catalog_item_id;
+DROP TABLE catalog_items;
+ALTER TABLE users DROP COLUMN is_admin;
+DROP EXTENSION IF EXISTS pg_trgm;
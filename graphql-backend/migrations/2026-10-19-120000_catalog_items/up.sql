@@ -0,0 +1,27 @@
+-- Your SQL goes here
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN DEFAULT FALSE NOT NULL;
+
+CREATE TABLE catalog_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    source TEXT NOT NULL,
+    external_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    theme TEXT,
+    year INTEGER,
+    image_url TEXT,
+    piece_count INTEGER,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (source, external_id)
+);
+
+CREATE INDEX catalog_items_external_id_idx ON catalog_items (external_id);
+-- trigram index for autocomplete on partial names
+CREATE INDEX catalog_items_name_trgm_idx ON catalog_items USING GIN (name gin_trgm_ops);
+
+SELECT diesel_manage_updated_at('catalog_items');
+
+ALTER TABLE lots ADD COLUMN catalog_item_id UUID REFERENCES catalog_items (id);
+CREATE INDEX lots_catalog_item_id_idx ON lots (catalog_item_id);
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER set_listed_at ON lots;
+DROP FUNCTION lots_set_listed_at();
+DROP INDEX lots_listed_at_idx;
+ALTER TABLE lots DROP COLUMN listed_at;
+DROP TABLE saved_searches;
+DROP TABLE watched_lots;
+DROP TABLE notifications;
@@ -0,0 +1,58 @@
+-- Your SQL goes here
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    read_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX notifications_user_id_created_at_idx ON notifications (user_id, created_at DESC);
+SELECT diesel_manage_updated_at('notifications');
+
+-- the status and price the watcher was last told about
+CREATE TABLE watched_lots (
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    lot_id UUID NOT NULL REFERENCES lots (id) ON DELETE CASCADE,
+    last_status TEXT NOT NULL,
+    last_asking_price NUMERIC,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, lot_id)
+);
+
+CREATE INDEX watched_lots_lot_id_idx ON watched_lots (lot_id);
+SELECT diesel_manage_updated_at('watched_lots');
+
+-- params holds a serialized FilterLots
+CREATE TABLE saved_searches (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    params JSONB NOT NULL,
+    last_matched_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (user_id, name)
+);
+
+SELECT diesel_manage_updated_at('saved_searches');
+
+-- when a lot was last put up for sale, so saved searches only match newly listed lots
+ALTER TABLE lots ADD COLUMN listed_at TIMESTAMP;
+UPDATE lots SET listed_at = updated_at WHERE status = 'for sale';
+CREATE INDEX lots_listed_at_idx ON lots (listed_at);
+
+CREATE OR REPLACE FUNCTION lots_set_listed_at() RETURNS trigger AS $$
+BEGIN
+    IF NEW.status = 'for sale' AND (TG_OP = 'INSERT' OR OLD.status <> 'for sale') THEN
+        NEW.listed_at := current_timestamp;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER set_listed_at BEFORE INSERT OR UPDATE ON lots
+    FOR EACH ROW EXECUTE PROCEDURE lots_set_listed_at();
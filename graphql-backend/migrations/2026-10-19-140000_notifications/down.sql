@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE notification_preferences;
+DROP INDEX notifications_unread_idx;
+ALTER TABLE notifications DROP COLUMN emailed_at;
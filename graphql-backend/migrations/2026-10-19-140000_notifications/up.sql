@@ -0,0 +1,17 @@
+-- Your SQL goes here
+-- set once the notification went out in an email digest
+ALTER TABLE notifications ADD COLUMN emailed_at TIMESTAMP;
+CREATE INDEX notifications_unread_idx ON notifications (user_id) WHERE read_at IS NULL;
+
+-- kinds without preferences are delivered in app and left out of the email digest
+CREATE TABLE notification_preferences (
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    enabled BOOLEAN DEFAULT TRUE NOT NULL,
+    email_digest BOOLEAN DEFAULT FALSE NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, kind)
+);
+
+SELECT diesel_manage_updated_at('notification_preferences');
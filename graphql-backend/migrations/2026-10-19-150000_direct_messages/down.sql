@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE user_blocks;
+DROP TABLE messages;
+DROP TABLE conversations;
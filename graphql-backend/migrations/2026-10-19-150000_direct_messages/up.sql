@@ -0,0 +1,43 @@
+-- Your SQL goes here
+-- the participants are stored in uuid order, so that a pair of users has one thread per lot
+CREATE TABLE conversations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_a_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    user_b_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    lot_id UUID REFERENCES lots (id) ON DELETE SET NULL,
+    last_message_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CHECK (user_a_id < user_b_id)
+);
+
+CREATE UNIQUE INDEX conversations_participants_idx ON conversations (user_a_id, user_b_id)
+    WHERE lot_id IS NULL;
+CREATE UNIQUE INDEX conversations_participants_lot_idx ON conversations (user_a_id, user_b_id, lot_id)
+    WHERE lot_id IS NOT NULL;
+CREATE INDEX conversations_user_b_id_idx ON conversations (user_b_id);
+
+SELECT diesel_manage_updated_at('conversations');
+
+CREATE TABLE messages (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    conversation_id UUID NOT NULL REFERENCES conversations (id) ON DELETE CASCADE,
+    sender_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    -- set once the other participant read the message
+    read_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX messages_conversation_id_created_at_idx ON messages (conversation_id, created_at DESC);
+
+SELECT diesel_manage_updated_at('messages');
+
+CREATE TABLE user_blocks (
+    blocker_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    blocked_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (blocker_id, blocked_id)
+);
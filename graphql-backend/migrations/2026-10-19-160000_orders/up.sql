@@ -0,0 +1,33 @@
+-- Your SQL goes here
+CREATE TABLE order_states (
+    description TEXT PRIMARY KEY NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+INSERT INTO order_states (description) VALUES ('awaiting payment'), ('paid'), ('shipped'), ('delivered'), ('disputed'), ('refunded'), ('cancelled');
+
+-- the price and currency are copied from the lot when the sale is agreed,
+-- later changes to the lot do not change what was agreed
+CREATE TABLE orders (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    lot_id UUID NOT NULL REFERENCES lots (id),
+    buyer_id UUID NOT NULL REFERENCES users (id),
+    seller_id UUID NOT NULL REFERENCES users (id),
+    price NUMERIC NOT NULL CHECK (price >= 0),
+    currency_symbol TEXT NOT NULL REFERENCES currencies (symbol),
+    shipping_address JSONB NOT NULL,
+    tracking_number TEXT,
+    state TEXT REFERENCES order_states (description) DEFAULT 'awaiting payment' NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CHECK (buyer_id != seller_id)
+);
+
+-- a lot is sold once, cancelled and refunded orders leave room for the next one
+CREATE UNIQUE INDEX orders_open_lot_id_idx ON orders (lot_id)
+    WHERE state NOT IN ('cancelled', 'refunded');
+CREATE INDEX orders_buyer_id_created_at_idx ON orders (buyer_id, created_at DESC);
+CREATE INDEX orders_seller_id_created_at_idx ON orders (seller_id, created_at DESC);
+
+SELECT diesel_manage_updated_at('orders');
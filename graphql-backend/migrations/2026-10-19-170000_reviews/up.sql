@@ -0,0 +1,18 @@
+-- Your SQL goes here
+-- buyer and seller can each review the other once per order
+CREATE TABLE reviews (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    order_id UUID NOT NULL REFERENCES orders (id) ON DELETE CASCADE,
+    reviewer_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    reviewee_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    rating INTEGER NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    body TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (order_id, reviewer_id),
+    CHECK (reviewer_id != reviewee_id)
+);
+
+CREATE INDEX reviews_reviewee_id_created_at_idx ON reviews (reviewee_id, created_at DESC);
+
+SELECT diesel_manage_updated_at('reviews');
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE chain_cursors;
+DROP TABLE marketplace_events;
+DROP INDEX lots_nft_address_token_id_idx;
+ALTER TABLE lots DROP CONSTRAINT lots_nft_chk;
+ALTER TABLE lots DROP COLUMN token_id;
+ALTER TABLE lots DROP COLUMN nft_address;
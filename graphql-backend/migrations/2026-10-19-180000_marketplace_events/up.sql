@@ -0,0 +1,51 @@
+-- Your SQL goes here
+-- the NFT a lot is sold as on chain, addresses are stored as lowercase 0x prefixed hex
+ALTER TABLE lots ADD COLUMN nft_address TEXT;
+ALTER TABLE lots ADD COLUMN token_id NUMERIC(78, 0) CHECK (token_id >= 0);
+ALTER TABLE lots ADD CONSTRAINT lots_nft_chk CHECK ((nft_address IS NULL) = (token_id IS NULL));
+
+CREATE UNIQUE INDEX lots_nft_address_token_id_idx ON lots (nft_address, token_id)
+    WHERE nft_address IS NOT NULL;
+
+-- logs of the Marketplace contract, see foundry-contracts/src/Marketplace.sol
+-- a log is identified by its transaction and position in it, so indexing a block twice is harmless
+CREATE TABLE marketplace_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    chain_id BIGINT NOT NULL,
+    contract_address TEXT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    -- ItemListed, ItemCanceled or ItemBought
+    event TEXT NOT NULL,
+    -- the seller, or the buyer for ItemBought
+    account TEXT NOT NULL,
+    nft_address TEXT NOT NULL,
+    token_id NUMERIC(78, 0) NOT NULL,
+    -- in wei, not set for ItemCanceled
+    price NUMERIC(78, 0),
+    lot_id UUID REFERENCES lots (id) ON DELETE SET NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (chain_id, tx_hash, log_index)
+);
+
+CREATE INDEX marketplace_events_nft_idx ON marketplace_events (nft_address, token_id, block_number DESC);
+CREATE INDEX marketplace_events_lot_id_idx ON marketplace_events (lot_id);
+CREATE INDEX marketplace_events_block_number_idx ON marketplace_events (chain_id, contract_address, block_number);
+
+SELECT diesel_manage_updated_at('marketplace_events');
+
+-- the last block indexed per contract, its hash tells whether the chain was reorganised underneath it
+CREATE TABLE chain_cursors (
+    chain_id BIGINT NOT NULL,
+    contract_address TEXT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (chain_id, contract_address)
+);
+
+SELECT diesel_manage_updated_at('chain_cursors');
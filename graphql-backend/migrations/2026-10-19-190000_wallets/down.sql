@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE siwe_nonces;
+DROP TABLE wallets;
+
+-- wallet accounts keep a placeholder they cannot sign in with
+UPDATE users SET email = id || '@wallet.invalid' WHERE email IS NULL;
+UPDATE users SET password = '' WHERE password IS NULL;
+ALTER TABLE users ALTER COLUMN password SET NOT NULL;
+ALTER TABLE users ALTER COLUMN email SET NOT NULL;
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+-- accounts created by signing in with a wallet have neither
+ALTER TABLE users ALTER COLUMN email DROP NOT NULL;
+ALTER TABLE users ALTER COLUMN password DROP NOT NULL;
+
+-- addresses are stored lowercase, and belong to one account at most
+CREATE TABLE wallets (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    address TEXT UNIQUE NOT NULL,
+    -- the chain the address was last signed in on
+    chain_id BIGINT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX wallets_user_id_idx ON wallets (user_id);
+
+SELECT diesel_manage_updated_at('wallets');
+
+-- handed out for sign-in messages, each can be used once before it expires
+CREATE TABLE siwe_nonces (
+    nonce TEXT PRIMARY KEY,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
@@ -0,0 +1,22 @@
+-- Your SQL goes here
+-- ToyNFT tokens minted with the metadata of a lot to a wallet of its owner, as indexed
+-- a lot is minted once, later mints with its metadata are not recorded
+CREATE TABLE lot_mints (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    lot_id UUID UNIQUE NOT NULL REFERENCES lots (id) ON DELETE CASCADE,
+    chain_id BIGINT NOT NULL,
+    nft_address TEXT NOT NULL,
+    token_id NUMERIC(78, 0) NOT NULL,
+    recipient TEXT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash TEXT NOT NULL,
+    tx_hash TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (chain_id, tx_hash, log_index)
+);
+
+CREATE INDEX lot_mints_chain_id_nft_address_block_number_idx ON lot_mints (chain_id, nft_address, block_number);
+
+SELECT diesel_manage_updated_at('lot_mints');
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE exchange_rates;
+ALTER TABLE currencies DROP COLUMN decimals;
@@ -0,0 +1,25 @@
+-- Your SQL goes here
+-- the number of decimals amounts in the currency are rounded to once converted
+ALTER TABLE currencies ADD COLUMN decimals INTEGER DEFAULT 2 NOT NULL;
+UPDATE currencies SET decimals = 8 WHERE symbol = 'BTC';
+UPDATE currencies SET decimals = 18 WHERE symbol = 'ETH';
+
+-- one unit of the base currency is worth rate units of the quote currency
+CREATE TABLE exchange_rates (
+    base_symbol TEXT NOT NULL REFERENCES currencies (symbol),
+    quote_symbol TEXT NOT NULL REFERENCES currencies (symbol),
+    source TEXT NOT NULL,
+    rate NUMERIC NOT NULL CHECK (rate > 0),
+    recorded_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    PRIMARY KEY (base_symbol, quote_symbol, recorded_at, source),
+    CHECK (base_symbol <> quote_symbol)
+);
+
+SELECT diesel_manage_updated_at('exchange_rates');
+
+-- rates as of the day the seeded prices were recorded
+INSERT INTO exchange_rates (base_symbol, quote_symbol, source, rate, recorded_at) VALUES
+('BTC', 'USD', 'seed', 27118.46, '2023-06-04'),
+('ETH', 'USD', 'seed', 1891.07, '2023-06-04');
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- disabled users can no longer sign in, and their tokens stop working
+ALTER TABLE users ADD COLUMN is_disabled BOOLEAN DEFAULT FALSE NOT NULL;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX lots_deleted_at_idx;
+ALTER TABLE lots DROP COLUMN status_before_delete;
+ALTER TABLE lots DROP COLUMN deleted_at;
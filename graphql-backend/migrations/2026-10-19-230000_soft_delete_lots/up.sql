@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- deleted lots stay in the trash of their owner, who can restore them to the status they had,
+-- until they are purged some days after deleted_at
+ALTER TABLE lots ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE lots ADD COLUMN status_before_delete TEXT REFERENCES lot_statuses (description);
+
+-- lots deleted before are restored as drafts, and get the whole retention from now on
+-- to be restored rather than being purged right away
+UPDATE lots SET deleted_at = now() WHERE status = 'deleted';
+
+CREATE INDEX lots_deleted_at_idx ON lots (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP MATERIALIZED VIEW trending_lots;
+ALTER TABLE lots DROP COLUMN view_count;
+DROP TABLE lot_views;
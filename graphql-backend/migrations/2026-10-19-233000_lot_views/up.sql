@@ -0,0 +1,44 @@
+-- Your SQL goes here
+-- a view of a lot counts once per user, or per fingerprint of an anonymous visitor,
+-- within the dedup window of the config
+CREATE TABLE lot_views (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    lot_id UUID NOT NULL REFERENCES lots (id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users (id) ON DELETE CASCADE,
+    fingerprint TEXT,
+    viewed_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    CHECK (user_id IS NOT NULL OR fingerprint IS NOT NULL)
+);
+
+CREATE INDEX lot_views_lot_id_viewed_at_idx ON lot_views (lot_id, viewed_at);
+CREATE INDEX lot_views_viewed_at_idx ON lot_views (viewed_at);
+
+ALTER TABLE lots ADD COLUMN view_count INTEGER DEFAULT 0 NOT NULL;
+
+-- Scores the lots by their views, watches and orders within a day and a week,
+-- each weighing half as much for every half life it is old.
+-- It is refreshed on an interval, see the trending section of the config.
+CREATE MATERIALIZED VIEW trending_lots AS
+WITH time_windows (time_window, period, half_life) AS (
+    VALUES ('day', INTERVAL '1 day', INTERVAL '6 hours'),
+           ('week', INTERVAL '7 days', INTERVAL '2 days')
+),
+events (lot_id, weight, happened_at) AS (
+    SELECT lot_id, 1.0, viewed_at FROM lot_views
+    UNION ALL
+    SELECT lot_id, 3.0, created_at FROM watched_lots
+    UNION ALL
+    SELECT lot_id, 5.0, created_at FROM orders
+)
+SELECT
+    events.lot_id,
+    time_windows.time_window,
+    SUM(events.weight * power(0.5,
+        EXTRACT(EPOCH FROM LOCALTIMESTAMP - events.happened_at) / EXTRACT(EPOCH FROM time_windows.half_life)
+    ))::DOUBLE PRECISION AS score
+FROM events
+JOIN time_windows ON events.happened_at > LOCALTIMESTAMP - time_windows.period
+GROUP BY events.lot_id, time_windows.time_window;
+
+-- lets the view be refreshed concurrently, without blocking the queries reading it
+CREATE UNIQUE INDEX trending_lots_time_window_lot_id_idx ON trending_lots (time_window, lot_id);
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+CREATE OR REPLACE FUNCTION lots_set_listed_at() RETURNS trigger AS $$
+BEGIN
+    IF NEW.status = 'for sale' AND (TG_OP = 'INSERT' OR OLD.status <> 'for sale') THEN
+        NEW.listed_at := current_timestamp;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX lots_listed_xid_idx;
+ALTER TABLE saved_searches DROP COLUMN last_matched_xid;
+ALTER TABLE lots DROP COLUMN listed_xid;
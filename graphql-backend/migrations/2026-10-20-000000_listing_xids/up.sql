@@ -0,0 +1,31 @@
+-- Your SQL goes here
+-- saved searches are matched up to a transaction id rather than a time
+-- a listing takes current_timestamp when its transaction starts and is seen once it commits,
+-- so a search matched in between moved past it for good
+-- every transaction below the xmin of a snapshot has finished, so the alert matcher reads
+-- the xmin first and matches the listings from the last one up to it
+ALTER TABLE lots ADD COLUMN listed_xid BIGINT;
+ALTER TABLE saved_searches ADD COLUMN last_matched_xid BIGINT;
+
+-- earlier listings and matches keep their order by time, below any transaction id
+UPDATE lots
+SET listed_xid = (extract(epoch FROM listed_at) * 1000000)::BIGINT - 4611686018427387904
+WHERE listed_at IS NOT NULL;
+UPDATE saved_searches
+SET last_matched_xid = (extract(epoch FROM last_matched_at) * 1000000)::BIGINT - 4611686018427387904 + 1;
+
+ALTER TABLE saved_searches
+    ALTER COLUMN last_matched_xid SET DEFAULT pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT,
+    ALTER COLUMN last_matched_xid SET NOT NULL;
+
+CREATE INDEX lots_listed_xid_idx ON lots (listed_xid);
+
+CREATE OR REPLACE FUNCTION lots_set_listed_at() RETURNS trigger AS $$
+BEGIN
+    IF NEW.status = 'for sale' AND (TG_OP = 'INSERT' OR OLD.status <> 'for sale') THEN
+        NEW.listed_at := current_timestamp;
+        NEW.listed_xid := pg_current_xact_id()::TEXT::BIGINT;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
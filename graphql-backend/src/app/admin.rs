@@ -0,0 +1,24 @@
+use uuid::Uuid;
+
+// Client Messages ↓
+
+// the auth of a user as if they had signed in, so that admin commands go through the
+// same handlers, and business rules, as the requests of the user
+#[derive(Debug)]
+pub struct ImpersonateUser {
+    pub username: String,
+}
+
+#[derive(Debug)]
+pub struct ImpersonateLotOwner {
+    pub lot_id: Uuid,
+}
+
+// sets the flags of a user that only admins can change, the ones not set are left as is
+#[derive(Debug)]
+pub struct SetUserFlags {
+    pub username: String,
+    pub email_verified: Option<bool>,
+    pub is_admin: Option<bool>,
+    pub is_disabled: Option<bool>,
+}
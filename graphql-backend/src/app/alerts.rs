@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use actix::prelude::*;
+
+use crate::db::Db;
+
+// Client Messages ↓
+
+// notifies watchers of lots that changed price or status,
+// and the owners of saved searches of lots newly put up for sale that match them
+#[derive(Debug)]
+pub struct MatchAlerts;
+
+// Actors ↓
+
+// runs the alert matching on an interval for as long as the server is up
+pub struct AlertMatcher {
+    pub db: Db,
+    pub interval: Duration,
+}
+
+impl Actor for AlertMatcher {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.interval, |matcher, _| {
+            let db = matcher.db.clone();
+            actix::spawn(async move {
+                match db.send(MatchAlerts).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(created)) => log::info!("created {} alert notifications", created),
+                    Ok(Err(e)) => log::error!("matching alerts failed: {}", e),
+                    Err(e) => log::error!("matching alerts failed: {}", e),
+                }
+            });
+        });
+    }
+}
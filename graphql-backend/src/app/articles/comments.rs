@@ -13,7 +13,6 @@ use crate::utils::{
 
 // Extractors ↓
 
-// use super::ArticlePath;
 
 // #[derive(Debug, Deserialize)]
 // pub struct ArticleCommentPath {
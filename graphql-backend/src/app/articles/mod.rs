@@ -14,11 +14,6 @@ use crate::utils::{
 
 // Extractors ↓
 
-#[derive(Debug, Deserialize)]
-pub struct ArticlePath {
-    pub slug: String,
-}
-
 #[derive(async_graphql::InputObject)]
 #[derive(Debug, Deserialize)]
 pub struct ArticlesParams {
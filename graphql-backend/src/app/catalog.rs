@@ -0,0 +1,94 @@
+use uuid::Uuid;
+
+use crate::{
+    app::exchange_rates::ConvertedAmount, error::ValidationError, models::Price,
+    utils::auth::Auth,
+};
+
+// Client Messages ↓
+
+// the dump formats the catalog can be imported from
+#[derive(async_graphql::Enum, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CatalogFormat {
+    // sets.csv from https://rebrickable.com/downloads
+    Rebrickable,
+    // the Sets catalog download from https://www.bricklink.com/catalogDownload.asp
+    Bricklink,
+}
+
+impl CatalogFormat {
+    // the source recorded on the imported items, which matches the source of their prices
+    pub fn source(&self) -> &str {
+        match self {
+            CatalogFormat::Rebrickable => "rebrickable",
+            CatalogFormat::Bricklink => "bricklink",
+        }
+    }
+}
+
+#[derive(async_graphql::InputObject, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCatalog {
+    pub format: CatalogFormat,
+    // contents of the dump, with its header row
+    pub items: String,
+    // contents of the Rebrickable themes.csv, used to name the theme ids of the sets
+    pub themes: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ImportCatalogAuthenticated {
+    pub auth: Auth,
+    pub params: ImportCatalog,
+}
+
+// imports a price list from a source, see utils/catalog_csv.rs for the columns
+#[derive(Debug)]
+pub struct ImportPrices {
+    pub source: String,
+    pub prices: String,
+}
+
+#[derive(Debug)]
+pub struct SearchCatalog {
+    pub query: String,
+    pub limit: Option<i32>, // <- if not set, is 10
+}
+
+#[derive(Debug)]
+pub struct GetCatalogItem {
+    pub id: Uuid,
+}
+
+// finds the catalog item a price was recorded for, preferring the source of the price
+#[derive(Debug)]
+pub struct GetCatalogItemForPrice {
+    pub external_id: String,
+    pub source: String,
+}
+
+#[derive(Debug)]
+pub struct GetPrices {
+    pub external_id: String,
+}
+
+// Server Responses ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+pub struct ImportCatalogResponse {
+    pub imported: usize,
+    // rows that were skipped, keyed by their line in the dump
+    pub errors: Vec<ValidationError>,
+}
+
+// a recorded price, along with its amount in the display currency of the query
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceResponse {
+    #[graphql(flatten)]
+    #[serde(flatten)]
+    pub price: Price,
+    // converted at the rates of when the price was recorded
+    pub display_amount: Option<ConvertedAmount>,
+}
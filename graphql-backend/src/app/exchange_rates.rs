@@ -0,0 +1,189 @@
+use std::time::Duration;
+
+use actix::prelude::*;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+
+use crate::{
+    app::catalog::PriceResponse,
+    db::Db,
+    error::ValidationError,
+    models::{LotWithImages, NewExchangeRate, Price},
+    prelude::*,
+    utils::{auth::Auth, exchange_rates::parse_snapshots, CustomDateTime, CustomDecimal},
+};
+
+// Client Messages ↓
+
+// imports rate snapshots uploaded by an admin, see utils/exchange_rates.rs for the format
+#[derive(Debug)]
+pub struct ImportExchangeRatesAuthenticated {
+    pub auth: Auth,
+    pub snapshots: String,
+}
+
+// stores the rates read by the importer, the latest one wins for a time and source
+#[derive(Debug)]
+pub struct StoreExchangeRates {
+    pub rates: Vec<NewExchangeRate>,
+}
+
+// converts amounts into a currency, each at the rates recorded last before its time
+#[derive(Debug)]
+pub struct ConvertAmounts {
+    pub to: String,
+    pub amounts: Vec<AmountToConvert>,
+}
+
+#[derive(Debug)]
+pub struct AmountToConvert {
+    pub amount: BigDecimal,
+    pub currency_symbol: String,
+    pub at: Option<NaiveDateTime>, // <- if not set, is now
+}
+
+// JSON response objects ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+pub struct ImportExchangeRatesResponse {
+    pub imported: usize,
+    // rates that were skipped, keyed by their pair of currencies
+    pub errors: Vec<ValidationError>,
+}
+
+// an amount converted into the display currency, the original is left as is
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertedAmount {
+    pub amount: CustomDecimal,
+    pub currency_symbol: String,
+    // units of the display currency per unit of the original one
+    pub rate: CustomDecimal,
+    // when the rate was recorded, none for amounts already in the display currency
+    pub rate_recorded_at: Option<CustomDateTime>,
+}
+
+// sets the asking prices of the lots in the display currency, if one is asked for
+pub async fn convert_lot_prices(
+    db: &Db,
+    lots: &mut [LotWithImages],
+    display_currency: Option<String>,
+) -> Result<()> {
+    let Some(to) = display_currency else {
+        return Ok(());
+    };
+
+    let priced: Vec<&mut LotWithImages> = lots
+        .iter_mut()
+        .filter(|lot| lot.lot.asking_price.is_some())
+        .collect();
+    let amounts = priced
+        .iter()
+        .map(|lot| AmountToConvert {
+            amount: lot.lot.asking_price.clone().unwrap_or_default(),
+            currency_symbol: lot.lot.currency_symbol.clone(),
+            at: None,
+        })
+        .collect();
+
+    let converted = db.send(ConvertAmounts { to, amounts }).await??;
+    for (lot, display_price) in priced.into_iter().zip(converted) {
+        lot.display_price = display_price;
+    }
+
+    Ok(())
+}
+
+// pairs the prices with their amounts in the display currency, if one is asked for
+pub async fn convert_prices(
+    db: &Db,
+    prices: Vec<Price>,
+    display_currency: Option<String>,
+) -> Result<Vec<PriceResponse>> {
+    let mut converted = match display_currency {
+        Some(to) => {
+            let amounts = prices
+                .iter()
+                .map(|price| AmountToConvert {
+                    amount: price.amount.clone(),
+                    currency_symbol: price.currency_symbol.clone(),
+                    at: Some(price.recorded_at),
+                })
+                .collect();
+            db.send(ConvertAmounts { to, amounts }).await??
+        }
+        None => vec![],
+    }
+    .into_iter();
+
+    Ok(prices
+        .into_iter()
+        .map(|price| PriceResponse {
+            price,
+            display_amount: converted.next().flatten(),
+        })
+        .collect())
+}
+
+// Actors ↓
+
+// imports rate snapshots on an interval from an http url, i.e. a mock rate API,
+// or from a local file kept up to date by something else
+pub struct ExchangeRateImporter {
+    pub db: Db,
+    pub url: String,
+    pub interval: Duration,
+}
+
+impl Actor for ExchangeRateImporter {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.import();
+        ctx.run_interval(self.interval, |importer, _| importer.import());
+    }
+}
+
+impl ExchangeRateImporter {
+    fn import(&self) {
+        let db = self.db.clone();
+        let url = self.url.clone();
+        actix::spawn(async move {
+            match import_snapshots(&db, &url).await {
+                Ok(0) => {}
+                Ok(imported) => log::info!("imported {} exchange rates", imported),
+                Err(e) => log::error!("importing exchange rates failed: {}", e),
+            }
+        });
+    }
+}
+
+async fn import_snapshots(db: &Db, url: &str) -> Result<usize, String> {
+    let contents = if url.starts_with("http://") || url.starts_with("https://") {
+        reqwest::get(url)
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?
+    } else {
+        std::fs::read_to_string(url).map_err(|e| e.to_string())?
+    };
+
+    // snapshots without a source are recorded as coming from where they were read
+    let parsed = parse_snapshots(&contents, url).map_err(|e| format!("{:?}", e))?;
+    let res = db
+        .send(StoreExchangeRates {
+            rates: parsed.rates,
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    for error in parsed.errors.iter().chain(&res.errors) {
+        log::warn!("skipped an exchange rate: {:?}", error);
+    }
+
+    Ok(res.imported)
+}
@@ -1,7 +1,16 @@
+use std::time::Duration;
+
+use actix::prelude::*;
+use bigdecimal::num_bigint::Sign;
 use uuid::Uuid;
 use validator::{Validate, ValidationError};
 
-use crate::{models, utils::auth::Auth};
+use crate::{
+    db::Db,
+    error::Error,
+    models,
+    utils::{auth::Auth, CustomDecimal},
+};
 
 // Client Messages ↓
 
@@ -19,6 +28,19 @@ pub struct CreateLot {
     pub description: String,
     pub images: Vec<CreateLotImage>,
     pub meta_data: serde_json::Value,
+    #[validate(custom(function = "validate_price", message = "cannot be negative"))]
+    pub asking_price: Option<CustomDecimal>,
+    pub currency_symbol: Option<String>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub quantity: Option<i32>,
+    // the external id is taken from the catalog item when it is not set
+    #[validate(custom(function = "validate_uuid", message = "catalog item id must be uuid"))]
+    pub catalog_item_id: Option<String>,
+    // the NFT the lot is sold as on the Marketplace contract, set together with the token id
+    #[validate(custom(function = "validate_address", message = "must be a 0x prefixed address"))]
+    pub nft_address: Option<String>,
+    #[validate(custom(function = "validate_token_id", message = "must be a whole number"))]
+    pub token_id: Option<CustomDecimal>,
 }
 
 #[derive(async_graphql::InputObject, Debug, Validate, Deserialize)]
@@ -32,6 +54,18 @@ pub struct UpdateLot {
     pub external_id: Option<String>,
     pub description: Option<String>,
     pub status: Option<String>,
+    #[validate(custom(function = "validate_price", message = "cannot be negative"))]
+    pub asking_price: Option<CustomDecimal>,
+    pub currency_symbol: Option<String>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub quantity: Option<i32>,
+    pub meta_data: Option<serde_json::Value>,
+    #[validate(custom(function = "validate_uuid", message = "catalog item id must be uuid"))]
+    pub catalog_item_id: Option<String>,
+    #[validate(custom(function = "validate_address", message = "must be a 0x prefixed address"))]
+    pub nft_address: Option<String>,
+    #[validate(custom(function = "validate_token_id", message = "must be a whole number"))]
+    pub token_id: Option<CustomDecimal>,
     //pub new_images: ...,
     // vec of image uuids to delete
     pub deleted_image_ids: Vec<String>,
@@ -43,12 +77,47 @@ fn validate_uuid(lot_id: &str) -> Result<(), ValidationError> {
         .map_err(|_| ValidationError::new("invalid_uuid"))
 }
 
+fn validate_price(price: &CustomDecimal) -> Result<(), ValidationError> {
+    if price.0.sign() == Sign::Minus {
+        return Err(ValidationError::new("negative_price"));
+    }
+    Ok(())
+}
+
+fn validate_address(address: &str) -> Result<(), ValidationError> {
+    match address.strip_prefix("0x") {
+        Some(hex) if hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Ok(()),
+        _ => Err(ValidationError::new("invalid_address")),
+    }
+}
+
+fn validate_token_id(token_id: &CustomDecimal) -> Result<(), ValidationError> {
+    if token_id.0.sign() == Sign::Minus || !token_id.0.is_integer() {
+        return Err(ValidationError::new("invalid_token_id"));
+    }
+    Ok(())
+}
+
+// the id of a field, for the callers that skip validate()
+pub fn parse_id(field: &str, value: &str) -> Result<Uuid, Error> {
+    Uuid::try_parse(value).map_err(|_| {
+        Error::UnprocessableEntity(json!({ "error": format!("{} must be uuid", field) }))
+    })
+}
+
 // convert client message to db message
-impl From<UpdateLot> for models::UpdateLot {
-    fn from(lot: UpdateLot) -> Self {
-        let id = Uuid::try_parse(&lot.lot_id).unwrap();
+impl TryFrom<UpdateLot> for models::UpdateLot {
+    type Error = Error;
 
-        models::UpdateLot {
+    fn try_from(lot: UpdateLot) -> Result<Self, Error> {
+        let id = parse_id("lot id", &lot.lot_id)?;
+        let catalog_item_id = lot
+            .catalog_item_id
+            .as_deref()
+            .map(|item_id| parse_id("catalog item id", item_id))
+            .transpose()?;
+
+        Ok(models::UpdateLot {
             id,
             category: lot.category,
             condition: lot.condition,
@@ -56,7 +125,14 @@ impl From<UpdateLot> for models::UpdateLot {
             external_id: lot.external_id,
             description: lot.description,
             status: lot.status,
-        }
+            asking_price: lot.asking_price.map(|price| price.0),
+            currency_symbol: lot.currency_symbol,
+            quantity: lot.quantity,
+            meta_data: lot.meta_data,
+            catalog_item_id,
+            nft_address: lot.nft_address.map(|address| address.to_lowercase()),
+            token_id: lot.token_id.map(|token_id| token_id.0),
+        })
     }
 }
 
@@ -72,12 +148,68 @@ pub struct UpdateLotAuthenticated {
     pub lot: models::UpdateLot,
 }
 
+// moves the lot to the trash
 #[derive(Debug)]
 pub struct DeleteLotAuthenticated {
     pub auth: Auth,
     pub lot_id: Uuid,
 }
 
+// takes the lot out of the trash with the status it had
+#[derive(Debug)]
+pub struct RestoreLotAuthenticated {
+    pub auth: Auth,
+    pub lot_id: Uuid,
+}
+
+// the lots in the trash of the user, most recently deleted first
+#[derive(Debug)]
+pub struct GetTrashedLots {
+    pub auth: Auth,
+}
+
+// deletes the lots that have been in the trash for more than the given days, with their images
+#[derive(Debug)]
+pub struct PurgeDeletedLots {
+    pub days: i64,
+}
+
+// the file formats lots are imported from and exported to, see utils/lot_file.rs
+#[derive(async_graphql::Enum, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LotFileFormat {
+    // a header row and a lot per row
+    Csv,
+    // a JSON object per line
+    JsonLines,
+}
+
+#[derive(async_graphql::InputObject, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportLots {
+    pub format: LotFileFormat,
+    // contents of the file
+    pub lots: String,
+    // only checks the lots, nothing is imported
+    #[graphql(default)]
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// imports all the lots of the file or, when a row has errors, none of them
+#[derive(Debug)]
+pub struct ImportLotsAuthenticated {
+    pub auth: Auth,
+    pub params: ImportLots,
+}
+
+// the lots of the user outside the trash, oldest first, in a file that imports them again
+#[derive(Debug)]
+pub struct ExportLotsAuthenticated {
+    pub auth: Auth,
+    pub format: LotFileFormat,
+}
+
 #[derive(async_graphql::InputObject, Debug, Validate, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateLotImage {
@@ -85,22 +217,186 @@ pub struct CreateLotImage {
     pub is_thumbnail: bool,
 }
 
-#[derive(async_graphql::InputObject, Debug, Validate, Deserialize)]
+#[derive(async_graphql::InputObject, Debug, Validate, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FilterLots {
     pub categories: Vec<String>,
     pub conditions: Vec<String>,
     pub terms: Vec<String>,
-    pub page: Option<i32>,
-    pub limit: Option<i32>,
+    pub page: Option<i32>,  // <- starts at 1, ignored when `after` is set
+    pub limit: Option<i32>, // <- if not set, is 20 when a page is set and every lot otherwise
+    // id of the last lot of the previous page
+    pub after: Option<String>,
     pub statuses: Vec<String>,
+    pub min_price: Option<CustomDecimal>,
+    pub max_price: Option<CustomDecimal>,
+    pub currency_symbol: Option<String>,
+    pub sort: Option<LotSort>,
+    #[graphql(default)]
+    #[serde(default)]
+    pub meta_data: Vec<MetaDataFilter>,
 }
 
+// matches lots whose meta data value at key equals a value and/or lies in a range,
+// i.e. theme equals "Star Wars" or year between 1999 and 2005
+// without a condition it matches lots that have the key
+#[derive(async_graphql::InputObject, Debug, Serialize, Deserialize)]
+pub struct MetaDataFilter {
+    pub key: String,
+    pub equals: Option<serde_json::Value>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+// if not set, lots are sorted by relevance when terms are present and newest first otherwise
+#[derive(async_graphql::Enum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LotSort {
+    Newest,
+    Oldest,
+    RecentlyUpdated,
+    PriceAsc,
+    PriceDesc,
+    Relevance,
+    TitleAsc,
+}
+
+// without auth only the lots out for anyone to see are listed
 #[derive(Debug)]
 pub struct FilterLotsAuthenticated {
-    pub auth: Auth,
+    pub auth: Option<Auth>,
+    pub params: FilterLots,
+    pub owner_id: Option<Uuid>,
+}
+
+// a lot by id, drafted and deleted lots are only found by their owner
+#[derive(Debug)]
+pub struct GetLot {
+    pub auth: Option<Auth>,
+    pub lot_id: Uuid,
+}
+
+// counts a view of a lot, unless the user or fingerprint viewed it within the dedup window
+#[derive(Debug)]
+pub struct RecordLotView {
+    pub lot_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub fingerprint: Option<String>,
+}
+
+// the lots for sale scored highest in the time window, see the lot_views migration
+#[derive(Debug)]
+pub struct GetTrendingLots {
+    pub category: Option<String>,
+    pub window: TrendingWindow,
+    pub limit: Option<i32>,
+}
+
+// recomputes the trending scores
+#[derive(Debug)]
+pub struct RefreshTrendingLots;
+
+#[derive(async_graphql::Enum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrendingWindow {
+    #[default]
+    Day,
+    Week,
+}
+
+impl TrendingWindow {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TrendingWindow::Day => "day",
+            TrendingWindow::Week => "week",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GetLotFacets {
     pub params: FilterLots,
     pub owner_id: Option<Uuid>,
 }
 
+// Actors ↓
+
+// purges the trash on an interval for as long as the server is up
+pub struct LotPurger {
+    pub db: Db,
+    pub days: i64,
+    pub interval: Duration,
+}
+
+impl Actor for LotPurger {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.interval, |purger, _| {
+            let db = purger.db.clone();
+            let days = purger.days;
+            actix::spawn(async move {
+                match db.send(PurgeDeletedLots { days }).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(purged)) => log::info!("purged {} deleted lots", purged),
+                    Ok(Err(e)) => log::error!("purging deleted lots failed: {}", e),
+                    Err(e) => log::error!("purging deleted lots failed: {}", e),
+                }
+            });
+        });
+    }
+}
+
+// refreshes the trending scores on an interval for as long as the server is up
+pub struct TrendingRefresher {
+    pub db: Db,
+    pub interval: Duration,
+}
+
+impl Actor for TrendingRefresher {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.interval, |refresher, _| {
+            let db = refresher.db.clone();
+            actix::spawn(async move {
+                match db.send(RefreshTrendingLots).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => log::error!("refreshing trending lots failed: {}", e),
+                    Err(e) => log::error!("refreshing trending lots failed: {}", e),
+                }
+            });
+        });
+    }
+}
+
 // Server Responses ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+pub struct ImportLotsResponse {
+    // the lots that passed the checks
+    pub valid: usize,
+    // none on a dry run
+    pub imported: usize,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceRange {
+    pub currency_symbol: String,
+    pub min: CustomDecimal,
+    pub max: CustomDecimal,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+pub struct LotFacets {
+    pub categories: Vec<FacetCount>,
+    pub conditions: Vec<FacetCount>,
+    pub statuses: Vec<FacetCount>,
+    pub prices: Vec<PriceRange>,
+}
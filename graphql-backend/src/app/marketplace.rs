@@ -0,0 +1,501 @@
+use std::time::Duration;
+
+use actix::prelude::*;
+use bigdecimal::BigDecimal;
+
+use crate::{
+    app::nft::lot_id_from_metadata_url,
+    db::Db,
+    models::{ChainCursor, MarketplaceEventKind, NewLotMint, NewMarketplaceEvent},
+    utils::{
+        abi::{self, AbiEvent, AbiValue},
+        eth_rpc::{parse_quantity, EthRpc, Log},
+        CustomDecimal,
+    },
+};
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+// see foundry-contracts/src/Marketplace.sol and ToyNFT.sol
+lazy_static! {
+    static ref MARKETPLACE_EVENTS: Vec<AbiEvent> =
+        abi::events(include_str!("../../abi/Marketplace.json"))
+            .expect("the Marketplace ABI is not valid");
+    static ref TRANSFER_EVENT: AbiEvent = abi::events(include_str!("../../abi/ToyNFT.json"))
+        .expect("the ToyNFT ABI is not valid")
+        .into_iter()
+        .find(|event| event.name == "Transfer")
+        .expect("the ToyNFT ABI has no Transfer event");
+}
+
+// Client Messages ↓
+
+#[derive(Debug)]
+pub struct GetChainCursor {
+    pub chain_id: i64,
+    pub contract_address: String,
+}
+
+// stores what was indexed of a contract in a block range and moves its cursor to the last block
+// events of the Marketplace contract, mints of the ToyNFT contract
+#[derive(Debug)]
+pub struct StoreIndexedLogs {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub events: Vec<NewMarketplaceEvent>,
+    pub mints: Vec<NewLotMint>,
+    pub block_number: i64,
+    pub block_hash: Option<String>,
+}
+
+// forgets what was indexed of a contract from the block on, so that it is indexed again
+#[derive(Debug)]
+pub struct RewindIndexedLogs {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub from_block: i64,
+}
+
+// the listing of an NFT on the marketplace, if it is listed
+#[derive(Debug)]
+pub struct GetMarketplaceListing {
+    pub nft_address: String,
+    pub token_id: BigDecimal,
+}
+
+// JSON response objects ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketplaceListing {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub seller: String,
+    // in wei
+    pub price: CustomDecimal,
+    pub block_number: i64,
+    pub tx_hash: String,
+}
+
+// Actors ↓
+
+// indexes the logs of the Marketplace contract into marketplace_events, and the mints
+// of lots on the ToyNFT contract into lot_mints
+// only blocks with enough confirmations are indexed, and if the last indexed block
+// is reorganised away regardless, the logs of the blocks before it are indexed again
+#[derive(Clone)]
+pub struct MarketplaceIndexer {
+    pub db: Db,
+    pub rpc: EthRpc,
+    // lowercase 0x prefixed hex
+    pub contract_address: String,
+    pub toy_nft_address: Option<String>,
+    pub start_block: i64,
+    pub confirmations: i64,
+    pub batch_size: i64,
+    pub interval: Duration,
+}
+
+impl Actor for MarketplaceIndexer {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.index(ctx);
+    }
+}
+
+impl MarketplaceIndexer {
+    // rounds run back to back, so a slow node never has two of them overlap
+    fn index(&mut self, ctx: &mut Context<Self>) {
+        self.clone()
+            .index_batch()
+            .into_actor(self)
+            .map(|res, indexer, ctx| {
+                let delay = match res {
+                    // behind the chain, carry on right away
+                    Ok(true) => Duration::ZERO,
+                    Ok(false) => indexer.interval,
+                    Err(e) => {
+                        log::error!("indexing marketplace events failed: {}", e);
+                        indexer.interval
+                    }
+                };
+                ctx.run_later(delay, |indexer, ctx| indexer.index(ctx));
+            })
+            .spawn(ctx);
+    }
+
+    // indexes the next batch of confirmed blocks of each contract, true when there are more to index
+    async fn index_batch(self) -> Result<bool, String> {
+        let chain_id = self.rpc.chain_id().await?;
+        let confirmed_block = self.rpc.block_number().await? - self.confirmations;
+
+        let mut behind = self
+            .index_contract(chain_id, confirmed_block, &self.contract_address)
+            .await?;
+        if let Some(ref toy_nft_address) = self.toy_nft_address {
+            behind |= self
+                .index_contract(chain_id, confirmed_block, toy_nft_address)
+                .await?;
+        }
+
+        Ok(behind)
+    }
+
+    async fn index_contract(
+        &self,
+        chain_id: i64,
+        confirmed_block: i64,
+        contract_address: &str,
+    ) -> Result<bool, String> {
+        let cursor = self
+            .db
+            .send(GetChainCursor {
+                chain_id,
+                contract_address: contract_address.to_string(),
+            })
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+
+        if let Some(ChainCursor {
+            block_number,
+            block_hash: Some(ref block_hash),
+            ..
+        }) = cursor
+        {
+            if self.rpc.block_hash(block_number).await?.as_ref() != Some(block_hash) {
+                let from_block = (block_number - self.confirmations).max(self.start_block);
+                log::warn!(
+                    "block {} was reorganised away, indexing {} again from block {}",
+                    block_number,
+                    contract_address,
+                    from_block
+                );
+                self.db
+                    .send(RewindIndexedLogs {
+                        chain_id,
+                        contract_address: contract_address.to_string(),
+                        from_block,
+                    })
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .map_err(|e| e.to_string())?;
+                return Ok(true);
+            }
+        }
+
+        let from_block = cursor
+            .map(|cursor| cursor.block_number + 1)
+            .unwrap_or(self.start_block);
+        if from_block > confirmed_block {
+            return Ok(false);
+        }
+        let to_block = confirmed_block.min(from_block + self.batch_size - 1);
+
+        let (events, mints) = if contract_address == self.contract_address {
+            let topics: Vec<String> = MARKETPLACE_EVENTS
+                .iter()
+                .map(|event| event.topic.clone())
+                .collect();
+            let logs = self
+                .rpc
+                .logs(contract_address, &topics, from_block, to_block)
+                .await?;
+            let events = logs
+                .iter()
+                .filter(|log| !log.removed)
+                .map(|log| decode_log(chain_id, log))
+                .collect::<Result<Vec<NewMarketplaceEvent>, String>>()?;
+            (events, Vec::new())
+        } else {
+            let logs = self
+                .rpc
+                .logs(
+                    contract_address,
+                    std::slice::from_ref(&TRANSFER_EVENT.topic),
+                    from_block,
+                    to_block,
+                )
+                .await?;
+            (Vec::new(), self.decode_mints(chain_id, &logs).await?)
+        };
+
+        let block_hash = self.rpc.block_hash(to_block).await?;
+        let stored = self
+            .db
+            .send(StoreIndexedLogs {
+                chain_id,
+                contract_address: contract_address.to_string(),
+                events,
+                mints,
+                block_number: to_block,
+                block_hash,
+            })
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+
+        if stored > 0 {
+            log::info!(
+                "indexed {} logs of {} up to block {}",
+                stored,
+                contract_address,
+                to_block
+            );
+        }
+
+        Ok(to_block < confirmed_block)
+    }
+
+    // the mints among the transfers whose token URI is the metadata url of a lot
+    async fn decode_mints(&self, chain_id: i64, logs: &[Log]) -> Result<Vec<NewLotMint>, String> {
+        let mut mints = Vec::new();
+
+        for log in logs.iter().filter(|log| !log.removed) {
+            let values = TRANSFER_EVENT.decode(&log.topics, &log.data)?;
+            let (
+                Some(AbiValue::Address(from)),
+                Some(AbiValue::Address(to)),
+                Some(AbiValue::Uint(token_id)),
+            ) = (values.get("from"), values.get("to"), values.get("tokenId"))
+            else {
+                return Err(format!("log {} is not a Transfer", log.transaction_hash));
+            };
+            if from != ZERO_ADDRESS {
+                continue;
+            }
+
+            // at the block of the mint, the token may be burned since
+            // anyone can mint with any URI, so one that cannot be read is not a lot's and must
+            // not hold up the logs after it
+            let block_number = parse_quantity(&log.block_number)?;
+            let call = abi::encode_call("tokenURI(uint256)", &[AbiValue::Uint(token_id.clone())])?;
+            let uri = self
+                .rpc
+                .eth_call(&log.address, &call, block_number)
+                .await
+                .and_then(|data| abi::decode_string(&data));
+            let uri = match uri {
+                Ok(uri) => uri,
+                Err(e) => {
+                    log::warn!(
+                        "skipping the mint of token {} of {}, its token URI cannot be read: {}",
+                        token_id,
+                        log.address,
+                        e
+                    );
+                    continue;
+                }
+            };
+            let Some(lot_id) = lot_id_from_metadata_url(&uri) else {
+                continue;
+            };
+
+            mints.push(NewLotMint {
+                lot_id,
+                chain_id,
+                nft_address: log.address.to_lowercase(),
+                token_id: token_id.clone(),
+                recipient: to.clone(),
+                block_number,
+                block_hash: log.block_hash.to_lowercase(),
+                tx_hash: log.transaction_hash.to_lowercase(),
+                log_index: parse_quantity(&log.log_index)? as i32,
+            });
+        }
+
+        Ok(mints)
+    }
+}
+
+fn decode_log(chain_id: i64, log: &Log) -> Result<NewMarketplaceEvent, String> {
+    let topic = log.topics.first().map(|topic| topic.to_lowercase());
+    let event = MARKETPLACE_EVENTS
+        .iter()
+        .find(|event| Some(&event.topic) == topic.as_ref())
+        .ok_or_else(|| {
+            format!(
+                "log {} has no Marketplace event topic",
+                log.transaction_hash
+            )
+        })?;
+    let kind = MarketplaceEventKind::parse(&event.name)
+        .ok_or_else(|| format!("{} is not a known Marketplace event", event.name))?;
+
+    let values = event.decode(&log.topics, &log.data)?;
+    let address = |name: &str| match values.get(name) {
+        Some(AbiValue::Address(address)) => Ok(address.clone()),
+        _ => Err(format!("{} has no address {}", event.name, name)),
+    };
+    let uint = |name: &str| match values.get(name) {
+        Some(AbiValue::Uint(value)) => Ok(value.clone()),
+        _ => Err(format!("{} has no uint {}", event.name, name)),
+    };
+
+    let (account, price) = match kind {
+        MarketplaceEventKind::Listed => (address("seller")?, Some(uint("price")?)),
+        MarketplaceEventKind::Canceled => (address("seller")?, None),
+        MarketplaceEventKind::Bought => (address("buyer")?, Some(uint("price")?)),
+    };
+
+    Ok(NewMarketplaceEvent {
+        chain_id,
+        contract_address: log.address.to_lowercase(),
+        block_number: parse_quantity(&log.block_number)?,
+        block_hash: log.block_hash.to_lowercase(),
+        tx_hash: log.transaction_hash.to_lowercase(),
+        log_index: parse_quantity(&log.log_index)? as i32,
+        event: kind.as_str().to_string(),
+        account,
+        nft_address: address("nftAddress")?,
+        token_id: uint("tokenId")?,
+        price,
+        // linked to the lot of the NFT when stored
+        lot_id: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_log, Log, TRANSFER_EVENT, ZERO_ADDRESS};
+    use crate::utils::abi::AbiValue;
+    use bigdecimal::BigDecimal;
+
+    const MARKETPLACE: &str = "0x5fbdb2315678afecb367f032d93f642f64180aa3";
+    const NFT: &str = "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512";
+    const SELLER: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+    const BUYER: &str = "0x70997970c51812dc3a010c7d01b50e0d17dc79c8";
+
+    const LISTED: &str = "0xd547e933094f12a9159076970143ebe73234e64480317844b0dcb36117116de4";
+    const CANCELED: &str = "0x9ba1a3cb55ce8d63d072a886f94d2a744f50cddf82128e897d0661f5ec623158";
+    const BOUGHT: &str = "0x263223b1dd81e51054a4e6f791d45a4a1ddb4aadcd93a2dfd892615c3fdac187";
+    const TRANSFER: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+    fn topic(value: &str) -> String {
+        format!("0x{:0>64}", value.trim_start_matches("0x"))
+    }
+
+    // a log as anvil returns it from eth_getLogs
+    fn log(address: &str, topics: &[String], data: &str) -> Log {
+        serde_json::from_value(json!({
+            "address": address,
+            "topics": topics,
+            "data": data,
+            "blockNumber": "0x1a",
+            "blockHash": "0xB1C3AD93D3A8D8F4E1B64D0A1D7C4B7E0A6E1E7A8E9F3C2B1A0D9C8B7A6F5E4D",
+            "transactionHash": "0xA3B9E5F1C7D2E8F4A0B6C2D8E4F0A6B2C8D4E0F6A2B8C4D0E6F2A8B4C0D6E2F8",
+            "transactionIndex": "0x0",
+            "logIndex": "0x2",
+            "removed": false
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn marketplace_logs_are_decoded_into_events() {
+        let price = topic("de0b6b3a7640000");
+
+        let listed = decode_log(
+            31337,
+            &log(
+                MARKETPLACE,
+                &[LISTED.to_string(), topic(SELLER), topic(NFT), topic("3")],
+                &price,
+            ),
+        )
+        .unwrap();
+        assert_eq!(listed.chain_id, 31337);
+        assert_eq!(listed.contract_address, MARKETPLACE);
+        assert_eq!(listed.block_number, 26);
+        assert_eq!(listed.log_index, 2);
+        assert_eq!(
+            listed.block_hash,
+            "0xb1c3ad93d3a8d8f4e1b64d0a1d7c4b7e0a6e1e7a8e9f3c2b1a0d9c8b7a6f5e4d"
+        );
+        assert_eq!(
+            listed.tx_hash,
+            "0xa3b9e5f1c7d2e8f4a0b6c2d8e4f0a6b2c8d4e0f6a2b8c4d0e6f2a8b4c0d6e2f8"
+        );
+        assert_eq!(listed.event, "ItemListed");
+        assert_eq!(listed.account, SELLER);
+        assert_eq!(listed.nft_address, NFT);
+        assert_eq!(listed.token_id, BigDecimal::from(3));
+        assert_eq!(
+            listed.price,
+            Some(BigDecimal::from(1_000_000_000_000_000_000u64))
+        );
+        assert_eq!(listed.lot_id, None);
+
+        let canceled = decode_log(
+            31337,
+            &log(
+                MARKETPLACE,
+                &[CANCELED.to_string(), topic(SELLER), topic(NFT), topic("3")],
+                "0x",
+            ),
+        )
+        .unwrap();
+        assert_eq!(canceled.event, "ItemCanceled");
+        assert_eq!(canceled.account, SELLER);
+        assert_eq!(canceled.price, None);
+
+        // the account of a purchase is the buyer's
+        let bought = decode_log(
+            31337,
+            &log(
+                MARKETPLACE,
+                &[BOUGHT.to_string(), topic(BUYER), topic(NFT), topic("3")],
+                &price,
+            ),
+        )
+        .unwrap();
+        assert_eq!(bought.event, "ItemBought");
+        assert_eq!(bought.account, BUYER);
+        assert_eq!(
+            bought.price,
+            Some(BigDecimal::from(1_000_000_000_000_000_000u64))
+        );
+    }
+
+    #[test]
+    fn logs_of_other_events_are_not_marketplace_events() {
+        let transfer = log(
+            NFT,
+            &[
+                TRANSFER.to_string(),
+                topic(ZERO_ADDRESS),
+                topic(SELLER),
+                topic("3"),
+            ],
+            "0x",
+        );
+        assert!(decode_log(31337, &transfer).is_err());
+
+        // a listing missing its price
+        let listed = log(
+            MARKETPLACE,
+            &[LISTED.to_string(), topic(SELLER), topic(NFT), topic("3")],
+            "0x",
+        );
+        assert!(decode_log(31337, &listed).is_err());
+    }
+
+    #[test]
+    fn transfer_logs_are_decoded() {
+        let values = TRANSFER_EVENT
+            .decode(
+                &[
+                    TRANSFER.to_string(),
+                    topic(ZERO_ADDRESS),
+                    topic(SELLER),
+                    topic("3"),
+                ],
+                "0x",
+            )
+            .unwrap();
+        assert_eq!(values["from"], AbiValue::Address(ZERO_ADDRESS.to_string()));
+        assert_eq!(values["to"], AbiValue::Address(SELLER.to_string()));
+        assert_eq!(values["tokenId"], AbiValue::Uint(BigDecimal::from(3)));
+    }
+}
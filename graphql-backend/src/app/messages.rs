@@ -0,0 +1,94 @@
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::app::profiles::ProfileResponseInner;
+use crate::utils::{auth::Auth, CustomDateTime};
+
+// Client Messages ↓
+
+// replies in a conversation, or messages a user, optionally about a lot,
+// in which case the recipient defaults to the seller of the lot
+#[derive(async_graphql::InputObject, Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendMessage {
+    pub conversation_id: Option<String>,
+    pub recipient: Option<String>, // <- username
+    pub lot_id: Option<String>,
+    #[validate(length(min = 1, max = 5000, message = "must be between 1 and 5000 characters"))]
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct SendMessageAuthenticated {
+    pub auth: Auth,
+    pub conversation_id: Option<Uuid>,
+    pub recipient: Option<String>,
+    pub lot_id: Option<Uuid>,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct GetConversations {
+    pub auth: Auth,
+}
+
+#[derive(Debug)]
+pub struct GetMessages {
+    pub auth: Auth,
+    pub conversation_id: Uuid,
+    // id of the oldest message loaded so far
+    pub cursor: Option<Uuid>,
+    pub first: Option<i32>, // <- if not set, is 30
+}
+
+// marks the messages the other participant sent as read
+#[derive(Debug)]
+pub struct MarkConversationRead {
+    pub auth: Auth,
+    pub conversation_id: Uuid,
+}
+
+#[derive(Debug)]
+pub struct BlockUser {
+    pub auth: Auth,
+    pub username: String,
+}
+
+#[derive(Debug)]
+pub struct UnblockUser {
+    pub auth: Auth,
+    pub username: String,
+}
+
+#[derive(Debug)]
+pub struct GetBlockedUsers {
+    pub auth: Auth,
+}
+
+// JSON response objects ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageResponse {
+    pub id: String,
+    pub conversation_id: String,
+    pub body: String,
+    pub sender: ProfileResponseInner,
+    // sent by the authenticated user
+    pub mine: bool,
+    pub read_at: Option<CustomDateTime>,
+    pub created_at: CustomDateTime,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationResponse {
+    pub id: String,
+    pub lot_id: Option<String>,
+    // the other participant
+    pub participant: ProfileResponseInner,
+    pub last_message: Option<MessageResponse>,
+    pub unread_count: i64,
+    pub last_message_at: CustomDateTime,
+    pub created_at: CustomDateTime,
+}
@@ -1,54 +1,170 @@
+pub mod admin;
+pub mod alerts;
 pub mod articles;
+pub mod catalog;
+pub mod exchange_rates;
 mod mutation;
 pub mod profiles;
 mod query;
 pub mod tags;
 pub mod users;
 pub mod lots;
+pub mod marketplace;
+pub mod messages;
+pub mod nft;
+pub mod notifications;
+pub mod orders;
+pub mod reviews;
+pub mod wallets;
+pub mod watchlists;
 
 use crate::{
-    db::{new_pool, DbExecutor},
-    utils::auth::Token,
+    config::{config, CorsConfig},
+    db::{new_pool, pending_migrations, run_pending_migrations, Db, PgPool, Ping},
+    error::Error,
+    utils::{
+        abi::to_hex,
+        auth::{Fingerprint, Token, Viewer},
+        eth_rpc::EthRpc,
+        mailer::LogMailer,
+        metrics, telemetry,
+    },
 };
-use actix::prelude::{Addr, SyncArbiter};
+use actix::prelude::Actor;
+use alerts::AlertMatcher;
+use exchange_rates::ExchangeRateImporter;
+use lots::{LotPurger, TrendingRefresher};
+use profiles::ProfileLoader;
+use marketplace::MarketplaceIndexer;
+use notifications::DigestMailer;
 use actix_cors::Cors;
 use actix_http::header::HeaderMap;
 use actix_web::{
     guard,
-    http::header::{AUTHORIZATION, CONTENT_TYPE},
-    middleware::Logger,
+    http::header::{AUTHORIZATION, CONTENT_TYPE, USER_AGENT, X_FORWARDED_FOR},
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::{from_fn, Logger, Next},
     web,
     web::Data,
-    App, HttpRequest, HttpResponse, HttpServer, Result,
+    App, HttpRequest, HttpResponse, HttpServer, Responder, Result,
+};
+use async_graphql::{
+    dataloader::DataLoader, extensions::Tracing, http::GraphiQLSource, EmptySubscription, Schema,
 };
-use async_graphql::{http::GraphiQLSource, EmptySubscription, Schema};
 use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use diesel::RunQueryDsl;
+use regex::Regex;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use mutation::MutationRoot;
+use nft::GetLotMetadata;
 use query::QueryRoot;
-use std::env;
+use sha3::{Digest, Keccak256};
+use std::io;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
 
 pub type GraphqlSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
 
+static REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+static TOKEN: HeaderName = HeaderName::from_static("token");
+static TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+
+// how long readyz waits on a db executor before calling the mailbox stuck
+const READY_TIMEOUT: Duration = Duration::from_secs(2);
+
 pub struct AppState {
-    pub db: Addr<DbExecutor>,
+    pub db: Db,
+}
+
+// the schema with the db executors to resolve against, and the limits of the config
+pub fn build_schema(db: Db) -> GraphqlSchema {
+    let limits = &config().limits;
+    let mut schema = Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(DataLoader::new(ProfileLoader { db: db.clone() }, actix_rt::spawn))
+        .data(AppState { db })
+        .extension(metrics::GraphqlMetrics)
+        .extension(Tracing);
+    if let Some(depth) = limits.max_query_depth {
+        schema = schema.limit_depth(depth);
+    }
+    if let Some(complexity) = limits.max_query_complexity {
+        schema = schema.limit_complexity(complexity);
+    }
+    schema.finish()
 }
 
 fn get_token_from_headers(headers: &HeaderMap) -> Option<Token> {
     headers
-        .get("Token")
+        .get(&TOKEN)
         .and_then(|value| value.to_str().map(|s| Token(s.to_string())).ok())
 }
 
+// the client address without the port, which changes with every connection, and its user agent
+fn get_fingerprint(req: &HttpRequest) -> Option<Fingerprint> {
+    let address = client_ip(
+        req.peer_addr()?.ip(),
+        req.headers(),
+        &config().server.trusted_proxies,
+    );
+    let user_agent = req
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    let hash = Keccak256::digest(format!("{} {}", address, user_agent).as_bytes());
+    Some(Fingerprint(to_hex(&hash)))
+}
+
+// the address of the client, which is the peer unless that is a trusted proxy
+// proxies append the address they were reached from to X-Forwarded-For, so it is read from the
+// right for as long as the hops are trusted, anything before that the client could have sent
+fn client_ip(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[String]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|proxy| proxy.parse() == Ok(*ip));
+
+    let forwarded: Vec<&str> = headers
+        .get_all(X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+
+    let mut ip = peer;
+    for hop in forwarded.into_iter().rev() {
+        if !is_trusted(&ip) {
+            break;
+        }
+        match hop.trim().parse::<IpAddr>() {
+            Ok(hop) => ip = hop,
+            Err(_) => break,
+        }
+    }
+    ip
+}
+
 async fn index(
     schema: web::Data<GraphqlSchema>,
     req: HttpRequest,
     gql_request: GraphQLRequest,
-) -> GraphQLResponse {
-    let mut request = gql_request.into_inner();
+) -> HttpResponse {
+    let mut request = gql_request.into_inner().data(Viewer::default());
     if let Some(token) = get_token_from_headers(req.headers()) {
         request = request.data(token);
     }
-    schema.execute(request).await.into()
+    if let Some(fingerprint) = get_fingerprint(&req) {
+        request = request.data(fingerprint);
+    }
+    let response: GraphQLResponse = schema.execute(request).await.into();
+    // async-graphql 7 answers with application/graphql-response+json, clients expect plain json
+    let mut response = response.respond_to(&req);
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
 }
 
 async fn index_graphiql() -> Result<HttpResponse> {
@@ -57,55 +173,315 @@ async fn index_graphiql() -> Result<HttpResponse> {
         .body(GraphiQLSource::build().endpoint("/").finish()))
 }
 
+// the ERC-721 metadata of a lot, the token URI of the ToyNFT token minted for it
+async fn lot_metadata(state: Data<AppState>, lot_id: web::Path<String>) -> HttpResponse {
+    let Ok(lot_id) = lot_id.parse::<Uuid>() else {
+        return HttpResponse::NotFound().json(json!({ "error": "requested record was not found" }));
+    };
+
+    match state.db.send(GetLotMetadata { lot_id }).await {
+        Ok(Ok(metadata)) => HttpResponse::Ok().json(metadata),
+        Ok(Err(Error::NotFound(error))) => HttpResponse::NotFound().json(error),
+        _ => HttpResponse::InternalServerError().json("Internal Server Error"),
+    }
+}
+
+// the frontends listed in the config can call the API from a browser, with the headers
+// it reads and the request id it returns
+// any origin is only allowed with the development flag, which validation keeps apart
+// from credentials
+fn cors(config: &CorsConfig, origin_patterns: Arc<Vec<Regex>>) -> Cors {
+    let mut cors = Cors::default()
+        .allowed_methods(vec!["GET", "POST"])
+        .allowed_headers(vec![
+            CONTENT_TYPE,
+            AUTHORIZATION,
+            TOKEN.clone(),
+            REQUEST_ID.clone(),
+            TRACEPARENT.clone(),
+        ])
+        .expose_headers(vec![REQUEST_ID.clone()])
+        .max_age(3600);
+
+    if config.allow_any_origin {
+        return cors.allow_any_origin().send_wildcard();
+    }
+    for origin in config.origins.iter().filter(|origin| *origin != "*") {
+        cors = cors.allowed_origin(origin);
+    }
+    if !origin_patterns.is_empty() {
+        cors = cors.allowed_origin_fn(move |origin, _| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| origin_patterns.iter().any(|pattern| pattern.is_match(origin)))
+        });
+    }
+    if config.allow_credentials {
+        cors = cors.supports_credentials();
+    }
+    cors
+}
+
+// every request runs in a span with its request id, taken from the X-Request-Id header
+// or generated, and returned in the response to find the request by in the traces
+// a traceparent header makes the span part of the trace of the caller
+async fn request_span(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>> {
+    let request_id = req
+        .headers()
+        .get(&REQUEST_ID)
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let span = tracing::info_span!(
+        "http request",
+        http.method = %req.method(),
+        http.target = %req.path(),
+        http.status_code = tracing::field::Empty,
+        request_id = %request_id,
+        otel.kind = "server",
+    );
+    span.set_parent(telemetry::parent_context(req.headers()));
+
+    let mut res = next.call(req).instrument(span.clone()).await?;
+    span.record("http.status_code", res.status().as_u16());
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut().insert(REQUEST_ID.clone(), value);
+    }
+
+    Ok(res)
+}
+
+// the process is up and answering
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+// the database answers a query and the db executors keep up with their mailbox,
+// the load balancer sends no requests while this is failing
+async fn readyz(state: Data<AppState>, pool: Data<PgPool>) -> HttpResponse {
+    let database = web::block(move || -> Result<(), String> {
+        let conn = &mut pool.get().map_err(|e| e.to_string())?;
+        diesel::sql_query("SELECT 1")
+            .execute(conn)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|res| res);
+
+    let mailbox = match actix_rt::time::timeout(READY_TIMEOUT, state.db.send(Ping)).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!(
+            "no db executor got to a message within {:?}, {} are waiting",
+            READY_TIMEOUT,
+            state.db.mailbox_depth()
+        )),
+    };
+
+    let check = |res: &Result<(), String>| match res {
+        Ok(()) => json!("ok"),
+        Err(e) => json!(e),
+    };
+    let body = json!({
+        "database": check(&database),
+        "dbExecutor": check(&mailbox),
+    });
+    if database.is_ok() && mailbox.is_ok() {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+async fn render_metrics(state: Data<AppState>, pool: Data<PgPool>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render(&pool, &state.db))
+}
+
 #[actix_web::main]
-pub async fn start_server() -> std::io::Result<()> {
+pub async fn start_server(migrate: bool) -> std::io::Result<()> {
+    let config = config();
 
-    // set vars from .env file 
-    let frontend_origin = env::var("FRONTEND_ORIGIN").ok();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let bind_address = env::var("BIND_ADDRESS").expect("BIND_ADDRESS is not set");
+    let database_pool = new_pool(config.database.url.clone(), config.database.pool_size)
+        .expect("Failed to create pool.");
 
-    let database_pool = new_pool(database_url).expect("Failed to create pool.");
-    let database_address =
-        SyncArbiter::start(num_cpus::get(), move || DbExecutor(database_pool.clone()));
+    // queries against a schema that is behind fail at random later on, so the server
+    // does not start until the migrations are applied, with `migrate` or `--migrate`
+    if migrate {
+        for version in run_pending_migrations(&database_pool).map_err(io::Error::other)? {
+            log::info!("applied migration {}", version);
+        }
+    } else {
+        let pending = pending_migrations(&database_pool).map_err(io::Error::other)?;
+        if !pending.is_empty() {
+            return Err(io::Error::other(format!(
+                "the database is missing the migrations {}, apply them with `graphql-backend migrate` or start with `serve --migrate`",
+                pending.join(", ")
+            )));
+        }
+    }
+    let database_address = Db::start(config.database.executors, database_pool.clone());
 
-    log::info!("GraphiQL IDE: {}", bind_address);
-    HttpServer::new(move || {
-        let state = AppState {
+    if config.alerts.enabled {
+        AlertMatcher {
+            db: database_address.clone(),
+            interval: config.alerts.interval(),
+        }
+        .start();
+    }
+
+    if config.digests.enabled {
+        DigestMailer {
             db: database_address.clone(),
-        };
-
-        // allow wildcard for development purposes
-        let cors = match frontend_origin {
-            // TODO production should not be allowed to send wildcard
-            Some(ref origin) if origin != "*" => Cors::default()
-                .allowed_origin(origin)
-                .allowed_headers(vec![AUTHORIZATION, CONTENT_TYPE])
-                .max_age(3600),
-            _ => Cors::default()
-                .send_wildcard()
-                .allow_any_origin()
-                .allow_any_method()
-                .allow_any_header()
-                .max_age(3600),
-        };
-
-        let schema = Schema::build(QueryRoot, MutationRoot, EmptySubscription)
-            .data(state)
-            .finish();
+            mailer: Arc::new(LogMailer),
+            interval: config.digests.interval(),
+        }
+        .start();
+    }
+
+    if config.trash.purge_enabled {
+        LotPurger {
+            db: database_address.clone(),
+            days: config.trash.retention_days,
+            interval: config.trash.purge_interval(),
+        }
+        .start();
+    }
+
+    if config.trending.refresh_enabled {
+        TrendingRefresher {
+            db: database_address.clone(),
+            interval: config.trending.refresh_interval(),
+        }
+        .start();
+    }
+
+    let exchange_rates = &config.exchange_rates;
+    if let Some(ref url) = exchange_rates.url {
+        log::info!("importing exchange rates from {}", url);
+        ExchangeRateImporter {
+            db: database_address.clone(),
+            url: url.clone(),
+            interval: Duration::from_secs(exchange_rates.interval_seconds),
+        }
+        .start();
+    }
+
+    // the marketplace indexer only runs with both a node and a contract to index
+    let marketplace = &config.marketplace;
+    if let (Some(rpc_url), Some(contract_address)) = (&marketplace.rpc_url, &marketplace.address) {
+        log::info!("indexing marketplace events of {} from {}", contract_address, rpc_url);
+        MarketplaceIndexer {
+            db: database_address.clone(),
+            rpc: EthRpc::new(rpc_url.clone()),
+            contract_address: contract_address.clone(),
+            toy_nft_address: nft::toy_nft_address(),
+            start_block: marketplace.start_block,
+            confirmations: marketplace.confirmations,
+            batch_size: marketplace.batch_size,
+            interval: Duration::from_secs(marketplace.interval_seconds),
+        }
+        .start();
+    }
+
+    let db = database_address.clone();
+    let origin_patterns = Arc::new(config.cors.origin_regexes());
+    if config.server.graphiql {
+        log::info!("GraphiQL IDE: {}", config.server.bind_address);
+    }
+    HttpServer::new(move || {
+        let schema = build_schema(database_address.clone());
 
         App::new()
-            .app_data(Data::new(schema.clone()))
-            .wrap(Logger::default())
-            .wrap(cors)
+            .app_data(Data::new(schema))
+            .app_data(Data::new(AppState {
+                db: database_address.clone(),
+            }))
+            .app_data(Data::new(database_pool.clone()))
+            .wrap(from_fn(request_span))
+            // the default format, with the request id
+            .wrap(Logger::new(
+                r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}o"#,
+            ))
+            .wrap(cors(&config.cors, origin_patterns.clone()))
             .configure(routes)
     })
-    .bind(&bind_address)?
+    .workers(config.server.workers)
+    // on SIGTERM or SIGINT the server stops accepting connections
+    // and gives the requests in flight this long to finish
+    .shutdown_timeout(config.server.shutdown_timeout_seconds)
+    .bind(&config.server.bind_address)?
     .run()
-    .await
+    .await?;
+
+    // the requests are done, the messages they and the background jobs sent get to finish
+    let unfinished = db.drain(config.server.shutdown_timeout()).await;
+    if unfinished > 0 {
+        log::warn!("stopping with {} db messages unfinished", unfinished);
+    }
+    Ok(())
 }
 
 fn routes(app: &mut web::ServiceConfig) {
-    app.service(web::resource("/").guard(guard::Post()).to(index))
-        .service(web::resource("/").guard(guard::Get()).to(index_graphiql));
+    app.service(web::resource("/").guard(guard::Post()).to(index));
+    if config().server.graphiql {
+        app.service(web::resource("/").guard(guard::Get()).to(index_graphiql));
+    }
+    app
+        .service(
+            web::resource("/metadata/lots/{lot_id}")
+                .guard(guard::Get())
+                .to(lot_metadata),
+        )
+        .service(web::resource("/healthz").guard(guard::Get()).to(healthz))
+        .service(web::resource("/readyz").guard(guard::Get()).to(readyz))
+        .service(web::resource("/metrics").guard(guard::Get()).to(render_metrics));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::client_ip;
+    use actix_http::header::{HeaderMap, HeaderValue, X_FORWARDED_FOR};
+    use std::net::IpAddr;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    fn forwarded(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(X_FORWARDED_FOR, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn the_forwarded_address_is_only_taken_from_trusted_proxies() {
+        let proxies = ["10.0.0.1".to_string(), "10.0.0.2".to_string()];
+        let spoofed = forwarded(&["6.6.6.6, 1.2.3.4"]);
+
+        // a client connecting directly cannot pick its address
+        assert_eq!(client_ip(ip("1.2.3.4"), &spoofed, &proxies), ip("1.2.3.4"));
+        assert_eq!(client_ip(ip("1.2.3.4"), &spoofed, &[]), ip("1.2.3.4"));
+        // the hop a trusted proxy appended is the client, what came before is the client's own
+        assert_eq!(client_ip(ip("10.0.0.1"), &spoofed, &proxies), ip("1.2.3.4"));
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &forwarded(&["6.6.6.6", "1.2.3.4, 10.0.0.2"]), &proxies),
+            ip("1.2.3.4")
+        );
+        assert_eq!(client_ip(ip("10.0.0.1"), &forwarded(&[]), &proxies), ip("10.0.0.1"));
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), &forwarded(&["nonsense"]), &proxies),
+            ip("10.0.0.1")
+        );
+    }
 }
@@ -9,7 +9,7 @@ use crate::{
         AppState,
     },
     error::validation_errors_to_error,
-    utils::auth::authenticate_token, models::LotWithImages,
+    utils::auth::authenticate_token, models::{LotWithImages, SavedSearch},
 };
 
 use super::{
@@ -18,12 +18,33 @@ use super::{
         ArticleResponse, CreateArticle, CreateArticleOuter, DeleteArticle, FavoriteArticle,
         UnfavoriteArticle, UpdateArticle, UpdateArticleOuter,
     },
+    catalog::{ImportCatalog, ImportCatalogAuthenticated, ImportCatalogResponse},
+    exchange_rates::{ImportExchangeRatesAuthenticated, ImportExchangeRatesResponse},
     lots::{
-        CreateLot, CreateLotAuthenticated, DeleteLotAuthenticated, UpdateLot,
+        CreateLot, CreateLotAuthenticated, DeleteLotAuthenticated, ImportLots,
+        ImportLotsAuthenticated, ImportLotsResponse, RestoreLotAuthenticated, UpdateLot,
         UpdateLotAuthenticated,
     },
+    messages::{
+        BlockUser, MarkConversationRead, MessageResponse, SendMessage, SendMessageAuthenticated,
+        UnblockUser,
+    },
+    nft::{PrepareMint, PreparedMint},
+    notifications::{
+        MarkNotificationsRead, NotificationPreference, NotificationPreferenceInput,
+        UpdateNotificationPreferences,
+    },
+    orders::{
+        OrderResponse, PlaceOrder, PlaceOrderAuthenticated, UpdateOrderState,
+        UpdateOrderStateAuthenticated,
+    },
     profiles::{FollowProfile, ProfileResponse, UnfollowProfile},
+    reviews::{ReviewOrder, ReviewOrderAuthenticated, ReviewResponse},
     users::ForgotPassword,
+    wallets::{LinkWallet, SiweSignin, WalletResponse},
+    watchlists::{
+        DeleteSavedSearch, SaveSearch, SaveSearchAuthenticated, UnwatchLot, WatchLot,
+    },
 };
 pub struct MutationRoot;
 
@@ -258,7 +279,8 @@ impl MutationRoot {
         let res = state
             .db
             .send(CreateLotAuthenticated { auth, lot: params })
-            .await??;
+            .await?
+            .map_err(|e| e.extend())?;
 
         Ok(res)
     }
@@ -273,20 +295,20 @@ impl MutationRoot {
             .validate()
             .map_err(|e| validation_errors_to_error(e).extend())?;
 
+        let lot = crate::models::UpdateLot::try_from(params).map_err(|e| e.extend())?;
+
         let state = ctx.data_unchecked::<AppState>();
         let auth = authenticate_token(state, ctx).await?;
         let res = state
             .db
-            .send(UpdateLotAuthenticated {
-                auth,
-                lot: params.into(),
-            })
-            .await??;
+            .send(UpdateLotAuthenticated { auth, lot })
+            .await?
+            .map_err(|e| e.extend())?;
 
         Ok(res)
     }
 
-    // delete lot
+    // move a lot to the trash, where it stays restorable until it is purged
     async fn delete_lot<'ctx>(&self, ctx: &Context<'ctx>, lot_id: String) -> Result<usize> {
         let lot_id = lot_id.parse::<Uuid>()?;
         let state = ctx.data_unchecked::<AppState>();
@@ -294,8 +316,374 @@ impl MutationRoot {
         let res = state
             .db
             .send(DeleteLotAuthenticated { auth, lot_id })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // take a lot out of the trash with the status it had
+    async fn restore_lot<'ctx>(&self, ctx: &Context<'ctx>, lot_id: String) -> Result<LotWithImages> {
+        let lot_id = lot_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(RestoreLotAuthenticated { auth, lot_id })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // create lots in bulk from a CSV or JSON lines file, all of them or none
+    async fn import_lots<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: ImportLots,
+    ) -> Result<ImportLotsResponse> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(ImportLotsAuthenticated { auth, params })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // import catalog items from a Rebrickable or Bricklink dump, admins only
+    async fn import_catalog<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: ImportCatalog,
+    ) -> Result<ImportCatalogResponse> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(ImportCatalogAuthenticated { auth, params })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // import exchange rate snapshots, a JSON snapshot or a list of them, admins only
+    async fn import_exchange_rates<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        snapshots: String,
+    ) -> Result<ImportExchangeRatesResponse> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(ImportExchangeRatesAuthenticated { auth, snapshots })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // watch a lot for sale to be notified when its price or status changes
+    async fn watch_lot<'ctx>(&self, ctx: &Context<'ctx>, lot_id: String) -> Result<bool> {
+        let lot_id = lot_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(WatchLot { auth, lot_id })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // stop watching a lot
+    async fn unwatch_lot<'ctx>(&self, ctx: &Context<'ctx>, lot_id: String) -> Result<bool> {
+        let lot_id = lot_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(UnwatchLot { auth, lot_id }).await??;
+
+        Ok(res)
+    }
+
+    // save lot filter params to be notified of new lots for sale that match them
+    async fn save_search<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: SaveSearch,
+    ) -> Result<SavedSearch> {
+        params
+            .validate()
+            .map_err(|e| validation_errors_to_error(e).extend())?;
+
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(SaveSearchAuthenticated {
+                auth,
+                search: params,
+            })
+            .await??;
+
+        Ok(res)
+    }
+
+    // delete a saved search
+    async fn delete_saved_search<'ctx>(&self, ctx: &Context<'ctx>, id: String) -> Result<bool> {
+        let id = id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(DeleteSavedSearch { auth, id }).await??;
+
+        Ok(res)
+    }
+
+    // mark notifications read, all of them when no ids are given
+    async fn mark_notifications_read<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        ids: Option<Vec<String>>,
+    ) -> Result<usize> {
+        let ids = ids
+            .map(|ids| ids.iter().map(|id| id.parse::<Uuid>()).collect::<Result<Vec<Uuid>, _>>())
+            .transpose()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(MarkNotificationsRead { auth, ids }).await??;
+
+        Ok(res)
+    }
+
+    // set how notifications of the given kinds are delivered
+    async fn update_notification_preferences<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        preferences: Vec<NotificationPreferenceInput>,
+    ) -> Result<Vec<NotificationPreference>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(UpdateNotificationPreferences { auth, preferences })
+            .await??;
+
+        Ok(res)
+    }
+
+    // send a private message, see SendMessage for how the conversation is picked
+    async fn send_message<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: SendMessage,
+    ) -> Result<MessageResponse> {
+        params
+            .validate()
+            .map_err(|e| validation_errors_to_error(e).extend())?;
+
+        let conversation_id = params
+            .conversation_id
+            .map(|id| id.parse::<Uuid>())
+            .transpose()?;
+        let lot_id = params.lot_id.map(|id| id.parse::<Uuid>()).transpose()?;
+
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(SendMessageAuthenticated {
+                auth,
+                conversation_id,
+                recipient: params.recipient,
+                lot_id,
+                body: params.body,
+            })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // mark the messages received in a conversation as read
+    async fn mark_conversation_read<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        conversation_id: String,
+    ) -> Result<usize> {
+        let conversation_id = conversation_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(MarkConversationRead {
+                auth,
+                conversation_id,
+            })
             .await??;
 
         Ok(res)
     }
+
+    // stop messages to and from a user
+    async fn block_user<'ctx>(&self, ctx: &Context<'ctx>, username: String) -> Result<bool> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(BlockUser { auth, username })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // allow messages with a blocked user again
+    async fn unblock_user<'ctx>(&self, ctx: &Context<'ctx>, username: String) -> Result<bool> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(UnblockUser { auth, username }).await??;
+
+        Ok(res)
+    }
+
+    // buy a lot for sale at its asking price, which puts the lot on pending sale
+    async fn place_order<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: PlaceOrder,
+    ) -> Result<OrderResponse> {
+        params
+            .shipping_address
+            .validate()
+            .map_err(|e| validation_errors_to_error(e).extend())?;
+
+        let lot_id = params.lot_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(PlaceOrderAuthenticated {
+                auth,
+                lot_id,
+                shipping_address: params.shipping_address,
+            })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // move an order along, the status of its lot follows
+    async fn update_order_state<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: UpdateOrderState,
+    ) -> Result<OrderResponse> {
+        params
+            .validate()
+            .map_err(|e| validation_errors_to_error(e).extend())?;
+
+        let order_id = params.order_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(UpdateOrderStateAuthenticated {
+                auth,
+                order_id,
+                state: params.state,
+                tracking_number: params.tracking_number,
+            })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // rate and review the other party of a delivered order, once per order
+    async fn review_order<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: ReviewOrder,
+    ) -> Result<ReviewResponse> {
+        params
+            .validate()
+            .map_err(|e| validation_errors_to_error(e).extend())?;
+
+        let order_id = params.order_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(ReviewOrderAuthenticated {
+                auth,
+                order_id,
+                rating: params.rating,
+                body: params.body,
+            })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // sign in with a Sign-In with Ethereum message signed by the wallet
+    // creates an account for wallets no account has linked yet
+    async fn siwe_signin<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        message: String,
+        signature: String,
+    ) -> Result<UserResponse> {
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state
+            .db
+            .send(SiweSignin { message, signature })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // link the wallet that signed a Sign-In with Ethereum message to the authenticated user
+    async fn link_wallet<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        message: String,
+        signature: String,
+    ) -> Result<WalletResponse> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(LinkWallet {
+                auth,
+                message,
+                signature,
+            })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // prepare the transaction that mints a lot as a ToyNFT token to the first linked wallet
+    // the token is recorded against the lot once the mint is indexed
+    async fn prepare_mint<'ctx>(&self, ctx: &Context<'ctx>, lot_id: String) -> Result<PreparedMint> {
+        let lot_id = lot_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(PrepareMint { auth, lot_id })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
 }
@@ -0,0 +1,77 @@
+
+use uuid::Uuid;
+
+use crate::config::config;
+use crate::utils::{auth::Auth, CustomDecimal};
+
+// see MINT_PRICE in foundry-contracts/src/ToyNFT.sol, 0.08 ether
+pub const MINT_PRICE_WEI: u64 = 80_000_000_000_000_000;
+
+// Client Messages ↓
+
+// the ERC-721 metadata of a lot, which tokens minted for it point to
+#[derive(Debug)]
+pub struct GetLotMetadata {
+    pub lot_id: Uuid,
+}
+
+// the transaction that mints the lot as a ToyNFT token to a wallet of its owner
+// the token is recorded against the lot once the indexer sees it minted
+#[derive(Debug)]
+pub struct PrepareMint {
+    pub auth: Auth,
+    pub lot_id: Uuid,
+}
+
+// JSON response objects ↓
+
+// as marketplaces like OpenSea read it
+#[derive(Debug, Serialize)]
+pub struct LotMetadata {
+    pub name: String,
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    pub attributes: Vec<LotAttribute>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LotAttribute {
+    pub trait_type: String,
+    pub value: serde_json::Value,
+}
+
+// a transaction for the wallet to send as is
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreparedMint {
+    // the ToyNFT contract
+    pub to: String,
+    // calldata of mintTo(recipient, metadataUrl)
+    pub data: String,
+    // the mint price in wei
+    pub value: CustomDecimal,
+    pub recipient: String,
+    pub metadata_url: String,
+}
+
+// the ToyNFT contract lots are minted with, lots cannot be minted without it
+pub fn toy_nft_address() -> Option<String> {
+    config().marketplace.toy_nft_address.clone()
+}
+
+// the url the server can be reached at from the outside, i.e. by marketplaces
+fn public_url() -> String {
+    config().server.public_url()
+}
+
+pub fn metadata_url(lot_id: Uuid) -> String {
+    format!("{}/metadata/lots/{}", public_url(), lot_id)
+}
+
+// the lot a token URI is the metadata url of, none for tokens minted for anything else
+pub fn lot_id_from_metadata_url(url: &str) -> Option<Uuid> {
+    url.strip_prefix(&format!("{}/metadata/lots/", public_url()))?
+        .parse()
+        .ok()
+}
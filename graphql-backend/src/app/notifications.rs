@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix::prelude::*;
+use uuid::Uuid;
+
+use crate::{
+    db::Db,
+    models::{Notification, NotificationKind},
+    utils::{
+        auth::Auth,
+        mailer::{Email, Mailer},
+    },
+};
+
+// Client Messages ↓
+
+#[derive(Debug)]
+pub struct GetNotifications {
+    pub auth: Auth,
+    pub unread_only: bool,
+    pub first: Option<i32>, // <- if not set, is 20
+    // id of the last notification of the previous page
+    pub after: Option<Uuid>,
+}
+
+// marks the given notifications read, or all of them without ids
+#[derive(Debug)]
+pub struct MarkNotificationsRead {
+    pub auth: Auth,
+    pub ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Debug)]
+pub struct CountUnreadNotifications {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug)]
+pub struct GetNotificationPreferences {
+    pub auth: Auth,
+}
+
+#[derive(async_graphql::InputObject, Debug, Deserialize)]
+pub struct NotificationPreferenceInput {
+    pub kind: NotificationKind,
+    // disabled kinds are not delivered at all
+    pub enabled: bool,
+    pub email_digest: bool,
+}
+
+#[derive(Debug)]
+pub struct UpdateNotificationPreferences {
+    pub auth: Auth,
+    pub preferences: Vec<NotificationPreferenceInput>,
+}
+
+// claims the notifications due for the email digest, so no other server sends them as well
+#[derive(Debug)]
+pub struct ClaimDigests;
+
+// hands the notifications of digests that could not be sent back for the next run
+#[derive(Debug)]
+pub struct ReleaseDigest {
+    pub ids: Vec<Uuid>,
+}
+
+// Server Responses ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+pub struct NotificationPreference {
+    pub kind: NotificationKind,
+    pub enabled: bool,
+    pub email_digest: bool,
+}
+
+#[derive(Debug)]
+pub struct Digest {
+    pub email: String,
+    pub username: String,
+    pub notifications: Vec<Notification>,
+}
+
+impl Digest {
+    fn email(&self) -> Email {
+        let lines = self
+            .notifications
+            .iter()
+            .filter_map(|notification| notification.typed_payload().ok())
+            .map(|payload| format!("- {}", payload.summary()))
+            .collect::<Vec<String>>();
+
+        let subject = match self.notifications.len() {
+            1 => "You have 1 new notification".to_string(),
+            count => format!("You have {} new notifications", count),
+        };
+
+        Email {
+            to: self.email.clone(),
+            subject,
+            body: format!("Hi {},\n\n{}\n", self.username, lines.join("\n")),
+        }
+    }
+}
+
+// Actors ↓
+
+// sends the email digests on an interval for as long as the server is up
+pub struct DigestMailer {
+    pub db: Db,
+    pub mailer: Arc<dyn Mailer>,
+    pub interval: Duration,
+}
+
+impl Actor for DigestMailer {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(self.interval, |digest_mailer, _| {
+            let db = digest_mailer.db.clone();
+            let mailer = digest_mailer.mailer.clone();
+            actix::spawn(async move {
+                let digests = match db.send(ClaimDigests).await {
+                    Ok(Ok(digests)) => digests,
+                    Ok(Err(e)) => return log::error!("claiming email digests failed: {}", e),
+                    Err(e) => return log::error!("claiming email digests failed: {}", e),
+                };
+
+                for digest in digests {
+                    let email = digest.email();
+                    let mailer = mailer.clone();
+                    let sent = actix_web::rt::task::spawn_blocking(move || mailer.send(&email)).await;
+
+                    if !matches!(sent, Ok(Ok(()))) {
+                        log::error!("sending the email digest to {} failed", digest.username);
+                        let ids = digest.notifications.iter().map(|n| n.id).collect();
+                        if let Ok(Err(e)) = db.send(ReleaseDigest { ids }).await {
+                            log::error!("releasing the email digest failed: {}", e);
+                        }
+                    }
+                }
+            });
+        });
+    }
+}
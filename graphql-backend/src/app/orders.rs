@@ -0,0 +1,108 @@
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::app::profiles::ProfileResponseInner;
+use crate::models::{OrderRole, OrderState};
+use crate::utils::{auth::Auth, CustomDateTime, CustomDecimal};
+
+// Client Messages ↓
+
+#[derive(
+    async_graphql::InputObject,
+    async_graphql::SimpleObject,
+    Debug,
+    Clone,
+    Validate,
+    Serialize,
+    Deserialize,
+)]
+#[graphql(input_name = "ShippingAddressInput")]
+#[serde(rename_all = "camelCase")]
+pub struct ShippingAddress {
+    #[validate(length(min = 1, max = 200, message = "must be between 1 and 200 characters"))]
+    pub name: String,
+    #[validate(length(min = 1, max = 200, message = "must be between 1 and 200 characters"))]
+    pub line1: String,
+    #[validate(length(max = 200, message = "must be at most 200 characters"))]
+    pub line2: Option<String>,
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters"))]
+    pub city: String,
+    #[validate(length(max = 100, message = "must be at most 100 characters"))]
+    pub region: Option<String>,
+    #[validate(length(min = 1, max = 20, message = "must be between 1 and 20 characters"))]
+    pub postal_code: String,
+    // ISO 3166-1 alpha-2, i.e. NL
+    #[validate(length(equal = 2, message = "must be a two letter country code"))]
+    pub country: String,
+}
+
+// buys a lot for sale at its asking price
+#[derive(async_graphql::InputObject, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaceOrder {
+    pub lot_id: String,
+    pub shipping_address: ShippingAddress,
+}
+
+#[derive(Debug)]
+pub struct PlaceOrderAuthenticated {
+    pub auth: Auth,
+    pub lot_id: Uuid,
+    pub shipping_address: ShippingAddress,
+}
+
+// moves an order to its next state, see OrderState::can_become for who may do what
+#[derive(async_graphql::InputObject, Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateOrderState {
+    pub order_id: String,
+    pub state: OrderState,
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters"))]
+    pub tracking_number: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct UpdateOrderStateAuthenticated {
+    pub auth: Auth,
+    pub order_id: Uuid,
+    pub state: OrderState,
+    pub tracking_number: Option<String>,
+}
+
+// order history of the authenticated user
+#[derive(Debug)]
+pub struct GetOrders {
+    pub auth: Auth,
+    pub role: Option<OrderRole>, // <- if not set, both bought and sold
+    pub states: Vec<OrderState>,
+    pub first: Option<i32>, // <- if not set, is 20
+    // id of the last order of the previous page
+    pub after: Option<Uuid>,
+}
+
+#[derive(Debug)]
+pub struct GetOrder {
+    pub auth: Auth,
+    pub order_id: Uuid,
+}
+
+// JSON response objects ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderResponse {
+    pub id: String,
+    pub lot_id: String,
+    pub lot_title: String,
+    pub buyer: ProfileResponseInner,
+    pub seller: ProfileResponseInner,
+    // the part the authenticated user plays in the order
+    pub role: OrderRole,
+    pub state: OrderState,
+    pub price: CustomDecimal,
+    pub currency_symbol: String,
+    pub shipping_address: ShippingAddress,
+    pub tracking_number: Option<String>,
+    pub created_at: CustomDateTime,
+    pub updated_at: CustomDateTime,
+}
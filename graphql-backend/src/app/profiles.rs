@@ -1,4 +1,11 @@
+use std::collections::HashMap;
+
+use async_graphql::dataloader::Loader;
+
+use crate::app::{reviews::{GetProfileStats, ProfileStats}, AppState};
+use crate::db::Db;
 use crate::utils::auth::Auth;
+use uuid::Uuid;
 
 // Extractors ↓
 
@@ -16,6 +23,13 @@ pub struct GetProfile {
     pub username: String,
 }
 
+// the profiles of users by id, i.e. the sellers of a page of lots, as the viewer sees them
+#[derive(Debug)]
+pub struct GetProfilesByIds {
+    pub viewer_id: Option<Uuid>,
+    pub user_ids: Vec<Uuid>,
+}
+
 #[derive(Debug)]
 pub struct FollowProfile {
     pub auth: Auth,
@@ -36,11 +50,81 @@ pub struct ProfileResponse {
     pub profile: ProfileResponseInner,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[derive(async_graphql::SimpleObject)]
+#[graphql(complex)] // NOTE: If you want the `ComplexObject` macro to take effect, this `complex` attribute is required.
 pub struct ProfileResponseInner {
     pub username: String,
     pub bio: Option<String>,
     pub image: Option<String>,
     pub following: bool,
 }
+
+#[async_graphql::ComplexObject]
+impl ProfileResponseInner {
+    // ratings and completed sales, only looked up when asked for
+    async fn stats<'ctx>(
+        &self,
+        ctx: &async_graphql::Context<'ctx>,
+    ) -> async_graphql::Result<ProfileStats> {
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state
+            .db
+            .send(GetProfileStats {
+                username: self.username.clone(),
+            })
+            .await??;
+
+        Ok(res)
+    }
+}
+
+// Loaders ↓
+
+// a profile to look up, along with the viewer, who may or may not follow them
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProfileKey {
+    pub user_id: Uuid,
+    pub viewer_id: Option<Uuid>,
+}
+
+// the profiles asked for together, i.e. the sellers of every lot of a page, are looked up
+// with one message per viewer
+pub struct ProfileLoader {
+    pub db: Db,
+}
+
+impl Loader<ProfileKey> for ProfileLoader {
+    type Value = ProfileResponseInner;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[ProfileKey],
+    ) -> async_graphql::Result<HashMap<ProfileKey, ProfileResponseInner>> {
+        let mut by_viewer: HashMap<Option<Uuid>, Vec<Uuid>> = HashMap::new();
+        for key in keys {
+            by_viewer
+                .entry(key.viewer_id)
+                .or_default()
+                .push(key.user_id);
+        }
+
+        let mut profiles = HashMap::new();
+        for (viewer_id, user_ids) in by_viewer {
+            let res = self
+                .db
+                .send(GetProfilesByIds {
+                    viewer_id,
+                    user_ids,
+                })
+                .await??;
+            profiles.extend(
+                res.into_iter()
+                    .map(|(user_id, profile)| (ProfileKey { user_id, viewer_id }, profile)),
+            );
+        }
+
+        Ok(profiles)
+    }
+}
@@ -1,9 +1,15 @@
 use crate::{
     app::{users::UserResponse, AppState},
-    models::{LotWithImages, LotStatus},
-    utils::auth::authenticate_token,
+    error::Error,
+    models::{CatalogItem, LotWithImages, LotStatus, Notification, OrderRole, OrderState, SavedSearch},
+    utils::{
+        auth::{authenticate_token, viewer, Auth, Fingerprint},
+        CustomDecimal,
+    },
 };
+use chrono::{DateTime, Utc};
 use async_graphql::*;
+use uuid::Uuid;
 
 use super::{
     articles::{
@@ -11,9 +17,22 @@ use super::{
         ArticleListResponse, ArticleResponse, ArticlesParams, FeedParams, GetArticle, GetArticles,
         GetFeed,
     },
-    lots::{FilterLots, FilterLotsAuthenticated},
-    profiles::{GetProfile, ProfileResponse},
+    catalog::{GetPrices, PriceResponse, SearchCatalog},
+    exchange_rates::{
+        convert_lot_prices, convert_prices, AmountToConvert, ConvertAmounts, ConvertedAmount,
+    },
+    lots::{
+        ExportLotsAuthenticated, FilterLots, FilterLotsAuthenticated, GetLot, GetLotFacets,
+        GetTrashedLots, GetTrendingLots, LotFacets, LotFileFormat, RecordLotView, TrendingWindow,
+    },
+    messages::{ConversationResponse, GetBlockedUsers, GetConversations, GetMessages, MessageResponse},
+    notifications::{GetNotificationPreferences, GetNotifications, NotificationPreference},
+    orders::{GetOrder, GetOrders, OrderResponse},
+    profiles::{GetProfile, ProfileResponse, ProfileResponseInner},
+    reviews::{GetReviews, ReviewResponse},
     tags::{GetTags, TagsResponse},
+    wallets::IssueSiweNonce,
+    watchlists::{GetSavedSearches, GetWatchedLots},
 };
 
 pub struct QueryRoot;
@@ -34,10 +53,7 @@ impl QueryRoot {
         username: String,
     ) -> Result<ProfileResponse> {
         let state = ctx.data_unchecked::<AppState>();
-        let auth = authenticate_token(state, ctx)
-            .await
-            .map(|auth| Some(auth))
-            .unwrap_or(None);
+        let auth = viewer(state, ctx).await;
 
         let res = state.db.send(GetProfile { auth, username }).await??;
 
@@ -51,10 +67,7 @@ impl QueryRoot {
         slug: String,
     ) -> Result<ArticleResponse> {
         let state = ctx.data_unchecked::<AppState>();
-        let auth = authenticate_token(state, ctx)
-            .await
-            .map(|auth| Some(auth))
-            .unwrap_or(None);
+        let auth = viewer(state, ctx).await;
 
         let res = state.db.send(GetArticle { auth, slug }).await??;
 
@@ -68,10 +81,7 @@ impl QueryRoot {
         filter: ArticlesParams,
     ) -> Result<ArticleListResponse> {
         let state = ctx.data_unchecked::<AppState>();
-        let auth = authenticate_token(state, ctx)
-            .await
-            .map(|auth| Some(auth))
-            .unwrap_or(None);
+        let auth = viewer(state, ctx).await;
 
         let res = state
             .db
@@ -105,10 +115,7 @@ impl QueryRoot {
         slug: String,
     ) -> Result<CommentListResponse> {
         let state = ctx.data_unchecked::<AppState>();
-        let auth = authenticate_token(state, ctx)
-            .await
-            .map(|auth| Some(auth))
-            .unwrap_or(None);
+        let auth = viewer(state, ctx).await;
 
         let res = state.db.send(GetComments { auth, slug }).await??;
 
@@ -124,19 +131,24 @@ impl QueryRoot {
     }
 
     // get lots by authenticated user
+    // with a display currency, the asking prices are also converted into it
     async fn get_user_lots<'ctx>(
         &self,
         ctx: &Context<'ctx>,
         params: FilterLots,
+        display_currency: Option<String>,
     ) -> Result<Vec<LotWithImages>> {
         let state = ctx.data_unchecked::<AppState>();
         let auth = authenticate_token(state, ctx).await?;
         let user_id = auth.user.id;
 
-        let res = state
+        let mut res = state
             .db
-            .send(FilterLotsAuthenticated { auth, params, owner_id: Some(user_id) })
+            .send(FilterLotsAuthenticated { auth: Some(auth), params, owner_id: Some(user_id) })
             .await??;
+        convert_lot_prices(&state.db, &mut res, display_currency)
+            .await
+            .map_err(|e| e.extend())?;
 
         Ok(res)
     }
@@ -146,21 +158,390 @@ impl QueryRoot {
         &self,
         ctx: &Context<'ctx>,
         params: FilterLots,
+        display_currency: Option<String>,
     ) -> Result<Vec<LotWithImages>> {
         let state = ctx.data_unchecked::<AppState>();
         let auth = authenticate_token(state, ctx).await?;
 
-        let statuses = vec![LotStatus::ForSale.as_str().to_string()];
-        let params = FilterLots {
-            statuses,
-            ..params
+        lots_for_sale(state, Some(auth), params, display_currency).await
+    }
+
+    // get lots for sale, for anonymous visitors too
+    async fn lots_for_sale<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: FilterLots,
+        display_currency: Option<String>,
+    ) -> Result<Vec<LotWithImages>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = viewer(state, ctx).await;
+
+        lots_for_sale(state, auth, params, display_currency).await
+    }
+
+    // get a lot by id, which counts as a view of it
+    async fn lot<'ctx>(&self, ctx: &Context<'ctx>, id: String) -> Result<LotWithImages> {
+        let lot_id = id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = viewer(state, ctx).await;
+        let user_id = auth.as_ref().map(|auth| auth.user.id);
+
+        let res = state
+            .db
+            .send(GetLot { auth, lot_id })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        // owners looking at their own lots are no views, and a view that is not
+        // counted does not keep the lot from being shown
+        if user_id != Some(res.lot.user_id) {
+            let fingerprint = ctx.data_opt::<Fingerprint>().map(|fingerprint| fingerprint.0.clone());
+            let view = RecordLotView {
+                lot_id,
+                user_id,
+                fingerprint,
+            };
+            match state.db.send(view).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => log::warn!("counting a view of lot {} failed: {}", lot_id, e),
+                Err(e) => log::warn!("counting a view of lot {} failed: {}", lot_id, e),
+            }
+        }
+
+        Ok(res)
+    }
+
+    // get the lots for sale with the most views, watches and orders lately, the recent
+    // ones counting more, a day by default
+    async fn trending_lots<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        category: Option<String>,
+        window: Option<TrendingWindow>,
+        limit: Option<i32>,
+    ) -> Result<Vec<LotWithImages>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state
+            .db
+            .send(GetTrendingLots {
+                category,
+                window: window.unwrap_or_default(),
+                limit,
+            })
+            .await??;
+
+        Ok(res)
+    }
+
+    // get facet counts for lot listings, either the lots for sale or the user's own lots
+    async fn lot_facets<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        params: FilterLots,
+        owner_only: Option<bool>,
+    ) -> Result<LotFacets> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+
+        let msg = if owner_only.unwrap_or(false) {
+            GetLotFacets {
+                params,
+                owner_id: Some(auth.user.id),
+            }
+        } else {
+            let statuses = vec![LotStatus::ForSale.as_str().to_string()];
+            GetLotFacets {
+                params: FilterLots { statuses, ..params },
+                owner_id: None,
+            }
         };
 
+        let res = state.db.send(msg).await??;
+
+        Ok(res)
+    }
+
+    // autocomplete for catalog items by item number or name
+    async fn search_catalog<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        query: String,
+        limit: Option<i32>,
+    ) -> Result<Vec<CatalogItem>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state.db.send(SearchCatalog { query, limit }).await??;
+
+        Ok(res)
+    }
+
+    // get recorded prices for an item number
+    // with a display currency, the amounts are also converted into it at the rates of their time
+    async fn get_prices<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        external_id: String,
+        display_currency: Option<String>,
+    ) -> Result<Vec<PriceResponse>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let prices = state.db.send(GetPrices { external_id }).await??;
+        let res = convert_prices(&state.db, prices, display_currency)
+            .await
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // convert an amount between currencies at the rates recorded last before `at`, or now
+    async fn convert<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        amount: CustomDecimal,
+        from: String,
+        to: String,
+        at: Option<String>,
+    ) -> Result<ConvertedAmount> {
+        let at = at
+            .map(|at| DateTime::parse_from_rfc3339(&at).map(|at| at.with_timezone(&Utc)))
+            .transpose()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let from = from.to_uppercase();
+        let res = state
+            .db
+            .send(ConvertAmounts {
+                to: to.clone(),
+                amounts: vec![AmountToConvert {
+                    amount: amount.0,
+                    currency_symbol: from.clone(),
+                    at: at.map(|at| at.naive_utc()),
+                }],
+            })
+            .await?
+            .map_err(|e| e.extend())?;
+
+        res.into_iter().next().flatten().ok_or_else(|| {
+            Error::UnprocessableEntity(json!({
+                "error": format!("there is no exchange rate from {} to {}", from, to.to_uppercase()),
+            }))
+            .extend()
+        })
+    }
+
+    // get the lots the authenticated user watches
+    async fn watched_lots<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        display_currency: Option<String>,
+    ) -> Result<Vec<LotWithImages>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let mut res = state.db.send(GetWatchedLots { auth }).await??;
+        convert_lot_prices(&state.db, &mut res, display_currency)
+            .await
+            .map_err(|e| e.extend())?;
+
+        Ok(res)
+    }
+
+    // get the lots in the trash of the authenticated user, most recently deleted first
+    async fn trash<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<LotWithImages>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(GetTrashedLots { auth }).await??;
+
+        Ok(res)
+    }
+
+    // get the lots of the authenticated user outside the trash as a file importLots takes
+    async fn export_lots<'ctx>(&self, ctx: &Context<'ctx>, format: LotFileFormat) -> Result<String> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
         let res = state
             .db
-            .send(FilterLotsAuthenticated { auth, params, owner_id: None })
+            .send(ExportLotsAuthenticated { auth, format })
             .await??;
 
         Ok(res)
     }
+
+    // get the saved searches of the authenticated user
+    async fn saved_searches<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<SavedSearch>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(GetSavedSearches { auth }).await??;
+
+        Ok(res)
+    }
+
+    // get the notifications of the authenticated user, newest first
+    async fn notifications<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        unread_only: Option<bool>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Vec<Notification>> {
+        let after = after.map(|after| after.parse::<Uuid>()).transpose()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(GetNotifications {
+                auth,
+                unread_only: unread_only.unwrap_or(false),
+                first,
+                after,
+            })
+            .await??;
+
+        Ok(res)
+    }
+
+    // get the notification preferences of the authenticated user for every kind
+    async fn notification_preferences<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+    ) -> Result<Vec<NotificationPreference>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(GetNotificationPreferences { auth }).await??;
+
+        Ok(res)
+    }
+
+    // get the conversations of the authenticated user, most recently active first
+    async fn conversations<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<ConversationResponse>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(GetConversations { auth }).await??;
+
+        Ok(res)
+    }
+
+    // get the messages of a conversation, newest first, older than the cursor message if set
+    async fn messages<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        conversation_id: String,
+        cursor: Option<String>,
+        first: Option<i32>,
+    ) -> Result<Vec<MessageResponse>> {
+        let conversation_id = conversation_id.parse::<Uuid>()?;
+        let cursor = cursor.map(|cursor| cursor.parse::<Uuid>()).transpose()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(GetMessages {
+                auth,
+                conversation_id,
+                cursor,
+                first,
+            })
+            .await??;
+
+        Ok(res)
+    }
+
+    // get the users the authenticated user blocked
+    async fn blocked_users<'ctx>(&self, ctx: &Context<'ctx>) -> Result<Vec<ProfileResponseInner>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(GetBlockedUsers { auth }).await??;
+
+        Ok(res)
+    }
+
+    // get the order history of the authenticated user, newest first
+    // role narrows it down to what they bought or sold
+    async fn orders<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        role: Option<OrderRole>,
+        states: Option<Vec<OrderState>>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Vec<OrderResponse>> {
+        let after = after.map(|after| after.parse::<Uuid>()).transpose()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state
+            .db
+            .send(GetOrders {
+                auth,
+                role,
+                states: states.unwrap_or_default(),
+                first,
+                after,
+            })
+            .await??;
+
+        Ok(res)
+    }
+
+    // get an order the authenticated user bought or sold
+    async fn order<'ctx>(&self, ctx: &Context<'ctx>, order_id: String) -> Result<OrderResponse> {
+        let order_id = order_id.parse::<Uuid>()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = authenticate_token(state, ctx).await?;
+        let res = state.db.send(GetOrder { auth, order_id }).await??;
+
+        Ok(res)
+    }
+
+    // get the reviews a user received from the other party of their orders, newest first
+    async fn reviews<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        username: String,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Vec<ReviewResponse>> {
+        let after = after.map(|after| after.parse::<Uuid>()).transpose()?;
+        let state = ctx.data_unchecked::<AppState>();
+        let auth = viewer(state, ctx).await;
+        let res = state
+            .db
+            .send(GetReviews {
+                auth,
+                username,
+                first,
+                after,
+            })
+            .await??;
+
+        Ok(res)
+    }
+
+    // get a nonce to put in a Sign-In with Ethereum message, valid for 10 minutes
+    async fn siwe_nonce<'ctx>(&self, ctx: &Context<'ctx>) -> Result<String> {
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state.db.send(IssueSiweNonce).await??;
+
+        Ok(res)
+    }
+}
+
+// the lots for sale that match the filter params, with the asking prices also in the
+// display currency when one is given
+async fn lots_for_sale(
+    state: &AppState,
+    auth: Option<Auth>,
+    params: FilterLots,
+    display_currency: Option<String>,
+) -> Result<Vec<LotWithImages>> {
+    let statuses = vec![LotStatus::ForSale.as_str().to_string()];
+    let params = FilterLots {
+        statuses,
+        ..params
+    };
+
+    let mut res = state
+        .db
+        .send(FilterLotsAuthenticated { auth, params, owner_id: None })
+        .await??;
+    convert_lot_prices(&state.db, &mut res, display_currency)
+        .await
+        .map_err(|e| e.extend())?;
+
+    Ok(res)
 }
@@ -0,0 +1,69 @@
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::app::profiles::ProfileResponseInner;
+use crate::models::OrderRole;
+use crate::utils::{auth::Auth, CustomDateTime, CustomDecimal};
+
+// Client Messages ↓
+
+// reviews the other party of a delivered order
+#[derive(async_graphql::InputObject, Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewOrder {
+    pub order_id: String,
+    #[validate(range(min = 1, max = 5, message = "must be between 1 and 5"))]
+    pub rating: i32,
+    #[validate(length(min = 1, max = 2000, message = "must be between 1 and 2000 characters"))]
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct ReviewOrderAuthenticated {
+    pub auth: Auth,
+    pub order_id: Uuid,
+    pub rating: i32,
+    pub body: String,
+}
+
+// reviews a user received, newest first
+#[derive(Debug)]
+pub struct GetReviews {
+    // auth is option in case authentication fails or isn't present
+    pub auth: Option<Auth>,
+    pub username: String,
+    pub first: Option<i32>, // <- if not set, is 20
+    // id of the last review of the previous page
+    pub after: Option<Uuid>,
+}
+
+#[derive(Debug)]
+pub struct GetProfileStats {
+    pub username: String,
+}
+
+// JSON response objects ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewResponse {
+    pub id: String,
+    pub order_id: String,
+    pub lot_title: String,
+    pub rating: i32,
+    pub body: String,
+    pub reviewer: ProfileResponseInner,
+    // whether the reviewer bought or sold in the reviewed order
+    pub reviewer_role: OrderRole,
+    pub created_at: CustomDateTime,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileStats {
+    // rounded to two decimals, null without reviews
+    pub average_rating: Option<CustomDecimal>,
+    pub review_count: i64,
+    // orders sold that were delivered
+    pub completed_sales: i64,
+}
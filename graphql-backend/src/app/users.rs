@@ -1,11 +1,16 @@
 use regex::Regex;
 use std::convert::From;
+use uuid::Uuid;
 use validator::{Validate, ValidationError};
 
 use crate::models::User;
 use crate::utils::{auth::Auth, jwt::CanGenerateJwt};
 
-use super::AppState;
+use super::{
+    notifications::CountUnreadNotifications,
+    wallets::{GetWallets, WalletResponse},
+    AppState,
+};
 
 lazy_static! {
     static ref RE_USERNAME: Regex = Regex::new(r"^[_0-9a-zA-Z]+$").unwrap();
@@ -167,8 +172,13 @@ pub struct UserResponse {
 }
 
 #[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[graphql(complex)] // NOTE: If you want the `ComplexObject` macro to take effect, this `complex` attribute is required.
 pub struct UserResponseInner {
-    pub email: String,
+    #[graphql(skip)]
+    #[serde(skip)]
+    pub id: Uuid,
+    // none for accounts created by signing in with a wallet
+    pub email: Option<String>,
     pub token: String,
     pub username: String,
     pub bio: Option<String>,
@@ -179,6 +189,7 @@ impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         UserResponse {
             user: UserResponseInner {
+                id: user.id,
                 token: user.generate_jwt().unwrap(),
                 email: user.email,
                 username: user.username,
@@ -189,10 +200,37 @@ impl From<User> for UserResponse {
     }
 }
 
+#[async_graphql::ComplexObject]
+impl UserResponseInner {
+    async fn unread_notifications<'ctx>(
+        &self,
+        ctx: &async_graphql::Context<'ctx>,
+    ) -> async_graphql::Result<i64> {
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state
+            .db
+            .send(CountUnreadNotifications { user_id: self.id })
+            .await??;
+
+        Ok(res)
+    }
+
+    async fn wallets<'ctx>(
+        &self,
+        ctx: &async_graphql::Context<'ctx>,
+    ) -> async_graphql::Result<Vec<WalletResponse>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state.db.send(GetWallets { user_id: self.id }).await??;
+
+        Ok(res)
+    }
+}
+
 impl UserResponse {
     pub fn create_with_auth(auth: Auth) -> Self {
         UserResponse {
             user: UserResponseInner {
+                id: auth.user.id,
                 token: auth.token,
                 email: auth.user.email,
                 username: auth.user.username,
@@ -0,0 +1,51 @@
+use uuid::Uuid;
+
+use crate::models::Wallet;
+use crate::utils::{auth::Auth, CustomDateTime};
+
+// Client Messages ↓
+
+// hands out a nonce for a Sign-In with Ethereum message
+#[derive(Debug)]
+pub struct IssueSiweNonce;
+
+// signs in with a signed Sign-In with Ethereum message
+// an address that no account has linked yet gets an account of its own
+#[derive(Debug)]
+pub struct SiweSignin {
+    pub message: String,
+    pub signature: String,
+}
+
+// links the address of a signed Sign-In with Ethereum message to the account
+#[derive(Debug)]
+pub struct LinkWallet {
+    pub auth: Auth,
+    pub message: String,
+    pub signature: String,
+}
+
+#[derive(Debug)]
+pub struct GetWallets {
+    pub user_id: Uuid,
+}
+
+// JSON response objects ↓
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletResponse {
+    pub address: String,
+    pub chain_id: i64,
+    pub created_at: CustomDateTime,
+}
+
+impl From<Wallet> for WalletResponse {
+    fn from(wallet: Wallet) -> Self {
+        WalletResponse {
+            address: wallet.address,
+            chain_id: wallet.chain_id,
+            created_at: CustomDateTime(wallet.created_at),
+        }
+    }
+}
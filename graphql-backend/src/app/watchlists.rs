@@ -0,0 +1,49 @@
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{app::lots::FilterLots, utils::auth::Auth};
+
+// Client Messages ↓
+
+#[derive(Debug)]
+pub struct WatchLot {
+    pub auth: Auth,
+    pub lot_id: Uuid,
+}
+
+#[derive(Debug)]
+pub struct UnwatchLot {
+    pub auth: Auth,
+    pub lot_id: Uuid,
+}
+
+#[derive(Debug)]
+pub struct GetWatchedLots {
+    pub auth: Auth,
+}
+
+// saving a search under a name that is already taken replaces that search
+#[derive(async_graphql::InputObject, Debug, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveSearch {
+    #[validate(length(min = 1, message = "fails validation - cannot be empty"))]
+    pub name: String,
+    pub params: FilterLots,
+}
+
+#[derive(Debug)]
+pub struct SaveSearchAuthenticated {
+    pub auth: Auth,
+    pub search: SaveSearch,
+}
+
+#[derive(Debug)]
+pub struct DeleteSavedSearch {
+    pub auth: Auth,
+    pub id: Uuid,
+}
+
+#[derive(Debug)]
+pub struct GetSavedSearches {
+    pub auth: Auth,
+}
@@ -0,0 +1,437 @@
+use std::fs;
+
+use actix::prelude::*;
+use clap::{Parser, Subcommand};
+use rand::{distributions::Alphanumeric, seq::SliceRandom, thread_rng, Rng};
+use uuid::Uuid;
+use validator::{Validate, ValidateArgs, ValidationErrors};
+
+use crate::{
+    app::{
+        admin::{ImpersonateLotOwner, ImpersonateUser, SetUserFlags},
+        catalog::ImportPrices,
+        lots::{CreateLot, CreateLotAuthenticated, UpdateLotAuthenticated},
+        users::{FindUser, RegisterUser, UpdateUser, UpdateUserOuter},
+        AppState,
+    },
+    config::config,
+    db::{new_pool, run_pending_migrations, Db, DbExecutor},
+    error::Error,
+    models::{self, LotStatus},
+    utils::CustomDecimal,
+};
+
+const DEMO_PASSWORD: &str = "Demo-pass1";
+
+#[derive(Parser)]
+#[command(about = "The GraphQL backend, and the commands to administer its database")]
+pub struct Cli {
+    /// A TOML file with the settings, see config.example.toml, the environment
+    /// overrides it, defaults to CONFIG_FILE
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start the server, the default without a command
+    Serve {
+        /// Apply the pending migrations first, instead of refusing to start
+        #[arg(long)]
+        migrate: bool,
+    },
+    /// Apply the migrations the database does not have yet
+    Migrate,
+    /// Manage user accounts
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Manage lots
+    #[command(subcommand)]
+    Lot(LotCommand),
+    /// Fill the database with data to try the app with
+    Seed {
+        /// Demo users with lots for sale, who sign in with the password Demo-pass1
+        #[arg(long, required = true)]
+        demo: bool,
+    },
+    /// Manage recorded prices
+    #[command(subcommand)]
+    Prices(PricesCommand),
+}
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// Register a user, a password is generated and printed if none is given
+    Create {
+        username: String,
+        email: String,
+        #[arg(long)]
+        password: Option<String>,
+        /// Mark the email as verified, so that the user can sign in right away
+        #[arg(long)]
+        verified: bool,
+        #[arg(long)]
+        admin: bool,
+    },
+    /// Mark the email of a user as verified
+    Verify { username: String },
+    /// Stop a user from signing in, the tokens they have stop working too
+    Disable { username: String },
+    /// Let a disabled user sign in again
+    Enable { username: String },
+    /// Set a new password, one is generated and printed if none is given
+    ResetPassword {
+        username: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum LotCommand {
+    /// Change the status of a lot, by the same rules as its owner changing it
+    SetStatus {
+        lot_id: Uuid,
+        /// i.e. "for sale", or for-sale
+        status: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PricesCommand {
+    /// Import a CSV price list with the columns external_id, currency_symbol, amount
+    /// and optionally recorded_at
+    Import {
+        file: String,
+        /// Where the prices come from, i.e. bricklink
+        #[arg(long)]
+        source: String,
+    },
+}
+
+// runs a command other than serve against the database of the config
+// the commands send the same messages to the db executor as the requests do
+pub fn run(command: Command) -> Result<(), String> {
+    let pool = new_pool(config().database.url.clone(), 2).map_err(describe)?;
+
+    if let Command::Migrate = command {
+        let versions = run_pending_migrations(&pool)?;
+        if versions.is_empty() {
+            println!("the database is up to date");
+        }
+        for version in versions {
+            println!("applied {}", version);
+        }
+        return Ok(());
+    }
+
+    System::new().block_on(async move {
+        let state = AppState {
+            db: Db::start(2, pool),
+        };
+
+        match command {
+            Command::Serve { .. } | Command::Migrate => unreachable!("not an admin command"),
+            Command::User(command) => user(&state, command).await,
+            Command::Lot(LotCommand::SetStatus { lot_id, status }) => {
+                let auth = send(&state.db, ImpersonateLotOwner { lot_id }).await?;
+                let res = send(
+                    &state.db,
+                    UpdateLotAuthenticated {
+                        auth,
+                        lot: models::UpdateLot {
+                            id: lot_id,
+                            status: Some(status.replace(['-', '_'], " ")),
+                            ..Default::default()
+                        },
+                    },
+                )
+                .await?;
+                println!("\"{}\" is now {}", res.lot.title, res.lot.status);
+                Ok(())
+            }
+            Command::Seed { .. } => seed_demo(&state).await,
+            Command::Prices(PricesCommand::Import { file, source }) => {
+                let prices = fs::read_to_string(&file).map_err(|e| format!("{}: {}", file, e))?;
+                let res = send(&state.db, ImportPrices { source, prices }).await?;
+                for error in res.errors {
+                    eprintln!("skipped {}", json!(error));
+                }
+                println!("imported {} prices", res.imported);
+                Ok(())
+            }
+        }
+    })
+}
+
+async fn user(state: &AppState, command: UserCommand) -> Result<(), String> {
+    let set_flags = |username: String, email_verified, is_disabled| SetUserFlags {
+        username,
+        email_verified,
+        is_admin: None,
+        is_disabled,
+    };
+
+    match command {
+        UserCommand::Create {
+            username,
+            email,
+            password,
+            verified,
+            admin,
+        } => {
+            let generated = password.is_none();
+            let password = password.unwrap_or_else(generate_password);
+
+            let params = RegisterUser {
+                username,
+                email,
+                password: password.clone(),
+            };
+            let params = validate_blocking(state, move |state| {
+                params.validate_args((state, state)).map(|_| params)
+            })
+            .await?;
+            let res = send(&state.db, params).await?;
+
+            if verified || admin {
+                send(
+                    &state.db,
+                    SetUserFlags {
+                        username: res.user.username.clone(),
+                        email_verified: verified.then_some(true),
+                        is_admin: admin.then_some(true),
+                        is_disabled: None,
+                    },
+                )
+                .await?;
+            }
+            println!("created {}", res.user.username);
+            if generated {
+                println!("password: {}", password);
+            }
+        }
+        UserCommand::Verify { username } => {
+            let user = send(&state.db, set_flags(username, Some(true), None)).await?;
+            println!("verified the email of {}", user.username);
+        }
+        UserCommand::Disable { username } => {
+            let user = send(&state.db, set_flags(username, None, Some(true))).await?;
+            println!("disabled {}", user.username);
+        }
+        UserCommand::Enable { username } => {
+            let user = send(&state.db, set_flags(username, None, Some(false))).await?;
+            println!("enabled {}", user.username);
+        }
+        UserCommand::ResetPassword { username, password } => {
+            let generated = password.is_none();
+            let password = password.unwrap_or_else(generate_password);
+
+            let auth = send(&state.db, ImpersonateUser { username }).await?;
+            let update_user = UpdateUser {
+                username: None,
+                email: None,
+                email_verified: None,
+                password: Some(password.clone()),
+                bio: None,
+                image: None,
+            };
+            let update_user = validate_blocking(state, move |state| {
+                update_user.validate_args(state).map(|_| update_user)
+            })
+            .await?;
+            let res = send(&state.db, UpdateUserOuter { auth, update_user }).await?;
+
+            println!("reset the password of {}", res.user.username);
+            if generated {
+                println!("password: {}", password);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// a seller with lots for sale of the sets the prices migration records, and a buyer
+// users that exist already are left alone, so seeding twice changes nothing
+async fn seed_demo(state: &AppState) -> Result<(), String> {
+    for (username, email) in [
+        ("demo_seller", "seller@demo.example.com"),
+        ("demo_buyer", "buyer@demo.example.com"),
+    ] {
+        let exists = send(
+            &state.db,
+            FindUser {
+                username: username.to_string(),
+            },
+        )
+        .await
+        .is_ok();
+        if exists {
+            println!("{} exists already", username);
+            continue;
+        }
+
+        let params = RegisterUser {
+            username: username.to_string(),
+            email: email.to_string(),
+            password: DEMO_PASSWORD.to_string(),
+        };
+        let params = validate_blocking(state, move |state| {
+            params.validate_args((state, state)).map(|_| params)
+        })
+        .await?;
+        send(&state.db, params).await?;
+        send(
+            &state.db,
+            SetUserFlags {
+                username: username.to_string(),
+                email_verified: Some(true),
+                is_admin: None,
+                is_disabled: None,
+            },
+        )
+        .await?;
+        println!("created {}", username);
+
+        if username != "demo_seller" {
+            continue;
+        }
+        for (title, condition, set_number, meta_data, price, currency) in [
+            (
+                "Millennium Falcon, Ultimate Collector Series",
+                "used",
+                "75192",
+                json!({ "set_number": "75192-1", "theme": "Star Wars", "year": 2017, "piece_count": 7541 }),
+                "899.00",
+                "USD",
+            ),
+            (
+                "AT-TE Walker",
+                "new",
+                "75337",
+                json!({ "set_number": "75337-1", "theme": "Star Wars", "year": 2022, "piece_count": 1082 }),
+                "0.065",
+                "ETH",
+            ),
+            (
+                "Clone Troopers Battle Pack",
+                "new",
+                "75345",
+                json!({ "set_number": "75345-1", "theme": "Star Wars", "year": 2023, "piece_count": 119 }),
+                "19.99",
+                "USD",
+            ),
+            (
+                "Castle in the Forest",
+                "new",
+                "910001-1",
+                json!({ "set_number": "910001-1", "theme": "BrickLink Designer Program", "year": 2023 }),
+                "0.0045",
+                "BTC",
+            ),
+        ] {
+            let auth = send(
+                &state.db,
+                ImpersonateUser {
+                    username: username.to_string(),
+                },
+            )
+            .await?;
+            let lot = CreateLot {
+                category: "set".to_string(),
+                condition: condition.to_string(),
+                title: title.to_string(),
+                external_id: Some(set_number.to_string()),
+                description: format!("{}, a demo lot", title),
+                images: vec![],
+                meta_data,
+                asking_price: Some(CustomDecimal(price.parse().unwrap())),
+                currency_symbol: Some(currency.to_string()),
+                quantity: None,
+                catalog_item_id: None,
+                nft_address: None,
+                token_id: None,
+            };
+            lot.validate().map_err(|e| describe(e.into()))?;
+            let created = send(&state.db, CreateLotAuthenticated { auth, lot }).await?;
+
+            let auth = send(
+                &state.db,
+                ImpersonateUser {
+                    username: username.to_string(),
+                },
+            )
+            .await?;
+            send(
+                &state.db,
+                UpdateLotAuthenticated {
+                    auth,
+                    lot: models::UpdateLot {
+                        id: created.lot.id,
+                        status: Some(LotStatus::ForSale.as_str().to_string()),
+                        ..Default::default()
+                    },
+                },
+            )
+            .await?;
+            println!("listed \"{}\" for sale", title);
+        }
+    }
+
+    Ok(())
+}
+
+// the validators that look up the database block on the send, which the arbiter of this
+// thread has to forward to the db executor, so they run on a thread of their own
+async fn validate_blocking<T, F>(state: &AppState, validate: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&AppState) -> Result<T, ValidationErrors> + Send + 'static,
+{
+    let state = AppState {
+        db: state.db.clone(),
+    };
+    actix_rt::task::spawn_blocking(move || validate(&state))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| describe(e.into()))
+}
+
+async fn send<M, T>(db: &Db, msg: M) -> Result<T, String>
+where
+    M: Message<Result = Result<T, Error>> + Send + 'static,
+    T: Send + 'static,
+    DbExecutor: Handler<M, Result = Result<T, Error>>,
+{
+    db.send(msg)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(describe)
+}
+
+// validation errors are listed by field, the display of the others says enough
+fn describe(error: Error) -> String {
+    match error {
+        Error::ValidationErrors(errors) => format!("Validation Errors: {}", json!(errors)),
+        error => error.to_string(),
+    }
+}
+
+// letters and digits, with the characters the password rules ask for mixed in
+fn generate_password() -> String {
+    let mut rng = thread_rng();
+    let mut password: Vec<char> = (&mut rng)
+        .sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
+    password.push(rng.gen_range('A'..='Z'));
+    password.push(rng.gen_range('a'..='z'));
+    password.push(rng.gen_range('0'..='9'));
+    password.push(*b"!#%*-_".choose(&mut rng).unwrap() as char);
+    password.shuffle(&mut rng);
+    password.into_iter().collect()
+}
@@ -0,0 +1,685 @@
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use regex::Regex;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+lazy_static! {
+    static ref ADDRESS_RE: Regex = Regex::new(r"^0x[0-9a-fA-F]{40}$").unwrap();
+    static ref ORIGIN_RE: Regex = Regex::new(r"^https?://[^/\s]+$").unwrap();
+}
+
+// the settings of the server and the commands, read from an optional TOML file first,
+// see config.example.toml, and then from the environment, which overrides the file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub cors: CorsConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub limits: LimitsConfig,
+    pub alerts: AlertsConfig,
+    pub digests: DigestsConfig,
+    pub trash: TrashConfig,
+    pub trending: TrendingConfig,
+    pub exchange_rates: ExchangeRatesConfig,
+    pub marketplace: MarketplaceConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    // the url the server is reached at from the outside, defaults to http:// and the bind address
+    pub public_url: Option<String>,
+    pub workers: usize,
+    // how long in-flight requests and db messages get to finish after a SIGTERM
+    pub shutdown_timeout_seconds: u64,
+    pub graphiql: bool,
+    // the addresses of the reverse proxies whose X-Forwarded-For is taken for the client address
+    pub trusted_proxies: Vec<String>,
+}
+
+// the frontends allowed to call the API from a browser, none without an origin listed
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    // exact origins, i.e. http://localhost:5173
+    pub origins: Vec<String>,
+    // regular expressions an origin has to match as a whole, i.e. https://[a-z0-9-]+\.example\.com
+    pub origin_patterns: Vec<String>,
+    // browsers send cookies along, which rules out allow_any_origin
+    pub allow_credentials: bool,
+    // any origin, for development only
+    pub allow_any_origin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub pool_size: u32,
+    // the threads handling the db messages
+    pub executors: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    pub token_lifetime_days: i64,
+    // the domain Sign-In with Ethereum messages have to be meant for
+    pub siwe_domain: String,
+    pub siwe_nonce_minutes: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    // the most lots or articles a page lists
+    pub max_page_size: i64,
+    pub max_query_depth: Option<usize>,
+    pub max_query_complexity: Option<usize>,
+    // the most lots an import takes at once
+    pub max_import_rows: usize,
+}
+
+// the saved searches are matched against new lots on an interval
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    pub enabled: bool,
+    pub interval_seconds: u64,
+}
+
+// the unread notifications are mailed on an interval
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DigestsConfig {
+    pub enabled: bool,
+    pub interval_seconds: u64,
+}
+
+// deleted lots are purged on an interval once they have been in the trash for some days
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrashConfig {
+    pub purge_enabled: bool,
+    pub retention_days: i64,
+    pub purge_interval_seconds: u64,
+}
+
+// views of a lot count once per viewer within the dedup window, and the trending
+// scores are refreshed on an interval
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrendingConfig {
+    pub view_dedup_minutes: i64,
+    pub refresh_enabled: bool,
+    pub refresh_interval_seconds: u64,
+}
+
+// snapshots are imported when the url is set
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExchangeRatesConfig {
+    pub url: Option<String>,
+    pub interval_seconds: u64,
+}
+
+// events are indexed when both the rpc url and the address are set
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarketplaceConfig {
+    pub rpc_url: Option<String>,
+    pub address: Option<String>,
+    // the ToyNFT contract lots are minted with, lots cannot be minted without it
+    pub toy_nft_address: Option<String>,
+    pub start_block: i64,
+    pub confirmations: i64,
+    pub batch_size: i64,
+    pub interval_seconds: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: "127.0.0.1:9000".to_string(),
+            public_url: None,
+            workers: num_cpus::get(),
+            shutdown_timeout_seconds: 30,
+            graphiql: true,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            pool_size: 10,
+            executors: num_cpus::get(),
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            jwt_secret: String::new(),
+            token_lifetime_days: 21,
+            siwe_domain: "localhost:5173".to_string(),
+            siwe_nonce_minutes: 10,
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_page_size: 100,
+            max_query_depth: None,
+            max_query_complexity: None,
+            max_import_rows: 1000,
+        }
+    }
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig {
+            enabled: true,
+            interval_seconds: 60,
+        }
+    }
+}
+
+impl Default for DigestsConfig {
+    fn default() -> Self {
+        DigestsConfig {
+            enabled: true,
+            interval_seconds: 24 * 60 * 60,
+        }
+    }
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        TrashConfig {
+            purge_enabled: true,
+            retention_days: 30,
+            purge_interval_seconds: 60 * 60,
+        }
+    }
+}
+
+impl Default for TrendingConfig {
+    fn default() -> Self {
+        TrendingConfig {
+            view_dedup_minutes: 30,
+            refresh_enabled: true,
+            refresh_interval_seconds: 5 * 60,
+        }
+    }
+}
+
+impl Default for ExchangeRatesConfig {
+    fn default() -> Self {
+        ExchangeRatesConfig {
+            url: None,
+            interval_seconds: 60 * 60,
+        }
+    }
+}
+
+impl Default for MarketplaceConfig {
+    fn default() -> Self {
+        MarketplaceConfig {
+            rpc_url: None,
+            address: None,
+            toy_nft_address: None,
+            start_block: 0,
+            confirmations: 12,
+            batch_size: 2000,
+            interval_seconds: 12,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn public_url(&self) -> String {
+        self.public_url
+            .clone()
+            .unwrap_or_else(|| format!("http://{}", self.bind_address))
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_seconds)
+    }
+}
+
+impl CorsConfig {
+    // the patterns, anchored so that they match an origin as a whole
+    pub fn origin_regexes(&self) -> Vec<Regex> {
+        self.origin_patterns
+            .iter()
+            .map(|pattern| Regex::new(&format!("^(?:{})$", pattern)).unwrap())
+            .collect()
+    }
+}
+
+impl LimitsConfig {
+    // the size of a page, the one asked for kept between 1 and max_page_size
+    pub fn page_size(&self, requested: Option<i64>, default: i64) -> i64 {
+        requested.unwrap_or(default).clamp(1, self.max_page_size)
+    }
+}
+
+impl AlertsConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_seconds)
+    }
+}
+
+impl DigestsConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_seconds)
+    }
+}
+
+impl TrashConfig {
+    pub fn purge_interval(&self) -> Duration {
+        Duration::from_secs(self.purge_interval_seconds)
+    }
+}
+
+impl TrendingConfig {
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_interval_seconds)
+    }
+}
+
+// the config loaded in main, before anything else runs
+pub fn config() -> &'static Config {
+    CONFIG.get().expect("the config is loaded at startup")
+}
+
+// the defaults with a secret to sign tokens with, for the tests, which leave the
+// environment alone
+#[cfg(test)]
+pub fn load_for_tests() -> &'static Config {
+    CONFIG.get_or_init(|| Config {
+        auth: AuthConfig {
+            jwt_secret: "test secret".to_string(),
+            ..AuthConfig::default()
+        },
+        ..Config::default()
+    })
+}
+
+// reads and validates the config, every problem found is returned at once
+pub fn load(file: Option<String>) -> Result<&'static Config, Vec<String>> {
+    let mut errors = Vec::new();
+
+    let mut config = match file.or_else(|| env::var("CONFIG_FILE").ok()) {
+        Some(path) => match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                errors.push(format!("{}: {}", path, e));
+                Config::default()
+            }),
+            Err(e) => {
+                errors.push(format!("{}: {}", path, e));
+                Config::default()
+            }
+        },
+        None => Config::default(),
+    };
+    config.read_env(&mut errors);
+    config.validate(&mut errors);
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+impl Config {
+    fn read_env(&mut self, errors: &mut Vec<String>) {
+        let mut env = EnvVars { errors };
+
+        let server = &mut self.server;
+        env.set("BIND_ADDRESS", &mut server.bind_address);
+        env.set_some("PUBLIC_URL", &mut server.public_url);
+        env.set("WORKERS", &mut server.workers);
+        env.set(
+            "SHUTDOWN_TIMEOUT_SECONDS",
+            &mut server.shutdown_timeout_seconds,
+        );
+        env.set("GRAPHIQL_ENABLED", &mut server.graphiql);
+        env.set_list("TRUSTED_PROXIES", &mut server.trusted_proxies);
+
+        let cors = &mut self.cors;
+        // the single origin the server used to allow, which would be dropped along with
+        // CORS_ORIGINS
+        if env::var_os("FRONTEND_ORIGIN").is_some() && env::var_os("CORS_ORIGINS").is_some() {
+            env.errors.push(
+                "FRONTEND_ORIGIN and CORS_ORIGINS are both set, list the frontend origin in CORS_ORIGINS"
+                    .to_string(),
+            );
+        }
+        env.set_list("FRONTEND_ORIGIN", &mut cors.origins);
+        env.set_list("CORS_ORIGINS", &mut cors.origins);
+        env.set_list("CORS_ORIGIN_PATTERNS", &mut cors.origin_patterns);
+        env.set("CORS_ALLOW_CREDENTIALS", &mut cors.allow_credentials);
+        env.set("CORS_ALLOW_ANY_ORIGIN", &mut cors.allow_any_origin);
+
+        let database = &mut self.database;
+        env.set("DATABASE_URL", &mut database.url);
+        env.set("DB_POOL_SIZE", &mut database.pool_size);
+        env.set("DB_EXECUTORS", &mut database.executors);
+
+        let auth = &mut self.auth;
+        env.set("JWT_SECRET", &mut auth.jwt_secret);
+        env.set("TOKEN_LIFETIME_DAYS", &mut auth.token_lifetime_days);
+        env.set("SIWE_DOMAIN", &mut auth.siwe_domain);
+        env.set("SIWE_NONCE_MINUTES", &mut auth.siwe_nonce_minutes);
+
+        let limits = &mut self.limits;
+        env.set("MAX_PAGE_SIZE", &mut limits.max_page_size);
+        env.set_some("MAX_QUERY_DEPTH", &mut limits.max_query_depth);
+        env.set_some("MAX_QUERY_COMPLEXITY", &mut limits.max_query_complexity);
+        env.set("MAX_IMPORT_ROWS", &mut limits.max_import_rows);
+
+        env.set("ALERTS_ENABLED", &mut self.alerts.enabled);
+        env.set("ALERT_INTERVAL_SECONDS", &mut self.alerts.interval_seconds);
+        env.set("DIGESTS_ENABLED", &mut self.digests.enabled);
+        env.set(
+            "DIGEST_INTERVAL_SECONDS",
+            &mut self.digests.interval_seconds,
+        );
+
+        let trash = &mut self.trash;
+        env.set("TRASH_PURGE_ENABLED", &mut trash.purge_enabled);
+        env.set("TRASH_RETENTION_DAYS", &mut trash.retention_days);
+        env.set(
+            "TRASH_PURGE_INTERVAL_SECONDS",
+            &mut trash.purge_interval_seconds,
+        );
+
+        let trending = &mut self.trending;
+        env.set("LOT_VIEW_DEDUP_MINUTES", &mut trending.view_dedup_minutes);
+        env.set("TRENDING_REFRESH_ENABLED", &mut trending.refresh_enabled);
+        env.set(
+            "TRENDING_REFRESH_INTERVAL_SECONDS",
+            &mut trending.refresh_interval_seconds,
+        );
+
+        let exchange_rates = &mut self.exchange_rates;
+        env.set_some("EXCHANGE_RATES_URL", &mut exchange_rates.url);
+        env.set(
+            "EXCHANGE_RATES_INTERVAL_SECONDS",
+            &mut exchange_rates.interval_seconds,
+        );
+
+        let marketplace = &mut self.marketplace;
+        env.set_some("ETH_RPC_URL", &mut marketplace.rpc_url);
+        env.set_some("MARKETPLACE_ADDRESS", &mut marketplace.address);
+        env.set_some("TOY_NFT_ADDRESS", &mut marketplace.toy_nft_address);
+        env.set("MARKETPLACE_START_BLOCK", &mut marketplace.start_block);
+        env.set("MARKETPLACE_CONFIRMATIONS", &mut marketplace.confirmations);
+        env.set("MARKETPLACE_BATCH_SIZE", &mut marketplace.batch_size);
+        env.set(
+            "MARKETPLACE_INTERVAL_SECONDS",
+            &mut marketplace.interval_seconds,
+        );
+    }
+
+    fn validate(&mut self, errors: &mut Vec<String>) {
+        let mut check = |ok: bool, error: &str| {
+            if !ok {
+                errors.push(error.to_string());
+            }
+        };
+
+        let server = &self.server;
+        check(
+            server
+                .bind_address
+                .to_socket_addrs()
+                .is_ok_and(|mut addrs| addrs.next().is_some()),
+            "server.bind_address (BIND_ADDRESS) must be a host and port, i.e. 127.0.0.1:9000",
+        );
+        check(
+            server
+                .public_url
+                .as_deref()
+                .is_none_or(|url| url.starts_with("http://") || url.starts_with("https://")),
+            "server.public_url (PUBLIC_URL) must be an http or https url",
+        );
+        check(
+            server.workers > 0,
+            "server.workers (WORKERS) must be at least 1",
+        );
+        check(
+            server
+                .trusted_proxies
+                .iter()
+                .all(|proxy| proxy.parse::<IpAddr>().is_ok()),
+            "server.trusted_proxies (TRUSTED_PROXIES) must be ip addresses",
+        );
+
+        let cors = &self.cors;
+        for origin in &cors.origins {
+            check(
+                origin != "*" || cors.allow_any_origin,
+                "cors.origins (CORS_ORIGINS) has *, any origin is only allowed with cors.allow_any_origin (CORS_ALLOW_ANY_ORIGIN) set for development",
+            );
+            check(
+                origin == "*" || ORIGIN_RE.is_match(origin),
+                &format!(
+                    "cors.origins (CORS_ORIGINS) has {}, origins are a scheme and host without a path, i.e. http://localhost:5173",
+                    origin
+                ),
+            );
+        }
+        for pattern in &cors.origin_patterns {
+            if let Err(e) = Regex::new(pattern) {
+                check(
+                    false,
+                    &format!(
+                        "cors.origin_patterns (CORS_ORIGIN_PATTERNS) has {}, which is not a valid regular expression: {}",
+                        pattern, e
+                    ),
+                );
+            }
+        }
+        check(
+            !(cors.allow_any_origin && cors.allow_credentials),
+            "cors.allow_credentials (CORS_ALLOW_CREDENTIALS) cannot be set with cors.allow_any_origin (CORS_ALLOW_ANY_ORIGIN), list the origins instead",
+        );
+
+        let database = &self.database;
+        check(
+            !database.url.is_empty(),
+            "database.url (DATABASE_URL) must be set",
+        );
+        check(
+            database.pool_size > 0,
+            "database.pool_size (DB_POOL_SIZE) must be at least 1",
+        );
+        check(
+            database.executors > 0,
+            "database.executors (DB_EXECUTORS) must be at least 1",
+        );
+
+        let auth = &self.auth;
+        check(
+            !auth.jwt_secret.is_empty(),
+            "auth.jwt_secret (JWT_SECRET) must be set",
+        );
+        check(
+            auth.token_lifetime_days > 0,
+            "auth.token_lifetime_days (TOKEN_LIFETIME_DAYS) must be at least 1",
+        );
+        check(
+            auth.siwe_nonce_minutes > 0,
+            "auth.siwe_nonce_minutes (SIWE_NONCE_MINUTES) must be at least 1",
+        );
+
+        let limits = &self.limits;
+        check(
+            limits.max_page_size > 0,
+            "limits.max_page_size (MAX_PAGE_SIZE) must be at least 1",
+        );
+        check(
+            limits.max_query_depth != Some(0),
+            "limits.max_query_depth (MAX_QUERY_DEPTH) must be at least 1",
+        );
+        check(
+            limits.max_query_complexity != Some(0),
+            "limits.max_query_complexity (MAX_QUERY_COMPLEXITY) must be at least 1",
+        );
+        check(
+            limits.max_import_rows > 0,
+            "limits.max_import_rows (MAX_IMPORT_ROWS) must be at least 1",
+        );
+
+        check(
+            self.alerts.interval_seconds > 0,
+            "alerts.interval_seconds (ALERT_INTERVAL_SECONDS) must be at least 1",
+        );
+        check(
+            self.digests.interval_seconds > 0,
+            "digests.interval_seconds (DIGEST_INTERVAL_SECONDS) must be at least 1",
+        );
+        check(
+            self.trash.retention_days >= 0,
+            "trash.retention_days (TRASH_RETENTION_DAYS) must not be negative",
+        );
+        check(
+            self.trash.purge_interval_seconds > 0,
+            "trash.purge_interval_seconds (TRASH_PURGE_INTERVAL_SECONDS) must be at least 1",
+        );
+        check(
+            self.trending.view_dedup_minutes >= 0,
+            "trending.view_dedup_minutes (LOT_VIEW_DEDUP_MINUTES) must not be negative",
+        );
+        check(
+            self.trending.refresh_interval_seconds > 0,
+            "trending.refresh_interval_seconds (TRENDING_REFRESH_INTERVAL_SECONDS) must be at least 1",
+        );
+        check(
+            self.exchange_rates.interval_seconds > 0,
+            "exchange_rates.interval_seconds (EXCHANGE_RATES_INTERVAL_SECONDS) must be at least 1",
+        );
+
+        let marketplace = &mut self.marketplace;
+        check(
+            marketplace.rpc_url.is_some() == marketplace.address.is_some(),
+            "marketplace.rpc_url (ETH_RPC_URL) and marketplace.address (MARKETPLACE_ADDRESS) are set together",
+        );
+        for (name, address) in [
+            (
+                "marketplace.address (MARKETPLACE_ADDRESS)",
+                &mut marketplace.address,
+            ),
+            (
+                "marketplace.toy_nft_address (TOY_NFT_ADDRESS)",
+                &mut marketplace.toy_nft_address,
+            ),
+        ] {
+            if let Some(address) = address {
+                check(
+                    ADDRESS_RE.is_match(address),
+                    &format!("{} must be a 0x prefixed contract address", name),
+                );
+                // addresses are compared as the indexer records them
+                *address = address.to_lowercase();
+            }
+        }
+        check(
+            marketplace.start_block >= 0,
+            "marketplace.start_block (MARKETPLACE_START_BLOCK) must not be negative",
+        );
+        check(
+            marketplace.confirmations >= 0,
+            "marketplace.confirmations (MARKETPLACE_CONFIRMATIONS) must not be negative",
+        );
+        check(
+            marketplace.batch_size > 0,
+            "marketplace.batch_size (MARKETPLACE_BATCH_SIZE) must be at least 1",
+        );
+        check(
+            marketplace.interval_seconds > 0,
+            "marketplace.interval_seconds (MARKETPLACE_INTERVAL_SECONDS) must be at least 1",
+        );
+    }
+}
+
+// sets the settings that have an environment variable, keeping the errors of the ones
+// that do not parse
+struct EnvVars<'a> {
+    errors: &'a mut Vec<String>,
+}
+
+impl EnvVars<'_> {
+    fn set<T>(&mut self, name: &str, setting: &mut T)
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if let Some(value) = self.parse(name) {
+            *setting = value;
+        }
+    }
+
+    fn set_some<T>(&mut self, name: &str, setting: &mut Option<T>)
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        if let Some(value) = self.parse(name) {
+            *setting = Some(value);
+        }
+    }
+
+    // a comma separated list
+    fn set_list(&mut self, name: &str, setting: &mut Vec<String>) {
+        if let Ok(value) = env::var(name) {
+            *setting = value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+    }
+
+    fn parse<T>(&mut self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = env::var(name).ok()?;
+        match value.trim().parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.errors.push(format!(
+                    "{} is {:?}, which is not valid: {}",
+                    name, value, e
+                ));
+                None
+            }
+        }
+    }
+}
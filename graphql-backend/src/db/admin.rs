@@ -0,0 +1,77 @@
+use actix::prelude::*;
+use diesel::prelude::*;
+
+use super::DbExecutor;
+use crate::app::admin::{ImpersonateLotOwner, ImpersonateUser, SetUserFlags};
+use crate::models::{User, UserFlagsChange};
+use crate::prelude::*;
+use crate::utils::{auth::Auth, jwt::CanGenerateJwt};
+
+// message handler implementations ↓
+
+impl Message for ImpersonateUser {
+    type Result = Result<Auth>;
+}
+
+impl Handler<ImpersonateUser> for DbExecutor {
+    type Result = Result<Auth>;
+
+    fn handle(&mut self, msg: ImpersonateUser, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::users::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let user: User = users.filter(username.eq(msg.username)).first(conn)?;
+        impersonate(user)
+    }
+}
+
+impl Message for ImpersonateLotOwner {
+    type Result = Result<Auth>;
+}
+
+impl Handler<ImpersonateLotOwner> for DbExecutor {
+    type Result = Result<Auth>;
+
+    fn handle(&mut self, msg: ImpersonateLotOwner, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{lots, users};
+
+        let conn = &mut self.0.get()?;
+
+        let user: User = lots::table
+            .inner_join(users::table)
+            .filter(lots::id.eq(msg.lot_id))
+            .select(users::all_columns)
+            .first(conn)?;
+        impersonate(user)
+    }
+}
+
+impl Message for SetUserFlags {
+    type Result = Result<User>;
+}
+
+impl Handler<SetUserFlags> for DbExecutor {
+    type Result = Result<User>;
+
+    fn handle(&mut self, msg: SetUserFlags, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::users::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let user = diesel::update(users.filter(username.eq(msg.username)))
+            .set(&UserFlagsChange {
+                email_verified: msg.email_verified,
+                is_admin: msg.is_admin,
+                is_disabled: msg.is_disabled,
+            })
+            .get_result::<User>(conn)?;
+
+        Ok(user)
+    }
+}
+
+fn impersonate(user: User) -> Result<Auth> {
+    let token = user.generate_jwt()?;
+    Ok(Auth { user, token })
+}
@@ -0,0 +1,161 @@
+use actix::prelude::*;
+use chrono::NaiveDateTime;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+
+use super::lots::filter::filter_lots_query;
+use super::notifications::notify;
+use super::{DbExecutor, PooledConn};
+use crate::app::{alerts::MatchAlerts, lots::FilterLots};
+use crate::models::{
+    Lot, LotStatus, NewNotification, NotificationPayload, SavedSearch, SavedSearchMatchPayload,
+    WatchedLot, WatchedLotChangedPayload,
+};
+use crate::prelude::*;
+use crate::utils::CustomDecimal;
+
+define_sql_function!(fn pg_try_advisory_xact_lock(key: BigInt) -> Bool);
+
+// the transactions below this id have all committed or aborted
+const FINISHED_XID_HORIZON: &str = "pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT";
+
+// keeps servers that share the database from matching the same changes at once
+const MATCH_ALERTS_LOCK: i64 = 0x6b69_7378_616c_7274;
+
+impl Message for MatchAlerts {
+    type Result = Result<usize>;
+}
+
+impl Handler<MatchAlerts> for DbExecutor {
+    type Result = Result<usize>;
+
+    fn handle(&mut self, _: MatchAlerts, _: &mut Self::Context) -> Self::Result {
+        let conn = &mut self.0.get()?;
+
+        conn.transaction(|connection| {
+            let locked = diesel::select(pg_try_advisory_xact_lock(MATCH_ALERTS_LOCK))
+                .get_result::<bool>(connection)?;
+            if !locked {
+                return Ok(0);
+            }
+
+            // read before this transaction writes, as its own id would hold the horizon back
+            let (now, horizon) = diesel::select((diesel::dsl::now, sql::<BigInt>(FINISHED_XID_HORIZON)))
+                .get_result::<(NaiveDateTime, i64)>(connection)?;
+
+            let mut alerts = watched_lot_changes(connection)?;
+            alerts.extend(saved_search_matches(now, horizon, connection)?);
+
+            notify(alerts, connection)
+        })
+    }
+}
+
+// notifications for watched lots whose status or price differs from what the watcher was last told
+fn watched_lot_changes(conn: &mut PooledConn) -> Result<Vec<NewNotification>> {
+    use crate::schema::{lots, watched_lots};
+    use diesel::PgExpressionMethods;
+
+    let changed: Vec<(WatchedLot, Lot)> = watched_lots::table
+        .inner_join(lots::table)
+        .filter(
+            lots::status
+                .ne(watched_lots::last_status)
+                .or(lots::asking_price.is_distinct_from(watched_lots::last_asking_price)),
+        )
+        .select((WatchedLot::as_select(), Lot::as_select()))
+        .load(conn)?;
+
+    let mut notifications = Vec::with_capacity(changed.len());
+    for (watched, lot) in changed {
+        notifications.push(NewNotification::new(
+            watched.user_id,
+            NotificationPayload::WatchedLotChanged(WatchedLotChangedPayload {
+                lot_id: lot.id.to_string(),
+                title: lot.title.clone(),
+                old_status: watched.last_status,
+                status: lot.status.clone(),
+                old_asking_price: watched.last_asking_price.map(CustomDecimal),
+                asking_price: lot.asking_price.clone().map(CustomDecimal),
+                currency_symbol: lot.currency_symbol.clone(),
+            }),
+        ));
+
+        diesel::update(
+            watched_lots::table
+                .filter(watched_lots::user_id.eq(watched.user_id))
+                .filter(watched_lots::lot_id.eq(watched.lot_id)),
+        )
+        .set((
+            watched_lots::last_status.eq(&lot.status),
+            watched_lots::last_asking_price.eq(&lot.asking_price),
+        ))
+        .execute(conn)?;
+    }
+
+    Ok(notifications)
+}
+
+// notifications for lots put up for sale since a saved search was last matched,
+// leaving out the lots of the search's owner
+// a listing is matched once its transaction is below the horizon, so one that commits
+// after the matcher ran is matched the next time rather than passed over
+fn saved_search_matches(
+    now: NaiveDateTime,
+    horizon: i64,
+    conn: &mut PooledConn,
+) -> Result<Vec<NewNotification>> {
+    use crate::schema::{lots, saved_searches};
+
+    let searches: Vec<SavedSearch> = saved_searches::table
+        .filter(saved_searches::last_matched_xid.lt(horizon))
+        .select(SavedSearch::as_select())
+        .load(conn)?;
+
+    let mut notifications = Vec::new();
+    for search in searches {
+        let params = match serde_json::from_value::<FilterLots>(search.params.clone()) {
+            Ok(params) => params,
+            Err(e) => {
+                log::warn!("skipping saved search {}: {}", search.id, e);
+                continue;
+            }
+        };
+        let params = FilterLots {
+            statuses: vec![LotStatus::ForSale.as_str().to_string()],
+            ..params
+        };
+
+        let matches: Vec<Lot> = filter_lots_query(&params, None, None)
+            .filter(lots::listed_xid.ge(search.last_matched_xid))
+            .filter(lots::listed_xid.lt(horizon))
+            .filter(lots::user_id.ne(search.user_id))
+            .order((lots::listed_at, lots::id))
+            .select(Lot::as_select())
+            .load(conn)?;
+
+        notifications.extend(matches.into_iter().map(|lot| {
+            NewNotification::new(
+                search.user_id,
+                NotificationPayload::SavedSearchMatch(SavedSearchMatchPayload {
+                    saved_search_id: search.id.to_string(),
+                    saved_search_name: search.name.clone(),
+                    lot_id: lot.id.to_string(),
+                    title: lot.title,
+                    asking_price: lot.asking_price.map(CustomDecimal),
+                    currency_symbol: lot.currency_symbol,
+                }),
+            )
+        }));
+
+        diesel::update(saved_searches::table.find(search.id))
+            .set((
+                saved_searches::last_matched_at.eq(now),
+                saved_searches::last_matched_xid.eq(horizon),
+            ))
+            .execute(conn)?;
+    }
+
+    Ok(notifications)
+}
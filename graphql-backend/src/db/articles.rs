@@ -4,14 +4,17 @@ use diesel::prelude::*;
 use slug::slugify;
 use uuid::Uuid;
 
+use super::notifications::notify;
 use super::{DbExecutor, PooledConn};
 use crate::app::articles::{
     ArticleListResponse, ArticleResponse, ArticleResponseInner, CreateArticleOuter, DeleteArticle,
     FavoriteArticle, GetArticle, GetArticles, GetFeed, UnfavoriteArticle, UpdateArticleOuter,
 };
 use crate::app::profiles::ProfileResponseInner;
+use crate::config::config;
 use crate::models::{
-    Article, ArticleChange, ArticleTag, NewArticle, NewArticleTag, NewFavoriteArticle, User,
+    Article, ArticleChange, ArticleFavoritePayload, ArticleTag, NewArticle, NewArticleTag,
+    NewFavoriteArticle, NewNotification, NotificationPayload, User,
 };
 use crate::prelude::*;
 use crate::utils::CustomDateTime;
@@ -95,7 +98,7 @@ impl Handler<UpdateArticleOuter> for DbExecutor {
         }
 
         let slug = match &msg.article.title {
-            Some(title) => Some(generate_slug(&article.id, &title)),
+            Some(title) => Some(generate_slug(&article.id, title)),
             None => None,
         };
 
@@ -181,6 +184,20 @@ impl Handler<FavoriteArticle> for DbExecutor {
             })
             .execute(conn)?;
 
+        if article.author_id != msg.auth.user.id {
+            notify(
+                vec![NewNotification::new(
+                    article.author_id,
+                    NotificationPayload::ArticleFavorite(ArticleFavoritePayload {
+                        slug: article.slug.clone(),
+                        title: article.title.clone(),
+                        username: msg.auth.user.username.clone(),
+                    }),
+                )],
+                conn,
+            )?;
+        }
+
         get_article_response(article.slug, Some(msg.auth.user.id), conn)
     }
 }
@@ -257,7 +274,10 @@ impl Handler<GetArticles> for DbExecutor {
             query = query.filter(articles::id.eq_any(tagged_article_ids));
         }
 
-        let limit = std::cmp::min(msg.params.limit.unwrap_or(20), 100) as i64;
+        let limit = config().limits.page_size(
+            msg.params.limit.map(|limit| i64::try_from(limit).unwrap_or(i64::MAX)),
+            20,
+        );
         let offset = msg.params.offset.unwrap_or(0) as i64;
 
         let matched_articles = query
@@ -285,7 +305,10 @@ impl Handler<GetFeed> for DbExecutor {
 
         let conn = &mut self.0.get()?;
 
-        let limit = std::cmp::min(msg.params.limit.unwrap_or(20), 100) as i64;
+        let limit = config().limits.page_size(
+            msg.params.limit.map(|limit| i64::try_from(limit).unwrap_or(i64::MAX)),
+            20,
+        );
         let offset = msg.params.offset.unwrap_or(0) as i64;
 
         let user_id = msg.auth.user.id;
@@ -418,7 +441,7 @@ where
     // this may look confusing but collect can convert to this
     // https://doc.rust-lang.org/std/iter/trait.Iterator.html#method.collect
     tags.into_iter()
-        .map(|tag_name| add_tag(article_id, &tag_name.to_string(), conn))
+        .map(|tag_name| add_tag(article_id, tag_name, conn))
         .collect::<Result<Vec<ArticleTag>>>()
 }
 
@@ -0,0 +1,236 @@
+use actix::prelude::*;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+
+use super::DbExecutor;
+use crate::app::catalog::{
+    GetCatalogItem, GetCatalogItemForPrice, GetPrices, ImportCatalogAuthenticated,
+    ImportCatalogResponse, ImportPrices, SearchCatalog,
+};
+use crate::models::{CatalogItem, NewPrice, Price};
+use crate::error::ValidationError;
+use crate::prelude::*;
+use crate::utils::catalog_csv::{parse_catalog, parse_prices};
+
+// upserts are sent in chunks to stay clear of the bind parameter limit
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
+// message handler implementations ↓
+
+impl Message for ImportCatalogAuthenticated {
+    type Result = Result<ImportCatalogResponse>;
+}
+
+impl Handler<ImportCatalogAuthenticated> for DbExecutor {
+    type Result = Result<ImportCatalogResponse>;
+
+    fn handle(&mut self, msg: ImportCatalogAuthenticated, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::catalog_items::dsl::*;
+
+        if !msg.auth.user.is_admin {
+            return Err(Error::Forbidden(json!({
+                "error": "only admins can import the catalog",
+            })));
+        }
+
+        let parsed = parse_catalog(
+            msg.params.format,
+            &msg.params.items,
+            msg.params.themes.as_deref(),
+        )?;
+
+        let conn = &mut self.0.get()?;
+
+        // items that are already in the catalog are refreshed from the dump
+        let imported = conn.transaction::<_, Error, _>(|connection| {
+            let mut imported = 0;
+            for chunk in parsed.items.chunks(IMPORT_CHUNK_SIZE) {
+                imported += diesel::insert_into(catalog_items)
+                    .values(chunk)
+                    .on_conflict((source, external_id))
+                    .do_update()
+                    .set((
+                        name.eq(excluded(name)),
+                        theme.eq(excluded(theme)),
+                        year.eq(excluded(year)),
+                        image_url.eq(excluded(image_url)),
+                        piece_count.eq(excluded(piece_count)),
+                    ))
+                    .execute(connection)?;
+            }
+            Ok(imported)
+        })?;
+
+        Ok(ImportCatalogResponse {
+            imported,
+            errors: parsed.errors,
+        })
+    }
+}
+
+impl Message for ImportPrices {
+    type Result = Result<ImportCatalogResponse>;
+}
+
+impl Handler<ImportPrices> for DbExecutor {
+    type Result = Result<ImportCatalogResponse>;
+
+    fn handle(&mut self, msg: ImportPrices, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::currencies;
+        use crate::schema::prices::dsl::*;
+
+        let mut parsed = parse_prices(&msg.source, &msg.prices, Utc::now().naive_utc())?;
+
+        let conn = &mut self.0.get()?;
+
+        // prices in currencies that are not in the currencies table are skipped
+        let symbols: Vec<String> = currencies::table
+            .select(currencies::symbol)
+            .load(conn)?;
+        let (known, unknown): (Vec<NewPrice>, Vec<NewPrice>) = parsed
+            .prices
+            .into_iter()
+            .partition(|price| symbols.contains(&price.currency_symbol));
+        parsed.errors.extend(unknown.into_iter().map(|price| {
+            ValidationError::new(
+                price.external_id,
+                format!("{} is not a known currency", price.currency_symbol),
+            )
+        }));
+
+        // a price recorded again for the same time is corrected
+        let imported = conn.transaction::<_, Error, _>(|connection| {
+            let mut imported = 0;
+            for chunk in known.chunks(IMPORT_CHUNK_SIZE) {
+                imported += diesel::insert_into(prices)
+                    .values(chunk)
+                    .on_conflict((recorded_at, external_id, source, currency_symbol))
+                    .do_update()
+                    .set(amount.eq(excluded(amount)))
+                    .execute(connection)?;
+            }
+            Ok(imported)
+        })?;
+
+        Ok(ImportCatalogResponse {
+            imported,
+            errors: parsed.errors,
+        })
+    }
+}
+
+impl Message for SearchCatalog {
+    type Result = Result<Vec<CatalogItem>>;
+}
+
+impl Handler<SearchCatalog> for DbExecutor {
+    type Result = Result<Vec<CatalogItem>>;
+
+    fn handle(&mut self, msg: SearchCatalog, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::catalog_items::dsl::*;
+
+        let query = msg.query.trim();
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let conn = &mut self.0.get()?;
+
+        let exact = escape_like(query);
+        let prefix = format!("{}%", exact);
+        let contains = format!("%{}%", exact);
+        let limit = msg.limit.unwrap_or(10).clamp(1, 50) as i64;
+
+        // exact item numbers first, then item numbers and names that start with the query
+        let items = catalog_items
+            .filter(external_id.ilike(&prefix).or(name.ilike(&contains)))
+            .order((
+                external_id.ilike(&exact).desc(),
+                external_id.ilike(&prefix).desc(),
+                name.ilike(&prefix).desc(),
+                name,
+                id,
+            ))
+            .limit(limit)
+            .select(CatalogItem::as_select())
+            .load(conn)?;
+
+        Ok(items)
+    }
+}
+
+impl Message for GetCatalogItem {
+    type Result = Result<CatalogItem>;
+}
+
+impl Handler<GetCatalogItem> for DbExecutor {
+    type Result = Result<CatalogItem>;
+
+    fn handle(&mut self, msg: GetCatalogItem, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::catalog_items::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let item = catalog_items
+            .find(msg.id)
+            .select(CatalogItem::as_select())
+            .first(conn)?;
+
+        Ok(item)
+    }
+}
+
+impl Message for GetCatalogItemForPrice {
+    type Result = Result<Option<CatalogItem>>;
+}
+
+impl Handler<GetCatalogItemForPrice> for DbExecutor {
+    type Result = Result<Option<CatalogItem>>;
+
+    fn handle(&mut self, msg: GetCatalogItemForPrice, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::catalog_items::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let item = catalog_items
+            .filter(external_id.eq(msg.external_id))
+            .order(source.eq(msg.source).desc())
+            .select(CatalogItem::as_select())
+            .first(conn)
+            .optional()?;
+
+        Ok(item)
+    }
+}
+
+impl Message for GetPrices {
+    type Result = Result<Vec<Price>>;
+}
+
+impl Handler<GetPrices> for DbExecutor {
+    type Result = Result<Vec<Price>>;
+
+    fn handle(&mut self, msg: GetPrices, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::prices::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        // most recent prices first
+        let item_prices = prices
+            .filter(external_id.eq(msg.external_id))
+            .order((recorded_at.desc(), source, currency_symbol))
+            .select(Price::as_select())
+            .load(conn)?;
+
+        Ok(item_prices)
+    }
+}
+
+// the query is matched literally, so LIKE wildcards in it are escaped
+fn escape_like(query: &str) -> String {
+    query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
@@ -2,13 +2,17 @@ use actix::prelude::*;
 use diesel::prelude::*;
 use uuid::Uuid;
 
+use super::notifications::notify;
 use super::{DbExecutor, PooledConn};
 use crate::app::articles::comments::{
     AddCommentOuter, CommentListResponse, CommentResponse, CommentResponseInner, DeleteComment,
     GetComments,
 };
 use crate::app::profiles::ProfileResponseInner;
-use crate::models::{Comment, Follower, NewComment, User};
+use crate::models::{
+    ArticleCommentPayload, Comment, Follower, NewComment, NewNotification, NotificationPayload,
+    User,
+};
 use crate::prelude::*;
 use crate::utils::CustomDateTime;
 
@@ -26,10 +30,10 @@ impl Handler<AddCommentOuter> for DbExecutor {
 
         let conn = &mut self.0.get()?;
 
-        let article_id = articles::table
-            .filter(articles::slug.eq(msg.slug))
-            .select(articles::id)
-            .get_result::<Uuid>(conn)?;
+        let (article_id, author_id, title) = articles::table
+            .filter(articles::slug.eq(&msg.slug))
+            .select((articles::id, articles::author_id, articles::title))
+            .get_result::<(Uuid, Uuid, String)>(conn)?;
 
         let user_id = msg.auth.user.id;
 
@@ -43,6 +47,21 @@ impl Handler<AddCommentOuter> for DbExecutor {
             .values(new_comment)
             .get_result::<Comment>(conn)?;
 
+        if author_id != user_id {
+            notify(
+                vec![NewNotification::new(
+                    author_id,
+                    NotificationPayload::ArticleComment(ArticleCommentPayload {
+                        slug: msg.slug,
+                        title,
+                        comment_id: comment.id,
+                        username: msg.auth.user.username,
+                    }),
+                )],
+                conn,
+            )?;
+        }
+
         get_comment_response(comment.id, Some(user_id), conn)
     }
 }
@@ -83,12 +102,20 @@ impl Handler<DeleteComment> for DbExecutor {
     type Result = Result<()>;
 
     fn handle(&mut self, msg: DeleteComment, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::articles;
         use crate::schema::comments::dsl::*;
 
         let conn = &mut self.0.get()?;
 
+        // the comment is only found under the article it was made on
+        let article = articles::table
+            .filter(articles::slug.eq(msg.slug))
+            .select(articles::id)
+            .get_result::<Uuid>(conn)?;
+
         let comment = comments
             .filter(id.eq(msg.comment_id))
+            .filter(article_id.eq(article))
             .get_result::<Comment>(conn)?;
 
         if msg.auth.user.id != comment.user_id {
@@ -0,0 +1,176 @@
+use std::collections::{btree_map::Entry, BTreeMap, HashMap};
+
+use actix::prelude::*;
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+
+use super::{DbExecutor, PooledConn};
+use crate::app::exchange_rates::{
+    ConvertAmounts, ConvertedAmount, ImportExchangeRatesAuthenticated,
+    ImportExchangeRatesResponse, StoreExchangeRates,
+};
+use crate::error::ValidationError;
+use crate::models::{ExchangeRate, NewExchangeRate};
+use crate::prelude::*;
+use crate::utils::exchange_rates::{parse_snapshots, ExchangeRates};
+use crate::utils::{CustomDateTime, CustomDecimal};
+
+// upserts are sent in chunks to stay clear of the bind parameter limit
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
+// message handler implementations ↓
+
+impl Message for ImportExchangeRatesAuthenticated {
+    type Result = Result<ImportExchangeRatesResponse>;
+}
+
+impl Handler<ImportExchangeRatesAuthenticated> for DbExecutor {
+    type Result = Result<ImportExchangeRatesResponse>;
+
+    fn handle(
+        &mut self,
+        msg: ImportExchangeRatesAuthenticated,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        if !msg.auth.user.is_admin {
+            return Err(Error::Forbidden(json!({
+                "error": "only admins can import exchange rates",
+            })));
+        }
+
+        let parsed = parse_snapshots(&msg.snapshots, "upload")?;
+
+        let conn = &mut self.0.get()?;
+        let mut res = store_rates(conn, parsed.rates)?;
+        res.errors.splice(0..0, parsed.errors);
+
+        Ok(res)
+    }
+}
+
+impl Message for StoreExchangeRates {
+    type Result = Result<ImportExchangeRatesResponse>;
+}
+
+impl Handler<StoreExchangeRates> for DbExecutor {
+    type Result = Result<ImportExchangeRatesResponse>;
+
+    fn handle(&mut self, msg: StoreExchangeRates, _: &mut Self::Context) -> Self::Result {
+        let conn = &mut self.0.get()?;
+
+        store_rates(conn, msg.rates)
+    }
+}
+
+impl Message for ConvertAmounts {
+    type Result = Result<Vec<Option<ConvertedAmount>>>;
+}
+
+impl Handler<ConvertAmounts> for DbExecutor {
+    type Result = Result<Vec<Option<ConvertedAmount>>>;
+
+    fn handle(&mut self, msg: ConvertAmounts, _: &mut Self::Context) -> Self::Result {
+        let conn = &mut self.0.get()?;
+
+        let to = msg.to.to_uppercase();
+        let decimals = currency_decimals(conn)?;
+        if !decimals.contains_key(&to) {
+            return Err(Error::UnprocessableEntity(json!({
+                "error": format!("{} is not a known currency", to),
+            })));
+        }
+
+        // the rates are looked up once for every distinct time
+        let now = Utc::now().naive_utc();
+        let mut rates_at: BTreeMap<NaiveDateTime, ExchangeRates> = BTreeMap::new();
+        let mut converted = Vec::with_capacity(msg.amounts.len());
+        for amount in msg.amounts {
+            let at = amount.at.unwrap_or(now);
+            let rates = match rates_at.entry(at) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let rates = latest_rates(conn, at)?;
+                    entry.insert(ExchangeRates::new(rates, decimals.clone()))
+                }
+            };
+
+            // amounts without a rate to the currency are left unconverted
+            let conversion = rates.convert(&amount.amount, &amount.currency_symbol, &to);
+            converted.push(conversion.map(|conversion| ConvertedAmount {
+                amount: CustomDecimal(conversion.amount),
+                currency_symbol: to.clone(),
+                rate: CustomDecimal(conversion.rate),
+                rate_recorded_at: conversion.recorded_at.map(CustomDateTime),
+            }));
+        }
+
+        Ok(converted)
+    }
+}
+
+// rates between currencies that are not in the currencies table are skipped
+fn store_rates(
+    conn: &mut PooledConn,
+    rates: Vec<NewExchangeRate>,
+) -> Result<ImportExchangeRatesResponse> {
+    use crate::schema::exchange_rates::dsl::*;
+
+    let decimals = currency_decimals(conn)?;
+    let mut errors = Vec::new();
+    let known_rates: Vec<NewExchangeRate> = rates
+        .into_iter()
+        .filter(|new_rate| {
+            let unknown = [&new_rate.base_symbol, &new_rate.quote_symbol]
+                .into_iter()
+                .find(|symbol| !decimals.contains_key(*symbol));
+            if let Some(symbol) = unknown {
+                errors.push(ValidationError::new(
+                    format!("{}/{}", new_rate.base_symbol, new_rate.quote_symbol),
+                    format!("{} is not a known currency", symbol),
+                ));
+            }
+            unknown.is_none()
+        })
+        .collect();
+
+    // a rate recorded again for the same time and source is corrected
+    let imported = conn.transaction::<_, Error, _>(|connection| {
+        let mut imported = 0;
+        for chunk in known_rates.chunks(IMPORT_CHUNK_SIZE) {
+            imported += diesel::insert_into(exchange_rates)
+                .values(chunk)
+                .on_conflict((base_symbol, quote_symbol, recorded_at, source))
+                .do_update()
+                .set(rate.eq(excluded(rate)))
+                .execute(connection)?;
+        }
+        Ok(imported)
+    })?;
+
+    Ok(ImportExchangeRatesResponse { imported, errors })
+}
+
+fn currency_decimals(conn: &mut PooledConn) -> Result<HashMap<String, i32>> {
+    use crate::schema::currencies::dsl::*;
+
+    let symbols = currencies
+        .select((symbol, decimals))
+        .load::<(String, i32)>(conn)?;
+
+    Ok(symbols.into_iter().collect())
+}
+
+// the rate of every pair recorded last at or before the time, from whichever source
+fn latest_rates(conn: &mut PooledConn, at: NaiveDateTime) -> Result<Vec<ExchangeRate>> {
+    use crate::schema::exchange_rates::dsl::*;
+
+    let rates = exchange_rates
+        .filter(recorded_at.le(at))
+        .distinct_on((base_symbol, quote_symbol))
+        .order((base_symbol, quote_symbol, recorded_at.desc(), source))
+        .select(ExchangeRate::as_select())
+        .load(conn)?;
+
+    Ok(rates)
+}
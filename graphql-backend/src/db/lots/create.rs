@@ -1,12 +1,15 @@
-use super::DbExecutor;
-use crate::app::lots::{CreateLotAuthenticated};
+use super::{DbExecutor, PooledConn};
+use crate::db::marketplace::link_marketplace_events;
+use crate::app::lots::{parse_id, CreateLot, CreateLotAuthenticated};
 use crate::models::LotWithImages;
+use crate::utils::meta_data::validate_meta_data;
 use crate::{
     models::{Lot, LotImage, NewLot, NewLotImage},
     prelude::*,
 };
 use actix::prelude::*;
 use diesel::prelude::*;
+use uuid::Uuid;
 
 
 impl Message for CreateLotAuthenticated {
@@ -17,47 +20,86 @@ impl Handler<CreateLotAuthenticated> for DbExecutor {
     type Result = Result<LotWithImages>;
 
     fn handle(&mut self, msg: CreateLotAuthenticated, _: &mut Self::Context) -> Self::Result {
-        use crate::schema::{lot_images::dsl::*, lots::dsl::*};
-
-        let new_lot = NewLot {
-            user_id: msg.auth.user.id,
-            category: msg.lot.category.clone(),
-            condition: msg.lot.condition.clone(),
-            title: msg.lot.title.clone(),
-            external_id: msg.lot.external_id.clone(),
-            description: msg.lot.description,
-            meta_data: serde_json::to_value(msg.lot.meta_data).unwrap(),
-        };
-
         let conn = &mut self.0.get()?;
 
-        conn.transaction(|connection| {
-            let inserted_lot: Lot = diesel::insert_into(lots)
-                .values(new_lot)
-                .get_result(connection)?;
-
-            let new_lot_images: Vec<NewLotImage> = msg
-                .lot
-                .images
-                .into_iter()
-                .map(|image| NewLotImage {
-                    lot_id: inserted_lot.id,
-                    image_url: image.image_url,
-                    is_thumbnail: image.is_thumbnail,
-                })
-                .collect();
-
-            let inserted_images: Vec<LotImage> = diesel::insert_into(lot_images)
-                .values(&new_lot_images)
-                .get_results(connection)?;
-
-            Ok(LotWithImages {
-                lot: inserted_lot,
-                images: inserted_images
-                    .into_iter()
-                    .map(|image| image.into())
-                    .collect(),
+        insert_lot(msg.auth.user.id, msg.lot, None, conn)
+    }
+}
+
+// inserts a lot of the user with its images, see also the lot import
+pub(super) fn insert_lot(
+    owner_id: Uuid,
+    lot: CreateLot,
+    lot_status: Option<String>,
+    conn: &mut PooledConn,
+) -> Result<LotWithImages> {
+    use crate::schema::{lot_images::dsl::*, lots::dsl::*};
+
+    validate_meta_data(&lot.category, &lot.meta_data)?;
+
+    let lot_catalog_item_id = lot
+        .catalog_item_id
+        .as_deref()
+        .map(|item_id| parse_id("catalog item id", item_id))
+        .transpose()?;
+
+    // a lot picked from the catalog is known by the item number of the catalog item
+    let lot_external_id = match (lot.external_id, lot_catalog_item_id) {
+        (None, Some(item_id)) => {
+            use crate::schema::catalog_items;
+            let item_external_id = catalog_items::table
+                .find(item_id)
+                .select(catalog_items::external_id)
+                .first::<String>(conn)?;
+            Some(item_external_id)
+        }
+        (lot_external_id, _) => lot_external_id,
+    };
+
+    let new_lot = NewLot {
+        user_id: owner_id,
+        category: lot.category.clone(),
+        condition: lot.condition.clone(),
+        title: lot.title.clone(),
+        external_id: lot_external_id,
+        description: lot.description,
+        meta_data: serde_json::to_value(lot.meta_data).unwrap(),
+        asking_price: lot.asking_price.map(|price| price.0),
+        currency_symbol: lot.currency_symbol,
+        quantity: lot.quantity,
+        catalog_item_id: lot_catalog_item_id,
+        nft_address: lot.nft_address.map(|address| address.to_lowercase()),
+        token_id: lot.token_id.map(|token| token.0),
+        status: lot_status,
+    };
+
+    conn.transaction(|connection| {
+        let inserted_lot: Lot = diesel::insert_into(lots)
+            .values(new_lot)
+            .get_result(connection)?;
+
+        if inserted_lot.nft_address.is_some() {
+            link_marketplace_events(&inserted_lot, connection)?;
+        }
+
+        let new_lot_images: Vec<NewLotImage> = lot
+            .images
+            .into_iter()
+            .map(|image| NewLotImage {
+                lot_id: inserted_lot.id,
+                image_url: image.image_url,
+                is_thumbnail: image.is_thumbnail,
             })
+            .collect();
+
+        let inserted_images: Vec<LotImage> = diesel::insert_into(lot_images)
+            .values(&new_lot_images)
+            .get_results(connection)?;
+
+        Ok(LotWithImages {
+            lot: inserted_lot,
+            images: inserted_images,
+            display_price: None,
         })
-    }
+    })
 }
@@ -1,7 +1,15 @@
 use super::DbExecutor;
-use crate::{app::lots::DeleteLotAuthenticated, prelude::*};
+use crate::db::messages::detach_lot_conversations;
+use crate::{
+    app::lots::{DeleteLotAuthenticated, GetTrashedLots, PurgeDeletedLots, RestoreLotAuthenticated},
+    models::{Lot, LotImage, LotStatus, LotWithImages, OrderState},
+    prelude::*,
+};
 use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime};
+use diesel::dsl::{exists, not};
 use diesel::prelude::*;
+use uuid::Uuid;
 
 
 impl Message for DeleteLotAuthenticated {
@@ -13,14 +21,195 @@ impl Handler<DeleteLotAuthenticated> for DbExecutor {
 
     fn handle(&mut self, msg: DeleteLotAuthenticated, _: &mut Self::Context) -> Self::Result {
         use crate::schema::lots::dsl::*;
+        use crate::schema::orders;
 
-        // delete lot where user_id = msg.auth.user.id and lot_id = msg.lot.id
+        // soft delete lot where user_id = msg.auth.user.id and lot_id = msg.lot.id
         let conn = &mut self.0.get()?;
-        let deleted = diesel::delete(
-            lots.filter(user_id.eq(msg.auth.user.id))
-                .filter(id.eq(msg.lot_id)),
-        )
-        .execute(conn)?;
-        Ok(deleted)
+
+        conn.transaction(|connection| {
+            let existing: Option<Lot> = lots
+                .filter(user_id.eq(msg.auth.user.id))
+                .filter(id.eq(msg.lot_id))
+                .filter(status.ne(LotStatus::Deleted.as_str()))
+                .for_update()
+                .get_result(connection)
+                .optional()?;
+            let Some(existing) = existing else {
+                return Ok(0);
+            };
+
+            // the buyer of an open order is still owed the lot
+            let open_states: Vec<&str> = OrderState::ALL
+                .iter()
+                .filter(|state| state.is_open())
+                .map(|state| state.as_str())
+                .collect();
+            let open_orders: i64 = orders::table
+                .filter(orders::lot_id.eq(existing.id))
+                .filter(orders::state.eq_any(open_states))
+                .count()
+                .get_result(connection)?;
+
+            if open_orders > 0 {
+                return Err(Error::UnprocessableEntity(json!({
+                    "error": "a lot with an open order cannot be deleted",
+                })));
+            }
+
+            let deleted = diesel::update(lots)
+                .filter(id.eq(existing.id))
+                .set((
+                    status.eq(LotStatus::Deleted.as_str()),
+                    status_before_delete.eq(&existing.status),
+                    deleted_at.eq(diesel::dsl::now),
+                ))
+                .execute(connection)?;
+            Ok(deleted)
+        })
+    }
+}
+
+impl Message for RestoreLotAuthenticated {
+    type Result = Result<LotWithImages>;
+}
+
+impl Handler<RestoreLotAuthenticated> for DbExecutor {
+    type Result = Result<LotWithImages>;
+
+    fn handle(&mut self, msg: RestoreLotAuthenticated, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::lots::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        conn.transaction(|connection| {
+            let existing: Lot = lots
+                .filter(user_id.eq(msg.auth.user.id))
+                .filter(id.eq(msg.lot_id))
+                .filter(status.eq(LotStatus::Deleted.as_str()))
+                .for_update()
+                .get_result(connection)
+                .optional()?
+                .ok_or_else(|| {
+                    Error::NotFound(json!({ "error": "the lot is not in your trash" }))
+                })?;
+
+            // lots deleted before the trash existed come back as drafts
+            let restored_status = existing
+                .status_before_delete
+                .unwrap_or_else(|| LotStatus::Drafted.as_str().to_string());
+
+            let restored: Lot = diesel::update(lots)
+                .filter(id.eq(existing.id))
+                .set((
+                    status.eq(restored_status),
+                    status_before_delete.eq(None::<String>),
+                    deleted_at.eq(None::<NaiveDateTime>),
+                ))
+                .get_result(connection)?;
+
+            let images = LotImage::belonging_to(&restored)
+                .select(LotImage::as_select())
+                .load(connection)?;
+
+            Ok(LotWithImages {
+                lot: restored,
+                images,
+                display_price: None,
+            })
+        })
+    }
+}
+
+impl Message for GetTrashedLots {
+    type Result = Result<Vec<LotWithImages>>;
+}
+
+impl Handler<GetTrashedLots> for DbExecutor {
+    type Result = Result<Vec<LotWithImages>>;
+
+    fn handle(&mut self, msg: GetTrashedLots, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::lots::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let trashed: Vec<Lot> = lots
+            .filter(user_id.eq(msg.auth.user.id))
+            .filter(status.eq(LotStatus::Deleted.as_str()))
+            .order((deleted_at.desc(), id))
+            .select(Lot::as_select())
+            .load(conn)?;
+
+        let images = LotImage::belonging_to(&trashed)
+            .select(LotImage::as_select())
+            .load(conn)?;
+
+        Ok(images
+            .grouped_by(&trashed)
+            .into_iter()
+            .zip(trashed)
+            .map(|(imgs, lot)| LotWithImages {
+                lot,
+                images: imgs,
+                display_price: None,
+            })
+            .collect())
+    }
+}
+
+impl Message for PurgeDeletedLots {
+    type Result = Result<usize>;
+}
+
+impl Handler<PurgeDeletedLots> for DbExecutor {
+    type Result = Result<usize>;
+
+    fn handle(&mut self, msg: PurgeDeletedLots, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::lots::dsl::*;
+        use crate::schema::orders;
+
+        let conn = &mut self.0.get()?;
+
+        let now = diesel::select(diesel::dsl::now).get_result::<NaiveDateTime>(conn)?;
+        let deleted_before = now - Duration::days(msg.days);
+
+        // lots with orders are kept for the order history
+        let purgeable = || {
+            lots.filter(status.eq(LotStatus::Deleted.as_str()))
+                .filter(deleted_at.lt(deleted_before))
+                .filter(not(exists(
+                    orders::table.filter(orders::lot_id.eq(id)),
+                )))
+        };
+
+        let candidates: Vec<Uuid> = purgeable().select(id).load(conn)?;
+
+        // each lot goes in its own transaction, so one that fails does not hold back the rest
+        let mut purged = 0;
+        for lot in candidates {
+            let result: Result<usize> = conn.transaction(|connection| {
+                // it may have been restored in the meantime
+                let still_purgeable: Option<Uuid> = purgeable()
+                    .filter(id.eq(lot))
+                    .select(id)
+                    .for_update()
+                    .get_result(connection)
+                    .optional()?;
+                if still_purgeable.is_none() {
+                    return Ok(0);
+                }
+
+                // the images, watches and mints of a lot go with it, marketplace events
+                // keep their place without it and its conversations are detached first
+                detach_lot_conversations(lot, connection)?;
+                Ok(diesel::delete(lots.filter(id.eq(lot))).execute(connection)?)
+            });
+
+            match result {
+                Ok(deleted) => purged += deleted,
+                Err(e) => log::warn!("skipping the purge of lot {}: {}", lot, e),
+            }
+        }
+
+        Ok(purged)
     }
 }
@@ -0,0 +1,136 @@
+use super::filter::{check_meta_data_filters, check_price_filter, filter_lots_query, Facet};
+use super::{DbExecutor, PooledConn};
+use crate::{
+    app::lots::{FacetCount, GetLotFacets, LotFacets, PriceRange},
+    prelude::*,
+    utils::CustomDecimal,
+};
+use actix::prelude::*;
+use bigdecimal::BigDecimal;
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Numeric};
+
+// diesel only provides min and max for types it can order, which excludes Numeric
+define_sql_function! {
+    #[aggregate]
+    #[sql_name = "min"]
+    fn min_numeric(x: Nullable<Numeric>) -> Nullable<Numeric>;
+}
+define_sql_function! {
+    #[aggregate]
+    #[sql_name = "max"]
+    fn max_numeric(x: Nullable<Numeric>) -> Nullable<Numeric>;
+}
+
+// the facets counted per value, the price is aggregated into ranges instead
+#[derive(Clone, Copy)]
+enum CountedFacet {
+    Category,
+    Condition,
+    Status,
+}
+
+impl From<CountedFacet> for Facet {
+    fn from(facet: CountedFacet) -> Self {
+        match facet {
+            CountedFacet::Category => Facet::Category,
+            CountedFacet::Condition => Facet::Condition,
+            CountedFacet::Status => Facet::Status,
+        }
+    }
+}
+
+impl Message for GetLotFacets {
+    type Result = Result<LotFacets>;
+}
+
+impl Handler<GetLotFacets> for DbExecutor {
+    type Result = Result<LotFacets>;
+
+    fn handle(&mut self, msg: GetLotFacets, _: &mut Self::Context) -> Self::Result {
+        check_price_filter(&msg.params)?;
+        check_meta_data_filters(&msg.params)?;
+
+        let conn = &mut self.0.get()?;
+
+        Ok(LotFacets {
+            categories: count_facet(&msg, CountedFacet::Category, conn)?,
+            conditions: count_facet(&msg, CountedFacet::Condition, conn)?,
+            statuses: count_facet(&msg, CountedFacet::Status, conn)?,
+            prices: price_ranges(&msg, conn)?,
+        })
+    }
+}
+
+// counts the lots per value of the facet under every filter except the facet's own
+fn count_facet(
+    msg: &GetLotFacets,
+    facet: CountedFacet,
+    conn: &mut PooledConn,
+) -> Result<Vec<FacetCount>> {
+    use crate::schema::lots::dsl::*;
+
+    // without an owner the statuses are the listing scope rather than a selection,
+    // so they stay applied to keep other users' drafts out of the counts
+    let exclude = match (facet, msg.owner_id) {
+        (CountedFacet::Status, None) => None,
+        _ => Some(facet.into()),
+    };
+
+    // boxed queries cannot be grouped, so group the matching ids instead
+    let matching =
+        lots.filter(id.eq_any(filter_lots_query(&msg.params, msg.owner_id, exclude).select(id)));
+
+    let counts = match facet {
+        CountedFacet::Category => matching
+            .group_by(category)
+            .select((category, count_star()))
+            .load::<(String, i64)>(conn)?,
+        CountedFacet::Condition => matching
+            .group_by(condition)
+            .select((condition, count_star()))
+            .load::<(String, i64)>(conn)?,
+        CountedFacet::Status => matching
+            .group_by(status)
+            .select((status, count_star()))
+            .load::<(String, i64)>(conn)?,
+    };
+
+    let mut counts = counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect::<Vec<FacetCount>>();
+
+    // most common values first
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+
+    Ok(counts)
+}
+
+// lowest and highest asking price per currency under every filter except the price range
+fn price_ranges(msg: &GetLotFacets, conn: &mut PooledConn) -> Result<Vec<PriceRange>> {
+    use crate::schema::lots::dsl::*;
+
+    let ranges = lots
+        .filter(id.eq_any(
+            filter_lots_query(&msg.params, msg.owner_id, Some(Facet::Price)).select(id),
+        ))
+        .filter(asking_price.is_not_null())
+        .group_by(currency_symbol)
+        .select((currency_symbol, min_numeric(asking_price), max_numeric(asking_price)))
+        .order(currency_symbol)
+        .load::<(String, Option<BigDecimal>, Option<BigDecimal>)>(conn)?;
+
+    Ok(ranges
+        .into_iter()
+        .filter_map(|(symbol, low, high)| match (low, high) {
+            (Some(low), Some(high)) => Some(PriceRange {
+                currency_symbol: symbol,
+                min: CustomDecimal(low),
+                max: CustomDecimal(high),
+            }),
+            _ => None,
+        })
+        .collect())
+}
@@ -1,15 +1,33 @@
-use std::str::FromStr;
-
 use super::DbExecutor;
 use crate::{
-    app::lots::FilterLotsAuthenticated,
+    app::lots::{FilterLots, FilterLotsAuthenticated, LotSort, MetaDataFilter},
+    config::config,
+    error::ValidationError,
     models::{Lot, LotImage, LotWithImages, LotStatus},
     prelude::*,
+    schema::lots,
 };
 use actix::prelude::*;
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use diesel::sql_types::Text;
-sql_function!(fn lower(x: Text) -> Text);
+use diesel::sql_types::{Array, Jsonb, Nullable, Text};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+define_sql_function!(fn lower(x: Text) -> Text);
+// see the lot_sorting migration
+define_sql_function!(fn lot_relevance(title: Text, description: Text, external_id: Nullable<Text>, terms: Array<Text>) -> BigInt);
+// see the lot_meta_data_filters migration
+define_sql_function!(fn lot_meta_data_matches(meta_data: Nullable<Jsonb>, path: Text, vars: Jsonb) -> Bool);
+
+// A facet is a filter dimension that can be left out of the query,
+// so that its counts reflect the alternatives to the current selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facet {
+    Category,
+    Condition,
+    Status,
+    Price,
+}
 
 // Messages
 // Filter lots by authenticated user
@@ -24,52 +42,59 @@ impl Handler<FilterLotsAuthenticated> for DbExecutor {
     fn handle(&mut self, msg: FilterLotsAuthenticated, _: &mut Self::Context) -> Self::Result {
         use crate::schema::lots::dsl::*;
 
+        check_price_filter(&msg.params)?;
+        check_meta_data_filters(&msg.params)?;
+
         let conn = &mut self.0.get()?;
 
-        let mut user_lots_query = if let Some(user) = msg.owner_id {
-            lots.filter(user_id.eq(user)).into_boxed()
-        } else {
-            lots.into_boxed()
+        let sort = lot_sort(&msg.params);
+        let terms = lowercase_terms(&msg.params);
+        // without a page or limit every lot is listed, as before lots were paged
+        let limit = match (msg.params.limit, msg.params.page) {
+            (None, None) => None,
+            (requested, _) => Some(config().limits.page_size(requested.map(i64::from), 20)),
         };
 
-        // remove soft deleted lots
-        user_lots_query = user_lots_query.filter(status.ne(LotStatus::Deleted.as_str()));
+        let mut user_lots_query = filter_lots_query(&msg.params, msg.owner_id, None);
 
-        if !msg.params.statuses.is_empty() {
-            user_lots_query = user_lots_query.filter(status.eq_any(&msg.params.statuses));
-        }
-        if !msg.params.categories.is_empty() {
-            user_lots_query = user_lots_query.filter(category.eq_any(&msg.params.categories));
-        }
-        if !msg.params.conditions.is_empty() {
-            user_lots_query = user_lots_query.filter(condition.eq_any(&msg.params.conditions));
-        }
-        if !msg.params.terms.is_empty() {
-            let ilike_terms: Vec<_> = msg
-                .params
-                .terms
-                .iter()
-                .map(|term| format!("%{}%", term.to_lowercase()))
-                .collect();
-
-            let ids: Vec<_> = msg
-                .params
-                .terms
-                .iter()
-                .map(|term| term.to_lowercase())
-                .collect();
-
-            // lower the description column and check if it contains any of the lowercase terms
-            // lower the title column and check if it contains any of the lowercase terms
-            // check if the external_id column is equal to any of the lowercase terms
-            user_lots_query = user_lots_query.filter(
-                lower(description)
-                    .like(ilike_terms.join(""))
-                    .or(lower(title).like(ilike_terms.join("")))
-                    .or(external_id.eq_any(ids)),
-            );
+        // drafts are not out yet for anyone but their owner to see
+        let drafted = LotStatus::Drafted.as_str();
+        user_lots_query = match msg.auth {
+            Some(ref auth) => {
+                user_lots_query.filter(status.ne(drafted).or(user_id.eq(auth.user.id)))
+            }
+            None => user_lots_query.filter(status.ne(drafted)),
+        };
+
+        match msg.params.after {
+            Some(ref after) => {
+                let cursor_id = Uuid::parse_str(after).map_err(|_| {
+                    Error::UnprocessableEntity(json!({ "error": "after must be a lot id" }))
+                })?;
+
+                let (cursor, cursor_relevance) = lots
+                    .find(cursor_id)
+                    .select((
+                        Lot::as_select(),
+                        lot_relevance(title, description, external_id, terms.clone()),
+                    ))
+                    .first::<(Lot, i64)>(conn)?;
+
+                user_lots_query =
+                    after_cursor(user_lots_query, sort, &cursor, cursor_relevance, terms.clone());
+            }
+            None => {
+                if let Some(limit) = limit {
+                    let page = msg.params.page.unwrap_or(1).max(1) as i64;
+                    user_lots_query = user_lots_query.offset((page - 1) * limit);
+                }
+            }
         }
 
+        let mut user_lots_query = order_lots(user_lots_query, sort, terms);
+        if let Some(limit) = limit {
+            user_lots_query = user_lots_query.limit(limit);
+        }
         let user_lots = user_lots_query.select(Lot::as_select()).load(conn)?;
 
         // lot images
@@ -82,10 +107,254 @@ impl Handler<FilterLotsAuthenticated> for DbExecutor {
             .grouped_by(&user_lots)
             .into_iter()
             .zip(user_lots)
-            .map(|(imgs, lot)| LotWithImages { lot, images: imgs })
+            .map(|(imgs, lot)| LotWithImages {
+                lot,
+                images: imgs,
+                display_price: None,
+            })
             .collect::<Vec<LotWithImages>>();
 
         Ok(lots_with_images)
     }
 }
 
+// asking prices are only comparable within a currency, so a price range or price sort
+// needs one to be picked
+pub fn check_price_filter(params: &FilterLots) -> Result<()> {
+    let by_price = params.min_price.is_some()
+        || params.max_price.is_some()
+        || matches!(params.sort, Some(LotSort::PriceAsc | LotSort::PriceDesc));
+
+    if by_price && params.currency_symbol.is_none() {
+        return Err(Error::UnprocessableEntity(json!({
+            "error": "a currency symbol is required to filter or sort lots by price",
+        })));
+    }
+    Ok(())
+}
+
+// a key or value that the JSON path cannot take is refused up front, as Postgres rejects
+// the NUL character and an object or array is never equal to a meta data value
+pub fn check_meta_data_filters(params: &FilterLots) -> Result<()> {
+    let mut errors = Vec::new();
+    for filter in &params.meta_data {
+        if filter.key.is_empty() || filter.key.contains('\0') {
+            errors.push(ValidationError::new(
+                "meta_data".to_string(),
+                format!("{:?} is not a meta data key", filter.key),
+            ));
+        }
+        match filter.equals {
+            Some(JsonValue::Array(_) | JsonValue::Object(_)) => errors.push(ValidationError::new(
+                "meta_data".to_string(),
+                format!("the value of {} must be a string, number or boolean", filter.key),
+            )),
+            Some(JsonValue::String(ref value)) if value.contains('\0') => {
+                errors.push(ValidationError::new(
+                    "meta_data".to_string(),
+                    format!("the value of {} cannot contain a NUL character", filter.key),
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(Error::ValidationErrors(errors));
+    }
+    Ok(())
+}
+
+// builds the lots query for the filter params, leaving out the selection of the excluded facet
+pub fn filter_lots_query(
+    params: &FilterLots,
+    owner_id: Option<Uuid>,
+    exclude: Option<Facet>,
+) -> lots::BoxedQuery<'_, Pg> {
+    use crate::schema::lots::dsl::*;
+
+    let mut user_lots_query = if let Some(user) = owner_id {
+        lots.filter(user_id.eq(user)).into_boxed()
+    } else {
+        lots.into_boxed()
+    };
+
+    // remove soft deleted lots
+    user_lots_query = user_lots_query.filter(status.ne(LotStatus::Deleted.as_str()));
+
+    if !params.statuses.is_empty() && exclude != Some(Facet::Status) {
+        user_lots_query = user_lots_query.filter(status.eq_any(&params.statuses));
+    }
+    if !params.categories.is_empty() && exclude != Some(Facet::Category) {
+        user_lots_query = user_lots_query.filter(category.eq_any(&params.categories));
+    }
+    if !params.conditions.is_empty() && exclude != Some(Facet::Condition) {
+        user_lots_query = user_lots_query.filter(condition.eq_any(&params.conditions));
+    }
+    if let Some(ref symbol) = params.currency_symbol {
+        user_lots_query = user_lots_query.filter(currency_symbol.eq(symbol));
+    }
+    if exclude != Some(Facet::Price) {
+        if let Some(ref min_price) = params.min_price {
+            user_lots_query = user_lots_query.filter(asking_price.ge(&min_price.0));
+        }
+        if let Some(ref max_price) = params.max_price {
+            user_lots_query = user_lots_query.filter(asking_price.le(&max_price.0));
+        }
+    }
+    for filter in &params.meta_data {
+        let (path, vars) = meta_data_path(filter);
+        user_lots_query = user_lots_query.filter(lot_meta_data_matches(meta_data, path, vars));
+    }
+    if !params.terms.is_empty() {
+        let ilike_terms: Vec<_> = params
+            .terms
+            .iter()
+            .map(|term| format!("%{}%", term.to_lowercase()))
+            .collect();
+
+        let ids = lowercase_terms(params);
+
+        // lower the description column and check if it contains any of the lowercase terms
+        // lower the title column and check if it contains any of the lowercase terms
+        // check if the external_id column is equal to any of the lowercase terms
+        user_lots_query = user_lots_query.filter(
+            lower(description)
+                .like(ilike_terms.join(""))
+                .or(lower(title).like(ilike_terms.join("")))
+                .or(external_id.eq_any(ids)),
+        );
+    }
+
+    user_lots_query
+}
+
+// builds the JSON path for a meta data filter along with its variables
+// values only ever reach the path as variables, and the key is quoted as a JSON path string
+fn meta_data_path(filter: &MetaDataFilter) -> (String, JsonValue) {
+    let mut conditions = Vec::new();
+    let mut vars = serde_json::Map::new();
+
+    if let Some(ref value) = filter.equals {
+        conditions.push("@ == $equals");
+        vars.insert("equals".to_string(), value.clone());
+    }
+    if let Some(min) = filter.min {
+        conditions.push("@ >= $min");
+        vars.insert("min".to_string(), json!(min));
+    }
+    if let Some(max) = filter.max {
+        conditions.push("@ <= $max");
+        vars.insert("max".to_string(), json!(max));
+    }
+
+    let key = serde_json::to_string(&filter.key).unwrap();
+    let path = if conditions.is_empty() {
+        format!("$.{}", key)
+    } else {
+        format!("$.{} ? ({})", key, conditions.join(" && "))
+    };
+
+    (path, JsonValue::Object(vars))
+}
+
+fn lowercase_terms(params: &FilterLots) -> Vec<String> {
+    params.terms.iter().map(|term| term.to_lowercase()).collect()
+}
+
+fn lot_sort(params: &FilterLots) -> LotSort {
+    match params.sort {
+        // relevance means nothing without terms
+        Some(LotSort::Relevance) if params.terms.is_empty() => LotSort::Newest,
+        Some(sort) => sort,
+        None if !params.terms.is_empty() => LotSort::Relevance,
+        None => LotSort::Newest,
+    }
+}
+
+// every sort ends on the id so that lots with equal sort keys keep a stable order across pages,
+// in the direction of the sort key where an index on (key, id) serves it
+fn order_lots(
+    query: lots::BoxedQuery<'_, Pg>,
+    sort: LotSort,
+    terms: Vec<String>,
+) -> lots::BoxedQuery<'_, Pg> {
+    use crate::schema::lots::dsl::*;
+
+    match sort {
+        LotSort::Newest => query.order((created_at.desc(), id.desc())),
+        LotSort::Oldest => query.order((created_at.asc(), id)),
+        LotSort::RecentlyUpdated => query.order((updated_at.desc(), id.desc())),
+        // lots without a price sort last either way
+        LotSort::PriceAsc => query.order((asking_price.asc().nulls_last(), id)),
+        LotSort::PriceDesc => query.order((asking_price.desc().nulls_last(), id)),
+        LotSort::Relevance => query.order((
+            lot_relevance(title, description, external_id, terms).desc(),
+            id,
+        )),
+        LotSort::TitleAsc => query.order((lower(title).asc(), id)),
+    }
+}
+
+// keeps the lots that come after the cursor lot in the given sort order
+fn after_cursor<'a>(
+    query: lots::BoxedQuery<'a, Pg>,
+    sort: LotSort,
+    cursor: &Lot,
+    cursor_relevance: i64,
+    terms: Vec<String>,
+) -> lots::BoxedQuery<'a, Pg> {
+    use crate::schema::lots::dsl::*;
+
+    let cursor_id = cursor.id;
+
+    match sort {
+        LotSort::Newest => query.filter(
+            created_at
+                .lt(cursor.created_at)
+                .or(created_at.eq(cursor.created_at).and(id.lt(cursor_id))),
+        ),
+        LotSort::Oldest => query.filter(
+            created_at
+                .gt(cursor.created_at)
+                .or(created_at.eq(cursor.created_at).and(id.gt(cursor_id))),
+        ),
+        LotSort::RecentlyUpdated => query.filter(
+            updated_at
+                .lt(cursor.updated_at)
+                .or(updated_at.eq(cursor.updated_at).and(id.lt(cursor_id))),
+        ),
+        LotSort::PriceAsc => match cursor.asking_price {
+            Some(ref price) => query.filter(
+                asking_price
+                    .gt(price.clone())
+                    .or(asking_price.eq(price.clone()).and(id.gt(cursor_id)))
+                    .or(asking_price.is_null()),
+            ),
+            None => query.filter(asking_price.is_null().and(id.gt(cursor_id))),
+        },
+        LotSort::PriceDesc => match cursor.asking_price {
+            Some(ref price) => query.filter(
+                asking_price
+                    .lt(price.clone())
+                    .or(asking_price.eq(price.clone()).and(id.gt(cursor_id)))
+                    .or(asking_price.is_null()),
+            ),
+            None => query.filter(asking_price.is_null().and(id.gt(cursor_id))),
+        },
+        LotSort::Relevance => {
+            let relevance = lot_relevance(title, description, external_id, terms);
+            query.filter(
+                relevance
+                    .clone()
+                    .lt(cursor_relevance)
+                    .or(relevance.eq(cursor_relevance).and(id.gt(cursor_id))),
+            )
+        }
+        LotSort::TitleAsc => query.filter(
+            lower(title)
+                .gt(lower(cursor.title.clone()))
+                .or(lower(title).eq(lower(cursor.title.clone())).and(id.gt(cursor_id))),
+        ),
+    }
+}
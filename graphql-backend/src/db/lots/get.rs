@@ -0,0 +1,44 @@
+use super::DbExecutor;
+use crate::{
+    app::lots::GetLot,
+    models::{Lot, LotImage, LotStatus, LotWithImages},
+    prelude::*,
+};
+use actix::prelude::*;
+use diesel::prelude::*;
+
+impl Message for GetLot {
+    type Result = Result<LotWithImages>;
+}
+
+impl Handler<GetLot> for DbExecutor {
+    type Result = Result<LotWithImages>;
+
+    fn handle(&mut self, msg: GetLot, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::lots::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let lot: Lot = lots
+            .filter(id.eq(msg.lot_id))
+            .select(Lot::as_select())
+            .get_result(conn)?;
+
+        // drafts are not out yet, and deleted lots only in the trash, for anyone else to see
+        let is_owner = msg.auth.is_some_and(|auth| auth.user.id == lot.user_id);
+        let hidden = [LotStatus::Drafted.as_str(), LotStatus::Deleted.as_str()];
+        if hidden.contains(&lot.status.as_str()) && !is_owner {
+            return Err(Error::NotFound(json!({ "error": "requested record was not found" })));
+        }
+
+        let images = LotImage::belonging_to(&lot)
+            .select(LotImage::as_select())
+            .load(conn)?;
+
+        Ok(LotWithImages {
+            lot,
+            images,
+            display_price: None,
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use super::create::insert_lot;
+use super::DbExecutor;
+use crate::app::lots::{ExportLotsAuthenticated, ImportLotsAuthenticated, ImportLotsResponse};
+use crate::config::config;
+use crate::error::ValidationError;
+use crate::models::{Lot, LotImage, LotStatus, LotWithImages};
+use crate::prelude::*;
+use crate::utils::lot_file::{parse_lots, write_lots};
+use actix::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+impl Message for ImportLotsAuthenticated {
+    type Result = Result<ImportLotsResponse>;
+}
+
+impl Handler<ImportLotsAuthenticated> for DbExecutor {
+    type Result = Result<ImportLotsResponse>;
+
+    fn handle(&mut self, msg: ImportLotsAuthenticated, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{catalog_items, currencies, lot_statuses};
+
+        let parsed = parse_lots(msg.params.format, &msg.params.lots)?;
+        let mut errors = parsed.errors;
+
+        let max_rows = config().limits.max_import_rows;
+        if parsed.rows.len() + errors.len() > max_rows {
+            return Err(Error::ValidationErrors(vec![ValidationError::new(
+                "lots".to_string(),
+                format!("at most {} lots are imported at once", max_rows),
+            )]));
+        }
+
+        let conn = &mut self.0.get()?;
+
+        // the checks that need the database, made for all rows before anything is inserted
+        let statuses: Vec<String> = lot_statuses::table
+            .select(lot_statuses::description)
+            .load(conn)?;
+        let symbols: Vec<String> = currencies::table
+            .select(currencies::symbol)
+            .load(conn)?;
+        let item_ids: Vec<Uuid> = parsed
+            .rows
+            .iter()
+            .filter_map(|row| row.lot.catalog_item_id.as_deref())
+            .filter_map(|item_id| Uuid::try_parse(item_id).ok())
+            .collect();
+        let known_items: Vec<Uuid> = catalog_items::table
+            .filter(catalog_items::id.eq_any(&item_ids))
+            .select(catalog_items::id)
+            .load(conn)?;
+
+        for row in &parsed.rows {
+            if let Some(row_status) = row.status.as_ref().filter(|s| !statuses.contains(s)) {
+                errors.push(
+                    ValidationError::new(
+                        "status".to_string(),
+                        format!("{} is not a lot status", row_status),
+                    )
+                    .on_line(row.line),
+                );
+            }
+            if let Some(symbol) = row.lot.currency_symbol.as_ref().filter(|s| !symbols.contains(s)) {
+                errors.push(
+                    ValidationError::new(
+                        "currency_symbol".to_string(),
+                        format!("{} is not a known currency", symbol),
+                    )
+                    .on_line(row.line),
+                );
+            }
+            let unknown_item = row
+                .lot
+                .catalog_item_id
+                .as_deref()
+                .and_then(|item_id| Uuid::try_parse(item_id).ok())
+                .filter(|item_id| !known_items.contains(item_id));
+            if let Some(item_id) = unknown_item {
+                errors.push(
+                    ValidationError::new(
+                        "catalog_item_id".to_string(),
+                        format!("{} is not in the catalog", item_id),
+                    )
+                    .on_line(row.line),
+                );
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(Error::ValidationErrors(errors));
+        }
+
+        let valid = parsed.rows.len();
+        if msg.params.dry_run {
+            return Ok(ImportLotsResponse { valid, imported: 0 });
+        }
+
+        // all of the lots or none of them
+        let imported = conn.transaction::<_, Error, _>(|connection| {
+            for row in parsed.rows {
+                insert_lot(msg.auth.user.id, row.lot, row.status, connection)?;
+            }
+            Ok(valid)
+        })?;
+
+        Ok(ImportLotsResponse { valid, imported })
+    }
+}
+
+impl Message for ExportLotsAuthenticated {
+    type Result = Result<String>;
+}
+
+impl Handler<ExportLotsAuthenticated> for DbExecutor {
+    type Result = Result<String>;
+
+    fn handle(&mut self, msg: ExportLotsAuthenticated, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::lot_images;
+        use crate::schema::lots::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        // lots and images inserted together share their created_at, the ties are broken
+        // so that the same lots are exported the same
+        let user_lots: Vec<Lot> = lots
+            .filter(user_id.eq(msg.auth.user.id))
+            .filter(status.ne(LotStatus::Deleted.as_str()))
+            .order((created_at, title, id))
+            .select(Lot::as_select())
+            .load(conn)?;
+
+        let images = LotImage::belonging_to(&user_lots)
+            .select(LotImage::as_select())
+            .order((lot_images::created_at, lot_images::image_url))
+            .load(conn)?;
+
+        let user_lots = images
+            .grouped_by(&user_lots)
+            .into_iter()
+            .zip(user_lots)
+            .map(|(imgs, lot)| LotWithImages {
+                lot,
+                images: imgs,
+                display_price: None,
+            })
+            .collect();
+
+        write_lots(msg.format, user_lots)
+    }
+}
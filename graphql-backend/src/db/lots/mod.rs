@@ -1,6 +1,11 @@
 mod create;
 mod delete;
-mod filter;
+mod facets;
+pub(super) mod filter;
+mod get;
+mod import;
+mod trending;
 mod update;
+mod views;
 
-use super::DbExecutor;
\ No newline at end of file
+use super::{DbExecutor, PooledConn};
@@ -0,0 +1,96 @@
+use super::DbExecutor;
+use crate::{
+    app::lots::{GetTrendingLots, RefreshTrendingLots},
+    config::config,
+    models::{Lot, LotImage, LotStatus, LotWithImages},
+    prelude::*,
+    schema::lots,
+};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+
+// the materialized view of the lot_views migration, which `diesel print-schema` leaves out
+diesel::table! {
+    trending_lots (time_window, lot_id) {
+        lot_id -> Uuid,
+        time_window -> Text,
+        score -> Float8,
+    }
+}
+
+diesel::joinable!(trending_lots -> lots (lot_id));
+diesel::allow_tables_to_appear_in_same_query!(trending_lots, lots);
+
+// views older than the longest time window no longer count
+const VIEW_RETENTION_DAYS: i64 = 7;
+
+impl Message for GetTrendingLots {
+    type Result = Result<Vec<LotWithImages>>;
+}
+
+impl Handler<GetTrendingLots> for DbExecutor {
+    type Result = Result<Vec<LotWithImages>>;
+
+    fn handle(&mut self, msg: GetTrendingLots, _: &mut Self::Context) -> Self::Result {
+        let conn = &mut self.0.get()?;
+
+        let limit = config().limits.page_size(msg.limit.map(i64::from), 20);
+
+        let mut query = lots::table
+            .inner_join(trending_lots::table)
+            .filter(trending_lots::time_window.eq(msg.window.as_str()))
+            .filter(lots::status.eq(LotStatus::ForSale.as_str()))
+            .into_boxed();
+        if let Some(ref category) = msg.category {
+            query = query.filter(lots::category.eq(category));
+        }
+
+        let trending: Vec<Lot> = query
+            .order((trending_lots::score.desc(), lots::id))
+            .limit(limit)
+            .select(Lot::as_select())
+            .load(conn)?;
+
+        let images = LotImage::belonging_to(&trending)
+            .select(LotImage::as_select())
+            .load(conn)?;
+
+        Ok(images
+            .grouped_by(&trending)
+            .into_iter()
+            .zip(trending)
+            .map(|(imgs, lot)| LotWithImages {
+                lot,
+                images: imgs,
+                display_price: None,
+            })
+            .collect())
+    }
+}
+
+impl Message for RefreshTrendingLots {
+    type Result = Result<()>;
+}
+
+impl Handler<RefreshTrendingLots> for DbExecutor {
+    type Result = Result<()>;
+
+    fn handle(&mut self, _: RefreshTrendingLots, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::lot_views;
+
+        let conn = &mut self.0.get()?;
+
+        let now = diesel::select(diesel::dsl::now).get_result::<NaiveDateTime>(conn)?;
+        diesel::delete(
+            lot_views::table
+                .filter(lot_views::viewed_at.lt(now - Duration::days(VIEW_RETENTION_DAYS))),
+        )
+        .execute(conn)?;
+
+        // the queries keep reading the previous scores while they are recomputed
+        diesel::sql_query("REFRESH MATERIALIZED VIEW CONCURRENTLY trending_lots").execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 use super::DbExecutor;
-use crate::models::{LotWithImages, LotImage};
+use crate::db::marketplace::link_marketplace_events;
+use crate::models::{LotWithImages, LotImage, LotStatus, OrderState};
 use crate::{app::lots::UpdateLotAuthenticated, models::Lot, prelude::*};
+use crate::utils::meta_data::validate_meta_data;
 use actix::prelude::*;
 use diesel::prelude::*;
 
@@ -17,14 +19,88 @@ impl Handler<UpdateLotAuthenticated> for DbExecutor {
         use crate::schema::lot_images::dsl::{lot_images, lot_id};
 
         let conn = &mut self.0.get()?;
+        let mut changes = msg.lot;
 
         conn.transaction(|connection| {
-            let updated: Lot = diesel::update(lots)
+            // lots in the trash are restored before they are changed
+            let existing: Lot = lots
                 .filter(user_id.eq(msg.auth.user.id))
-                .filter(id.eq(msg.lot.id))
-                .set(&msg.lot)
+                .filter(id.eq(changes.id))
+                .filter(status.ne(LotStatus::Deleted.as_str()))
+                .for_update()
+                .get_result(connection)?;
+
+            // the trash keeps the status to restore, which only deleteLot sets
+            if changes.status.as_deref() == Some(LotStatus::Deleted.as_str()) {
+                return Err(Error::UnprocessableEntity(json!({
+                    "error": "lots are deleted with deleteLot",
+                })));
+            }
+
+            // while an order is open the lot follows its state, see OrderState::lot_status
+            if changes.status.is_some() {
+                use crate::schema::orders;
+
+                let open_states: Vec<&str> = OrderState::ALL
+                    .iter()
+                    .filter(|state| state.is_open())
+                    .map(|state| state.as_str())
+                    .collect();
+                let open_orders: i64 = orders::table
+                    .filter(orders::lot_id.eq(existing.id))
+                    .filter(orders::state.eq_any(open_states))
+                    .count()
+                    .get_result(connection)?;
+
+                if open_orders > 0 {
+                    return Err(Error::UnprocessableEntity(json!({
+                        "error": "the status of a lot with an open order follows the order",
+                    })));
+                }
+            }
+
+            let new_status = changes.status.as_deref().unwrap_or(&existing.status);
+            let has_price = changes.asking_price.is_some() || existing.asking_price.is_some();
+
+            if new_status == LotStatus::ForSale.as_str() && !has_price {
+                return Err(Error::UnprocessableEntity(json!({
+                    "error": "an asking price is required to list a lot for sale",
+                })));
+            }
+
+            // the meta data has to fit the category it ends up with
+            if changes.category.is_some() || changes.meta_data.is_some() {
+                let new_category = changes.category.as_deref().unwrap_or(&existing.category);
+                let new_meta_data = changes
+                    .meta_data
+                    .as_ref()
+                    .or(existing.meta_data.as_ref())
+                    .cloned()
+                    .unwrap_or_else(|| json!({}));
+
+                validate_meta_data(new_category, &new_meta_data)?;
+            }
+
+            // a lot picked from the catalog is known by the item number of the catalog item,
+            // as on create
+            if let (None, Some(item_id)) = (&changes.external_id, changes.catalog_item_id) {
+                use crate::schema::catalog_items;
+                let item_external_id = catalog_items::table
+                    .find(item_id)
+                    .select(catalog_items::external_id)
+                    .first::<String>(connection)?;
+                changes.external_id = Some(item_external_id);
+            }
+
+            let updated: Lot = diesel::update(lots)
+                .filter(id.eq(existing.id))
+                .set(&changes)
                 .get_result(connection)?;
 
+            if changes.nft_address.is_some() || changes.token_id.is_some() {
+                link_marketplace_events(&updated, connection)?;
+            }
+
             // select all images for this lot
             let images: Vec<LotImage> = lot_images
                 .filter(lot_id.eq(updated.id))
@@ -32,8 +108,9 @@ impl Handler<UpdateLotAuthenticated> for DbExecutor {
                 .expect("Error loading posts");
 
             Ok(LotWithImages {
-                lot: updated.into(),
+                lot: updated,
                 images,
+                display_price: None,
             })
         })
     }
@@ -0,0 +1,65 @@
+use super::DbExecutor;
+use crate::{app::lots::RecordLotView, config::config, models::NewLotView, prelude::*};
+use actix::prelude::*;
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use uuid::Uuid;
+
+impl Message for RecordLotView {
+    type Result = Result<bool>;
+}
+
+impl Handler<RecordLotView> for DbExecutor {
+    type Result = Result<bool>;
+
+    fn handle(&mut self, msg: RecordLotView, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::lot_views::dsl::*;
+        use crate::schema::lots;
+
+        let viewer = match (msg.user_id, &msg.fingerprint) {
+            (Some(user), _) => format!("user {}", user),
+            (None, Some(hash)) => format!("fingerprint {}", hash),
+            (None, None) => return Ok(false),
+        };
+        let conn = &mut self.0.get()?;
+
+        conn.transaction(|connection| {
+            // the views of a viewer of a lot are counted one at a time, so that two requests
+            // at once do not both find no recent view
+            diesel::sql_query("SELECT pg_advisory_xact_lock(hashtextextended($1, 0))")
+                .bind::<Text, _>(format!("lot view {} {}", msg.lot_id, viewer))
+                .execute(connection)?;
+
+            let now = diesel::select(diesel::dsl::now).get_result::<NaiveDateTime>(connection)?;
+            let since = now - Duration::minutes(config().trending.view_dedup_minutes);
+
+            let mut recent = lot_views
+                .filter(lot_id.eq(msg.lot_id))
+                .filter(viewed_at.gt(since))
+                .into_boxed();
+            recent = match msg.user_id {
+                Some(user) => recent.filter(user_id.eq(user)),
+                None => recent.filter(fingerprint.eq(msg.fingerprint.clone())),
+            };
+            let seen = recent.select(id).first::<Uuid>(connection).optional()?;
+            if seen.is_some() {
+                return Ok(false);
+            }
+
+            // the fingerprint of a signed in viewer is left out, the user is known
+            diesel::insert_into(lot_views)
+                .values(NewLotView {
+                    lot_id: msg.lot_id,
+                    user_id: msg.user_id,
+                    fingerprint: msg.user_id.is_none().then(|| msg.fingerprint.clone()).flatten(),
+                })
+                .execute(connection)?;
+            diesel::update(lots::table.find(msg.lot_id))
+                .set(lots::view_count.eq(lots::view_count + 1))
+                .execute(connection)?;
+
+            Ok(true)
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use actix::prelude::*;
+use bigdecimal::BigDecimal;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::{DbExecutor, PooledConn};
+use crate::app::marketplace::{
+    GetChainCursor, GetMarketplaceListing, MarketplaceListing, RewindIndexedLogs, StoreIndexedLogs,
+};
+use crate::models::{ChainCursor, Lot, LotMint, MarketplaceEvent, MarketplaceEventKind};
+use crate::prelude::*;
+use crate::utils::CustomDecimal;
+
+// links the indexed events of the lot's NFT to the lot, and unlinks those of an NFT it no longer has
+pub(super) fn link_marketplace_events(lot: &Lot, conn: &mut PooledConn) -> Result<()> {
+    use crate::schema::marketplace_events::dsl::*;
+
+    diesel::update(marketplace_events.filter(lot_id.eq(lot.id)))
+        .set(lot_id.eq(None::<Uuid>))
+        .execute(conn)?;
+
+    if let (Some(ref address), Some(ref token)) = (&lot.nft_address, &lot.token_id) {
+        diesel::update(
+            marketplace_events
+                .filter(nft_address.eq(address))
+                .filter(token_id.eq(token)),
+        )
+        .set(lot_id.eq(lot.id))
+        .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+// message handler implementations ↓
+
+impl Message for GetChainCursor {
+    type Result = Result<Option<ChainCursor>>;
+}
+
+impl Handler<GetChainCursor> for DbExecutor {
+    type Result = Result<Option<ChainCursor>>;
+
+    fn handle(&mut self, msg: GetChainCursor, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::chain_cursors::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let cursor = chain_cursors
+            .find((msg.chain_id, msg.contract_address))
+            .select(ChainCursor::as_select())
+            .first(conn)
+            .optional()?;
+
+        Ok(cursor)
+    }
+}
+
+impl Message for StoreIndexedLogs {
+    type Result = Result<usize>;
+}
+
+impl Handler<StoreIndexedLogs> for DbExecutor {
+    type Result = Result<usize>;
+
+    fn handle(&mut self, msg: StoreIndexedLogs, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{chain_cursors, lot_mints, lots, marketplace_events, wallets};
+
+        let conn = &mut self.0.get()?;
+
+        conn.transaction(|connection| {
+            let mut events = msg.events;
+            for event in events.iter_mut() {
+                event.lot_id = lots::table
+                    .filter(lots::nft_address.eq(&event.nft_address))
+                    .filter(lots::token_id.eq(&event.token_id))
+                    .select(lots::id)
+                    .first::<Uuid>(connection)
+                    .optional()?;
+            }
+
+            // events stored by an earlier run that stopped before moving the cursor are skipped
+            let mut stored = diesel::insert_into(marketplace_events::table)
+                .values(&events)
+                .on_conflict((
+                    marketplace_events::chain_id,
+                    marketplace_events::tx_hash,
+                    marketplace_events::log_index,
+                ))
+                .do_nothing()
+                .execute(connection)?;
+
+            for mint in msg.mints {
+                // anyone can mint a token with the metadata of a lot, only mints to its owner count
+                let lot: Option<Lot> = lots::table
+                    .inner_join(wallets::table.on(wallets::user_id.eq(lots::user_id)))
+                    .filter(lots::id.eq(mint.lot_id))
+                    .filter(lots::nft_address.is_null())
+                    .filter(wallets::address.eq(&mint.recipient))
+                    .select(Lot::as_select())
+                    .first(connection)
+                    .optional()?;
+                let Some(lot) = lot else {
+                    continue;
+                };
+
+                let recorded = diesel::insert_into(lot_mints::table)
+                    .values(&mint)
+                    .on_conflict_do_nothing()
+                    .execute(connection)?;
+                if recorded == 0 {
+                    continue;
+                }
+
+                let minted: Lot = diesel::update(lots::table.find(lot.id))
+                    .set((
+                        lots::nft_address.eq(&mint.nft_address),
+                        lots::token_id.eq(&mint.token_id),
+                    ))
+                    .returning(Lot::as_returning())
+                    .get_result(connection)?;
+                link_marketplace_events(&minted, connection)?;
+                stored += recorded;
+            }
+
+            let cursor = ChainCursor {
+                chain_id: msg.chain_id,
+                contract_address: msg.contract_address,
+                block_number: msg.block_number,
+                block_hash: msg.block_hash,
+            };
+            diesel::insert_into(chain_cursors::table)
+                .values(&cursor)
+                .on_conflict((chain_cursors::chain_id, chain_cursors::contract_address))
+                .do_update()
+                .set((
+                    chain_cursors::block_number.eq(&cursor.block_number),
+                    chain_cursors::block_hash.eq(&cursor.block_hash),
+                ))
+                .execute(connection)?;
+
+            Ok(stored)
+        })
+    }
+}
+
+impl Message for RewindIndexedLogs {
+    type Result = Result<usize>;
+}
+
+impl Handler<RewindIndexedLogs> for DbExecutor {
+    type Result = Result<usize>;
+
+    fn handle(&mut self, msg: RewindIndexedLogs, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{chain_cursors, lot_mints, lots, marketplace_events};
+
+        let conn = &mut self.0.get()?;
+
+        conn.transaction(|connection| {
+            let mut removed = diesel::delete(
+                marketplace_events::table
+                    .filter(marketplace_events::chain_id.eq(msg.chain_id))
+                    .filter(marketplace_events::contract_address.eq(&msg.contract_address))
+                    .filter(marketplace_events::block_number.ge(msg.from_block)),
+            )
+            .execute(connection)?;
+
+            // lots minted in the blocks no longer are, until the mint is indexed again
+            let unminted: Vec<LotMint> = diesel::delete(
+                lot_mints::table
+                    .filter(lot_mints::chain_id.eq(msg.chain_id))
+                    .filter(lot_mints::nft_address.eq(&msg.contract_address))
+                    .filter(lot_mints::block_number.ge(msg.from_block)),
+            )
+            .returning(LotMint::as_returning())
+            .get_results(connection)?;
+            removed += unminted.len();
+
+            for mint in unminted {
+                let lot: Option<Lot> = diesel::update(
+                    lots::table
+                        .find(mint.lot_id)
+                        .filter(lots::nft_address.eq(&mint.nft_address))
+                        .filter(lots::token_id.eq(&mint.token_id)),
+                )
+                .set((
+                    lots::nft_address.eq(None::<String>),
+                    lots::token_id.eq(None::<BigDecimal>),
+                ))
+                .returning(Lot::as_returning())
+                .get_result(connection)
+                .optional()?;
+                if let Some(lot) = lot {
+                    link_marketplace_events(&lot, connection)?;
+                }
+            }
+
+            // without a hash the block before is taken as is, it has been confirmed long enough
+            diesel::update(chain_cursors::table.find((msg.chain_id, &msg.contract_address)))
+                .set((
+                    chain_cursors::block_number.eq(msg.from_block - 1),
+                    chain_cursors::block_hash.eq(None::<String>),
+                ))
+                .execute(connection)?;
+
+            Ok(removed)
+        })
+    }
+}
+
+impl Message for GetMarketplaceListing {
+    type Result = Result<Option<MarketplaceListing>>;
+}
+
+impl Handler<GetMarketplaceListing> for DbExecutor {
+    type Result = Result<Option<MarketplaceListing>>;
+
+    fn handle(&mut self, msg: GetMarketplaceListing, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::marketplace_events::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        // the latest event tells whether the NFT is still listed
+        let latest: Option<MarketplaceEvent> = marketplace_events
+            .filter(nft_address.eq(msg.nft_address))
+            .filter(token_id.eq(msg.token_id))
+            .order((block_number.desc(), log_index.desc()))
+            .select(MarketplaceEvent::as_select())
+            .first(conn)
+            .optional()?;
+
+        Ok(latest
+            .filter(|latest| {
+                MarketplaceEventKind::parse(&latest.event) == Some(MarketplaceEventKind::Listed)
+            })
+            .and_then(|latest| {
+                Some(MarketplaceListing {
+                    chain_id: latest.chain_id,
+                    contract_address: latest.contract_address,
+                    seller: latest.account,
+                    price: CustomDecimal(latest.price?),
+                    block_number: latest.block_number,
+                    tx_hash: latest.tx_hash,
+                })
+            }))
+    }
+}
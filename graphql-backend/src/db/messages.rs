@@ -0,0 +1,506 @@
+use actix::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::{DbExecutor, PooledConn};
+use crate::app::messages::{
+    BlockUser, ConversationResponse, GetBlockedUsers, GetConversations, GetMessages,
+    MarkConversationRead, MessageResponse, SendMessageAuthenticated, UnblockUser,
+};
+use crate::app::profiles::ProfileResponseInner;
+use crate::config::config;
+use crate::models::{
+    Conversation, Follower, Lot, LotStatus, Message as DirectMessage, NewConversation, NewMessage,
+    NewUserBlock, User,
+};
+use crate::prelude::*;
+use crate::utils::CustomDateTime;
+
+// message handler implementations ↓
+
+impl Message for SendMessageAuthenticated {
+    type Result = Result<MessageResponse>;
+}
+
+impl Handler<SendMessageAuthenticated> for DbExecutor {
+    type Result = Result<MessageResponse>;
+
+    fn handle(&mut self, msg: SendMessageAuthenticated, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{conversations, lots, messages, users};
+
+        let conn = &mut self.0.get()?;
+        let sender = msg.auth.user;
+
+        conn.transaction(|connection| {
+            let conversation = match msg.conversation_id {
+                Some(conversation_id) => {
+                    participant_conversation(conversation_id, sender.id, connection)?
+                }
+                None => {
+                    let lot = match msg.lot_id {
+                        Some(lot_id) => Some(
+                            lots::table
+                                .find(lot_id)
+                                .filter(lots::status.ne(LotStatus::Deleted.as_str()))
+                                .select(Lot::as_select())
+                                .first(connection)?,
+                        ),
+                        None => None,
+                    };
+
+                    // drafts stay private to their seller
+                    if let Some(ref lot) = lot {
+                        if lot.user_id != sender.id && lot.status == LotStatus::Drafted.as_str() {
+                            return Err(Error::NotFound(json!({
+                                "error": "requested record was not found",
+                            })));
+                        }
+                    }
+
+                    let recipient_id = match (msg.recipient, &lot) {
+                        (Some(username), _) => users::table
+                            .filter(users::username.eq(username))
+                            .select(users::id)
+                            .first::<Uuid>(connection)?,
+                        (None, Some(lot)) => lot.user_id,
+                        (None, None) => {
+                            return Err(Error::UnprocessableEntity(json!({
+                                "error": "a conversation id, recipient or lot id is required",
+                            })))
+                        }
+                    };
+
+                    if recipient_id == sender.id {
+                        return Err(Error::UnprocessableEntity(json!({
+                            "error": "You cannot message yourself",
+                        })));
+                    }
+
+                    let new_conversation = NewConversation::between(
+                        sender.id,
+                        recipient_id,
+                        lot.map(|lot| lot.id),
+                    );
+                    find_or_create_conversation(new_conversation, connection)?
+                }
+            };
+
+            let recipient_id = conversation.other_participant(sender.id);
+            if blocked_between(sender.id, recipient_id, connection)? {
+                return Err(Error::Forbidden(json!({
+                    "error": "messages between these users are blocked",
+                })));
+            }
+
+            let message = diesel::insert_into(messages::table)
+                .values(NewMessage {
+                    conversation_id: conversation.id,
+                    sender_id: sender.id,
+                    body: msg.body,
+                })
+                .returning(DirectMessage::as_returning())
+                .get_result(connection)?;
+
+            diesel::update(conversations::table.find(conversation.id))
+                .set(conversations::last_message_at.eq(message.created_at))
+                .execute(connection)?;
+
+            let sender_profile = profile(&sender, sender.id, connection)?;
+            Ok(message_response(message, sender_profile, sender.id))
+        })
+    }
+}
+
+impl Message for GetConversations {
+    type Result = Result<Vec<ConversationResponse>>;
+}
+
+impl Handler<GetConversations> for DbExecutor {
+    type Result = Result<Vec<ConversationResponse>>;
+
+    fn handle(&mut self, msg: GetConversations, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{conversations, messages, users};
+
+        let conn = &mut self.0.get()?;
+        let viewer = msg.auth.user;
+
+        // most recently active first
+        let threads: Vec<Conversation> = conversations::table
+            .filter(
+                conversations::user_a_id
+                    .eq(viewer.id)
+                    .or(conversations::user_b_id.eq(viewer.id)),
+            )
+            .order((conversations::last_message_at.desc(), conversations::id))
+            .select(Conversation::as_select())
+            .load(conn)?;
+
+        threads
+            .into_iter()
+            .map(|conversation| {
+                let other_user: User = users::table
+                    .find(conversation.other_participant(viewer.id))
+                    .first(conn)?;
+                let participant = profile(&other_user, viewer.id, conn)?;
+
+                let last_message: Option<DirectMessage> = messages::table
+                    .filter(messages::conversation_id.eq(conversation.id))
+                    .order((messages::created_at.desc(), messages::id.desc()))
+                    .select(DirectMessage::as_select())
+                    .first(conn)
+                    .optional()?;
+
+                let last_message = match last_message {
+                    Some(message) if message.sender_id == viewer.id => {
+                        let sender_profile = profile(&viewer, viewer.id, conn)?;
+                        Some(message_response(message, sender_profile, viewer.id))
+                    }
+                    Some(message) => Some(message_response(message, participant.clone(), viewer.id)),
+                    None => None,
+                };
+
+                let unread_count = messages::table
+                    .filter(messages::conversation_id.eq(conversation.id))
+                    .filter(messages::sender_id.ne(viewer.id))
+                    .filter(messages::read_at.is_null())
+                    .count()
+                    .get_result(conn)?;
+
+                Ok(ConversationResponse {
+                    id: conversation.id.to_string(),
+                    lot_id: conversation.lot_id.map(|lot_id| lot_id.to_string()),
+                    participant,
+                    last_message,
+                    unread_count,
+                    last_message_at: CustomDateTime(conversation.last_message_at),
+                    created_at: CustomDateTime(conversation.created_at),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Message for GetMessages {
+    type Result = Result<Vec<MessageResponse>>;
+}
+
+impl Handler<GetMessages> for DbExecutor {
+    type Result = Result<Vec<MessageResponse>>;
+
+    fn handle(&mut self, msg: GetMessages, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{messages, users};
+
+        let conn = &mut self.0.get()?;
+        let viewer = msg.auth.user;
+
+        let conversation = participant_conversation(msg.conversation_id, viewer.id, conn)?;
+        let limit = config().limits.page_size(msg.first.map(i64::from), 30);
+
+        let mut query = messages::table
+            .filter(messages::conversation_id.eq(conversation.id))
+            .into_boxed();
+
+        // newest first, so a cursor pages back in time
+        if let Some(cursor) = msg.cursor {
+            let cursor_created_at = messages::table
+                .filter(messages::conversation_id.eq(conversation.id))
+                .find(cursor)
+                .select(messages::created_at)
+                .first::<chrono::NaiveDateTime>(conn)?;
+
+            query = query.filter(
+                messages::created_at.lt(cursor_created_at).or(messages::created_at
+                    .eq(cursor_created_at)
+                    .and(messages::id.lt(cursor))),
+            );
+        }
+
+        let page: Vec<DirectMessage> = query
+            .order((messages::created_at.desc(), messages::id.desc()))
+            .limit(limit)
+            .select(DirectMessage::as_select())
+            .load(conn)?;
+
+        let other_user: User = users::table
+            .find(conversation.other_participant(viewer.id))
+            .first(conn)?;
+        let viewer_profile = profile(&viewer, viewer.id, conn)?;
+        let other_profile = profile(&other_user, viewer.id, conn)?;
+
+        Ok(page
+            .into_iter()
+            .map(|message| {
+                let sender_profile = if message.sender_id == viewer.id {
+                    viewer_profile.clone()
+                } else {
+                    other_profile.clone()
+                };
+                message_response(message, sender_profile, viewer.id)
+            })
+            .collect())
+    }
+}
+
+impl Message for MarkConversationRead {
+    type Result = Result<usize>;
+}
+
+impl Handler<MarkConversationRead> for DbExecutor {
+    type Result = Result<usize>;
+
+    fn handle(&mut self, msg: MarkConversationRead, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::messages::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let conversation = participant_conversation(msg.conversation_id, msg.auth.user.id, conn)?;
+
+        let marked = diesel::update(messages)
+            .filter(conversation_id.eq(conversation.id))
+            .filter(sender_id.ne(msg.auth.user.id))
+            .filter(read_at.is_null())
+            .set(read_at.eq(diesel::dsl::now))
+            .execute(conn)?;
+
+        Ok(marked)
+    }
+}
+
+impl Message for BlockUser {
+    type Result = Result<bool>;
+}
+
+impl Handler<BlockUser> for DbExecutor {
+    type Result = Result<bool>;
+
+    fn handle(&mut self, msg: BlockUser, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{user_blocks, users};
+
+        let conn = &mut self.0.get()?;
+
+        let blocked_id = users::table
+            .filter(users::username.eq(msg.username))
+            .select(users::id)
+            .first::<Uuid>(conn)?;
+
+        if blocked_id == msg.auth.user.id {
+            return Err(Error::UnprocessableEntity(
+                json!({"error": "You cannot block yourself"}),
+            ));
+        }
+
+        diesel::insert_into(user_blocks::table)
+            .values(NewUserBlock {
+                blocker_id: msg.auth.user.id,
+                blocked_id,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(true)
+    }
+}
+
+impl Message for UnblockUser {
+    type Result = Result<bool>;
+}
+
+impl Handler<UnblockUser> for DbExecutor {
+    type Result = Result<bool>;
+
+    fn handle(&mut self, msg: UnblockUser, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{user_blocks, users};
+
+        let conn = &mut self.0.get()?;
+
+        let blocked_id = users::table
+            .filter(users::username.eq(msg.username))
+            .select(users::id)
+            .first::<Uuid>(conn)?;
+
+        let deleted = diesel::delete(
+            user_blocks::table
+                .filter(user_blocks::blocker_id.eq(msg.auth.user.id))
+                .filter(user_blocks::blocked_id.eq(blocked_id)),
+        )
+        .execute(conn)?;
+
+        Ok(deleted > 0)
+    }
+}
+
+impl Message for GetBlockedUsers {
+    type Result = Result<Vec<ProfileResponseInner>>;
+}
+
+impl Handler<GetBlockedUsers> for DbExecutor {
+    type Result = Result<Vec<ProfileResponseInner>>;
+
+    fn handle(&mut self, msg: GetBlockedUsers, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{user_blocks, users};
+
+        let conn = &mut self.0.get()?;
+
+        let blocked: Vec<User> = users::table
+            .inner_join(user_blocks::table.on(user_blocks::blocked_id.eq(users::id)))
+            .filter(user_blocks::blocker_id.eq(msg.auth.user.id))
+            .order(users::username)
+            .select(users::all_columns)
+            .load(conn)?;
+
+        blocked
+            .iter()
+            .map(|user| profile(user, msg.auth.user.id, conn))
+            .collect()
+    }
+}
+
+// the conversation, as long as the user takes part in it
+// to anyone else it does not exist
+fn participant_conversation(
+    conversation_id: Uuid,
+    user_id: Uuid,
+    conn: &mut PooledConn,
+) -> Result<Conversation> {
+    use crate::schema::conversations::dsl::*;
+
+    let conversation: Conversation = conversations
+        .find(conversation_id)
+        .select(Conversation::as_select())
+        .first(conn)?;
+
+    if !conversation.includes(user_id) {
+        return Err(Error::NotFound(json!({
+            "error": "requested record was not found",
+        })));
+    }
+
+    Ok(conversation)
+}
+
+fn find_or_create_conversation(
+    new_conversation: NewConversation,
+    conn: &mut PooledConn,
+) -> Result<Conversation> {
+    use crate::schema::conversations::dsl::*;
+
+    let find = |conn: &mut PooledConn| {
+        let mut query = conversations
+            .filter(user_a_id.eq(new_conversation.user_a_id))
+            .filter(user_b_id.eq(new_conversation.user_b_id))
+            .into_boxed();
+        query = match new_conversation.lot_id {
+            Some(lot) => query.filter(lot_id.eq(lot)),
+            None => query.filter(lot_id.is_null()),
+        };
+        query
+            .select(Conversation::as_select())
+            .first(conn)
+            .optional()
+    };
+
+    if let Some(conversation) = find(conn)? {
+        return Ok(conversation);
+    }
+
+    // a concurrent first message may have started the conversation in the meantime
+    diesel::insert_into(conversations)
+        .values(&new_conversation)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    find(conn)?.ok_or(Error::InternalServerError)
+}
+
+// the conversations about a lot that is going away become the direct conversation
+// of their participants, merged into it when they already have one, as the unique
+// direct conversation of a pair would otherwise be taken twice
+pub(super) fn detach_lot_conversations(lot: Uuid, conn: &mut PooledConn) -> Result<()> {
+    use crate::schema::{conversations, messages};
+
+    let threads: Vec<Conversation> = conversations::table
+        .filter(conversations::lot_id.eq(lot))
+        .select(Conversation::as_select())
+        .load(conn)?;
+
+    for thread in threads {
+        let direct: Option<Conversation> = conversations::table
+            .filter(conversations::user_a_id.eq(thread.user_a_id))
+            .filter(conversations::user_b_id.eq(thread.user_b_id))
+            .filter(conversations::lot_id.is_null())
+            .select(Conversation::as_select())
+            .first(conn)
+            .optional()?;
+
+        let Some(direct) = direct else {
+            diesel::update(conversations::table.find(thread.id))
+                .set(conversations::lot_id.eq(None::<Uuid>))
+                .execute(conn)?;
+            continue;
+        };
+
+        diesel::update(messages::table.filter(messages::conversation_id.eq(thread.id)))
+            .set(messages::conversation_id.eq(direct.id))
+            .execute(conn)?;
+        diesel::update(conversations::table.find(direct.id))
+            .set(
+                conversations::last_message_at
+                    .eq(direct.last_message_at.max(thread.last_message_at)),
+            )
+            .execute(conn)?;
+        diesel::delete(conversations::table.find(thread.id)).execute(conn)?;
+    }
+
+    Ok(())
+}
+
+// either user blocking the other stops messages both ways
+pub(super) fn blocked_between(user: Uuid, other_user: Uuid, conn: &mut PooledConn) -> Result<bool> {
+    use crate::schema::user_blocks::dsl::*;
+
+    let blocks = user_blocks
+        .filter(
+            blocker_id
+                .eq(user)
+                .and(blocked_id.eq(other_user))
+                .or(blocker_id.eq(other_user).and(blocked_id.eq(user))),
+        )
+        .count()
+        .get_result::<i64>(conn)?;
+
+    Ok(blocks > 0)
+}
+
+pub(super) fn profile(user: &User, viewer: Uuid, conn: &mut PooledConn) -> Result<ProfileResponseInner> {
+    use crate::schema::followers;
+
+    let following = followers::table
+        .filter(followers::user_id.eq(user.id))
+        .filter(followers::follower_id.eq(viewer))
+        .first::<Follower>(conn)
+        .optional()?
+        .is_some();
+
+    Ok(ProfileResponseInner {
+        username: user.username.clone(),
+        bio: user.bio.clone(),
+        image: user.image.clone(),
+        following,
+    })
+}
+
+fn message_response(
+    message: DirectMessage,
+    sender: ProfileResponseInner,
+    viewer: Uuid,
+) -> MessageResponse {
+    MessageResponse {
+        id: message.id.to_string(),
+        conversation_id: message.conversation_id.to_string(),
+        body: message.body,
+        sender,
+        mine: message.sender_id == viewer,
+        read_at: message.read_at.map(CustomDateTime),
+        created_at: CustomDateTime(message.created_at),
+    }
+}
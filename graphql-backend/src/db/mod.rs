@@ -1,30 +1,199 @@
+mod admin;
+mod alerts;
 mod articles;
 mod auth;
+mod catalog;
 mod comments;
+mod exchange_rates;
 mod profiles;
 mod tags;
 mod users;
+mod wallets;
+mod watchlists;
 mod lots;
+mod marketplace;
+mod messages;
+mod nft;
+mod notifications;
+mod orders;
+mod reviews;
+#[cfg(test)]
+mod schema_check;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::prelude::*;
-use actix::prelude::{Actor, SyncContext};
+use futures::channel::oneshot;
+use actix::dev::Request;
+use actix::prelude::{Actor, Addr, Handler, MailboxError, Message, SyncArbiter, SyncContext};
 use diesel::{
     pg::PgConnection,
     r2d2::{self, ConnectionManager, Pool, PooledConnection},
 };
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
 pub type Conn = PgConnection;
 pub type PgPool = Pool<ConnectionManager<Conn>>;
 pub type PooledConn = PooledConnection<ConnectionManager<Conn>>;
 
+// the migrations folder, built into the binary
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
 pub struct DbExecutor(pub PgPool);
 
 impl Actor for DbExecutor {
     type Context = SyncContext<Self>;
 }
 
-pub fn new_pool<S: Into<String>>(database_url: S) -> Result<PgPool> {
+// the address of the db executors, keeping count of the messages waiting for one
+// and of the ones not handled yet
+#[derive(Clone)]
+pub struct Db {
+    addr: Addr<DbExecutor>,
+    queued: Arc<AtomicUsize>,
+    unfinished: Arc<AtomicUsize>,
+}
+
+impl Db {
+    pub fn start(threads: usize, pool: PgPool) -> Self {
+        Db {
+            addr: SyncArbiter::start(threads, move || DbExecutor(pool.clone())),
+            queued: Arc::new(AtomicUsize::new(0)),
+            unfinished: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // the message is handled within a span of the one it is sent from,
+    // so that the queries it runs show up under the resolver that asked for them
+    pub fn send<M>(&self, msg: M) -> impl Future<Output = Result<M::Result, MailboxError>>
+    where
+        M: Message + Send + 'static,
+        M::Result: Send,
+        DbExecutor: Handler<M, Result = M::Result>,
+    {
+        let queued = Counted::new(self.queued.clone());
+        let unfinished = Counted::new(self.unfinished.clone());
+        let span = tracing::info_span!("db message", message = message_name::<M>());
+        let (sender, receiver) = oneshot::channel();
+        let request = self.run(Box::new(move |executor, ctx| {
+            drop(queued);
+            let _entered = span.enter();
+            sender.send(Handler::<M>::handle(executor, msg, ctx)).ok();
+            drop(unfinished);
+        }));
+
+        async move {
+            request.await?;
+            // the sender is dropped without an answer if the handler panics
+            receiver.await.map_err(|_| MailboxError::Closed)
+        }
+    }
+
+    // apart from send, where the bound on M would stand in for the handler of Run
+    fn run(&self, handler: RunHandler) -> Request<DbExecutor, Run> {
+        self.addr.send(Run(handler))
+    }
+
+    pub fn mailbox_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    // waits for the messages sent so far to be handled, returning how many are left
+    // when the timeout runs out
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let unfinished = self.unfinished.load(Ordering::Relaxed);
+            if unfinished == 0 || Instant::now() >= deadline {
+                return unfinished;
+            }
+            actix_rt::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+fn message_name<M>() -> &'static str {
+    let name = std::any::type_name::<M>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+// counts a message until it is dropped, when an executor gets to it or is done with it,
+// or when it is dropped unhandled
+struct Counted(Arc<AtomicUsize>);
+
+impl Counted {
+    fn new(count: Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Counted(count)
+    }
+}
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// a message with its handler, run by whichever executor picks it up
+type RunHandler = Box<dyn FnOnce(&mut DbExecutor, &mut SyncContext<DbExecutor>) + Send>;
+
+struct Run(RunHandler);
+
+impl Message for Run {
+    type Result = ();
+}
+
+impl Handler<Run> for DbExecutor {
+    type Result = ();
+
+    fn handle(&mut self, msg: Run, ctx: &mut Self::Context) {
+        (msg.0)(self, ctx)
+    }
+}
+
+// answered as soon as an executor gets to it, to tell that the mailbox is moving
+pub struct Ping;
+
+impl Message for Ping {
+    type Result = Result<()>;
+}
+
+impl Handler<Ping> for DbExecutor {
+    type Result = Result<()>;
+
+    fn handle(&mut self, _: Ping, _: &mut Self::Context) -> Self::Result {
+        Ok(())
+    }
+}
+
+pub fn new_pool<S: Into<String>>(database_url: S, max_size: u32) -> Result<PgPool> {
     let manager = ConnectionManager::<Conn>::new(database_url.into());
-    let pool = r2d2::Pool::builder().build(manager)?;
+    let pool = r2d2::Pool::builder().max_size(max_size).build(manager)?;
     Ok(pool)
 }
+
+// applies the migrations the database does not have yet, returning their versions
+pub fn run_pending_migrations(pool: &PgPool) -> Result<Vec<String>, String> {
+    let conn = &mut pool.get().map_err(|e| e.to_string())?;
+    let versions = conn
+        .run_pending_migrations(MIGRATIONS)
+        .map_err(|e| e.to_string())?;
+
+    Ok(versions.iter().map(|version| version.to_string()).collect())
+}
+
+// the versions of the migrations the database does not have yet
+pub fn pending_migrations(pool: &PgPool) -> Result<Vec<String>, String> {
+    let conn = &mut pool.get().map_err(|e| e.to_string())?;
+    let migrations = conn
+        .pending_migrations(MIGRATIONS)
+        .map_err(|e| e.to_string())?;
+
+    Ok(migrations
+        .iter()
+        .map(|migration| migration.name().version().to_string())
+        .collect())
+}
@@ -0,0 +1,145 @@
+use actix::prelude::*;
+use bigdecimal::BigDecimal;
+use diesel::prelude::*;
+
+use super::DbExecutor;
+use crate::app::nft::{
+    metadata_url, toy_nft_address, GetLotMetadata, LotAttribute, LotMetadata, PrepareMint,
+    PreparedMint, MINT_PRICE_WEI,
+};
+use crate::models::{Lot, LotImage, LotStatus};
+use crate::prelude::*;
+use crate::utils::abi::{self, AbiValue};
+use crate::utils::CustomDecimal;
+
+// message handler implementations ↓
+
+impl Message for GetLotMetadata {
+    type Result = Result<LotMetadata>;
+}
+
+impl Handler<GetLotMetadata> for DbExecutor {
+    type Result = Result<LotMetadata>;
+
+    fn handle(&mut self, msg: GetLotMetadata, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{lot_images, lots};
+
+        let conn = &mut self.0.get()?;
+
+        // drafts are private, and deleted lots are gone
+        let lot: Lot = lots::table
+            .find(msg.lot_id)
+            .filter(lots::status.ne(LotStatus::Drafted.as_str()))
+            .filter(lots::status.ne(LotStatus::Deleted.as_str()))
+            .select(Lot::as_select())
+            .first(conn)?;
+
+        let image: Option<LotImage> = lot_images::table
+            .filter(lot_images::lot_id.eq(lot.id))
+            .order((
+                lot_images::is_thumbnail.desc(),
+                lot_images::created_at.asc(),
+            ))
+            .select(LotImage::as_select())
+            .first(conn)
+            .optional()?;
+
+        let mut attributes = vec![
+            LotAttribute {
+                trait_type: "Category".to_string(),
+                value: json!(lot.category),
+            },
+            LotAttribute {
+                trait_type: "Condition".to_string(),
+                value: json!(lot.condition),
+            },
+        ];
+        // the plain values of the meta data, i.e. the theme and year of a set
+        if let Some(serde_json::Value::Object(ref meta_data)) = lot.meta_data {
+            attributes.extend(
+                meta_data
+                    .iter()
+                    .filter(|(_, value)| {
+                        value.is_string() || value.is_number() || value.is_boolean()
+                    })
+                    .map(|(key, value)| LotAttribute {
+                        trait_type: key.clone(),
+                        value: value.clone(),
+                    }),
+            );
+        }
+
+        Ok(LotMetadata {
+            name: lot.title,
+            description: lot.description,
+            image: image.map(|image| image.image_url),
+            attributes,
+        })
+    }
+}
+
+impl Message for PrepareMint {
+    type Result = Result<PreparedMint>;
+}
+
+impl Handler<PrepareMint> for DbExecutor {
+    type Result = Result<PreparedMint>;
+
+    fn handle(&mut self, msg: PrepareMint, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{lots, wallets};
+
+        let conn = &mut self.0.get()?;
+
+        let toy_nft = toy_nft_address().ok_or_else(|| {
+            Error::UnprocessableEntity(json!({ "error": "minting lots is not enabled" }))
+        })?;
+
+        let lot: Lot = lots::table
+            .find(msg.lot_id)
+            .filter(lots::user_id.eq(msg.auth.user.id))
+            .filter(lots::status.ne(LotStatus::Deleted.as_str()))
+            .select(Lot::as_select())
+            .first(conn)?;
+
+        // the metadata of a draft is not served
+        if lot.status == LotStatus::Drafted.as_str() {
+            return Err(Error::UnprocessableEntity(json!({
+                "error": "publish the lot before minting it"
+            })));
+        }
+        if lot.nft_address.is_some() {
+            return Err(Error::UnprocessableEntity(json!({
+                "error": "the lot is already linked to a token"
+            })));
+        }
+
+        // minted to the wallet linked first
+        let recipient: String = wallets::table
+            .filter(wallets::user_id.eq(msg.auth.user.id))
+            .order(wallets::created_at.asc())
+            .select(wallets::address)
+            .first(conn)
+            .optional()?
+            .ok_or_else(|| {
+                Error::UnprocessableEntity(json!({ "error": "link a wallet to mint lots to" }))
+            })?;
+
+        let url = metadata_url(lot.id);
+        let data = abi::encode_call(
+            "mintTo(address,string)",
+            &[
+                AbiValue::Address(recipient.clone()),
+                AbiValue::String(url.clone()),
+            ],
+        )
+        .map_err(|_| Error::InternalServerError)?;
+
+        Ok(PreparedMint {
+            to: toy_nft,
+            data,
+            value: CustomDecimal(BigDecimal::from(MINT_PRICE_WEI)),
+            recipient,
+            metadata_url: url,
+        })
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use actix::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::{DbExecutor, PooledConn};
+use crate::app::notifications::{
+    ClaimDigests, CountUnreadNotifications, Digest, GetNotificationPreferences, GetNotifications,
+    MarkNotificationsRead, NotificationPreference as NotificationPreferenceResponse,
+    ReleaseDigest, UpdateNotificationPreferences,
+};
+use crate::config::config;
+use crate::models::{NewNotification, Notification, NotificationKind, NotificationPreference};
+use crate::prelude::*;
+
+// stores notifications, leaving out those of kinds their recipient disabled
+pub(super) fn notify(notifications: Vec<NewNotification>, conn: &mut PooledConn) -> Result<usize> {
+    use crate::schema::notification_preferences::dsl::*;
+
+    if notifications.is_empty() {
+        return Ok(0);
+    }
+
+    let recipients: Vec<Uuid> = notifications.iter().map(|n| n.user_id).collect();
+    let disabled: Vec<(Uuid, String)> = notification_preferences
+        .filter(user_id.eq_any(recipients))
+        .filter(enabled.eq(false))
+        .select((user_id, kind))
+        .load(conn)?;
+
+    let notifications: Vec<NewNotification> = notifications
+        .into_iter()
+        .filter(|n| !disabled.iter().any(|(user, k)| *user == n.user_id && *k == n.kind))
+        .collect();
+
+    let created = diesel::insert_into(crate::schema::notifications::table)
+        .values(&notifications)
+        .execute(conn)?;
+
+    Ok(created)
+}
+
+// message handler implementations ↓
+
+impl Message for GetNotifications {
+    type Result = Result<Vec<Notification>>;
+}
+
+impl Handler<GetNotifications> for DbExecutor {
+    type Result = Result<Vec<Notification>>;
+
+    fn handle(&mut self, msg: GetNotifications, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::notifications::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let limit = config().limits.page_size(msg.first.map(i64::from), 20);
+
+        let mut query = notifications
+            .filter(user_id.eq(msg.auth.user.id))
+            .into_boxed();
+
+        if msg.unread_only {
+            query = query.filter(read_at.is_null());
+        }
+
+        // newest first, with the id keeping notifications created at once in a stable order
+        if let Some(after) = msg.after {
+            let cursor_created_at = notifications
+                .filter(user_id.eq(msg.auth.user.id))
+                .find(after)
+                .select(created_at)
+                .first::<chrono::NaiveDateTime>(conn)?;
+
+            query = query.filter(
+                created_at
+                    .lt(cursor_created_at)
+                    .or(created_at.eq(cursor_created_at).and(id.gt(after))),
+            );
+        }
+
+        let page = query
+            .order((created_at.desc(), id))
+            .limit(limit)
+            .select(Notification::as_select())
+            .load(conn)?;
+
+        Ok(page)
+    }
+}
+
+impl Message for MarkNotificationsRead {
+    type Result = Result<usize>;
+}
+
+impl Handler<MarkNotificationsRead> for DbExecutor {
+    type Result = Result<usize>;
+
+    fn handle(&mut self, msg: MarkNotificationsRead, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::notifications::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let mut query = diesel::update(notifications)
+            .filter(user_id.eq(msg.auth.user.id))
+            .filter(read_at.is_null())
+            .into_boxed();
+
+        if let Some(ids) = msg.ids {
+            query = query.filter(id.eq_any(ids));
+        }
+
+        let marked = query.set(read_at.eq(diesel::dsl::now)).execute(conn)?;
+
+        Ok(marked)
+    }
+}
+
+impl Message for CountUnreadNotifications {
+    type Result = Result<i64>;
+}
+
+impl Handler<CountUnreadNotifications> for DbExecutor {
+    type Result = Result<i64>;
+
+    fn handle(&mut self, msg: CountUnreadNotifications, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::notifications::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let unread = notifications
+            .filter(user_id.eq(msg.user_id))
+            .filter(read_at.is_null())
+            .count()
+            .get_result(conn)?;
+
+        Ok(unread)
+    }
+}
+
+impl Message for GetNotificationPreferences {
+    type Result = Result<Vec<NotificationPreferenceResponse>>;
+}
+
+impl Handler<GetNotificationPreferences> for DbExecutor {
+    type Result = Result<Vec<NotificationPreferenceResponse>>;
+
+    fn handle(&mut self, msg: GetNotificationPreferences, _: &mut Self::Context) -> Self::Result {
+        let conn = &mut self.0.get()?;
+
+        preferences_response(msg.auth.user.id, conn)
+    }
+}
+
+impl Message for UpdateNotificationPreferences {
+    type Result = Result<Vec<NotificationPreferenceResponse>>;
+}
+
+impl Handler<UpdateNotificationPreferences> for DbExecutor {
+    type Result = Result<Vec<NotificationPreferenceResponse>>;
+
+    fn handle(&mut self, msg: UpdateNotificationPreferences, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::notification_preferences::dsl::*;
+        use diesel::upsert::excluded;
+
+        let conn = &mut self.0.get()?;
+
+        let preferences: Vec<NotificationPreference> = msg
+            .preferences
+            .into_iter()
+            .map(|preference| NotificationPreference {
+                user_id: msg.auth.user.id,
+                kind: preference.kind.as_str().to_string(),
+                enabled: preference.enabled,
+                email_digest: preference.email_digest,
+            })
+            .collect();
+
+        diesel::insert_into(notification_preferences)
+            .values(&preferences)
+            .on_conflict((user_id, kind))
+            .do_update()
+            .set((
+                enabled.eq(excluded(enabled)),
+                email_digest.eq(excluded(email_digest)),
+            ))
+            .execute(conn)?;
+
+        preferences_response(msg.auth.user.id, conn)
+    }
+}
+
+// the preferences for every kind, with the defaults for kinds the user has not set
+fn preferences_response(
+    user: Uuid,
+    conn: &mut PooledConn,
+) -> Result<Vec<NotificationPreferenceResponse>> {
+    use crate::schema::notification_preferences::dsl::*;
+
+    let stored: Vec<NotificationPreference> = notification_preferences
+        .filter(user_id.eq(user))
+        .select(NotificationPreference::as_select())
+        .load(conn)?;
+
+    Ok(NotificationKind::ALL
+        .into_iter()
+        .map(|notification_kind| {
+            let preference = stored.iter().find(|p| p.kind == notification_kind.as_str());
+            NotificationPreferenceResponse {
+                kind: notification_kind,
+                enabled: preference.map(|p| p.enabled).unwrap_or(true),
+                email_digest: preference.map(|p| p.email_digest).unwrap_or(false),
+            }
+        })
+        .collect())
+}
+
+impl Message for ClaimDigests {
+    type Result = Result<Vec<Digest>>;
+}
+
+impl Handler<ClaimDigests> for DbExecutor {
+    type Result = Result<Vec<Digest>>;
+
+    fn handle(&mut self, _: ClaimDigests, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{notification_preferences, notifications, users};
+
+        let conn = &mut self.0.get()?;
+
+        // unread notifications not yet emailed, of kinds the verified recipient wants in the digest
+        // wallet accounts have no email to send it to, and disabled accounts get none
+        let due = notifications::table
+            .inner_join(
+                notification_preferences::table.on(notification_preferences::user_id
+                    .eq(notifications::user_id)
+                    .and(notification_preferences::kind.eq(notifications::kind))),
+            )
+            .inner_join(users::table)
+            .filter(notification_preferences::email_digest.eq(true))
+            .filter(users::email_verified.eq(true))
+            .filter(users::email.is_not_null())
+            .filter(users::is_disabled.eq(false))
+            .filter(notifications::emailed_at.is_null())
+            .filter(notifications::read_at.is_null())
+            .select(notifications::id)
+            .load::<Uuid>(conn)?;
+
+        // only the update that sets emailed_at claims a notification
+        let mut claimed: Vec<Notification> = diesel::update(notifications::table)
+            .filter(notifications::id.eq_any(due))
+            .filter(notifications::emailed_at.is_null())
+            .filter(notifications::read_at.is_null())
+            .set(notifications::emailed_at.eq(diesel::dsl::now))
+            .returning(Notification::as_returning())
+            .get_results(conn)?;
+        claimed.sort_by_key(|n| n.created_at);
+
+        let mut claimed_by_user: HashMap<Uuid, Vec<Notification>> = HashMap::new();
+        for notification in claimed {
+            claimed_by_user
+                .entry(notification.user_id)
+                .or_default()
+                .push(notification);
+        }
+
+        let recipients: Vec<(Uuid, String, String)> = users::table
+            .filter(users::id.eq_any(claimed_by_user.keys().copied().collect::<Vec<Uuid>>()))
+            .filter(users::email.is_not_null())
+            .select((users::id, users::email.assume_not_null(), users::username))
+            .load(conn)?;
+
+        let digests = recipients
+            .into_iter()
+            .filter_map(|(recipient, email, username)| {
+                claimed_by_user
+                    .remove(&recipient)
+                    .map(|notifications| Digest {
+                        email,
+                        username,
+                        notifications,
+                    })
+            })
+            .collect();
+
+        Ok(digests)
+    }
+}
+
+impl Message for ReleaseDigest {
+    type Result = Result<usize>;
+}
+
+impl Handler<ReleaseDigest> for DbExecutor {
+    type Result = Result<usize>;
+
+    fn handle(&mut self, msg: ReleaseDigest, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::notifications::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let released = diesel::update(notifications)
+            .filter(id.eq_any(msg.ids))
+            .set(emailed_at.eq(None::<chrono::NaiveDateTime>))
+            .execute(conn)?;
+
+        Ok(released)
+    }
+}
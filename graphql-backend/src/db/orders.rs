@@ -0,0 +1,309 @@
+use actix::prelude::*;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::messages::{blocked_between, profile};
+use super::notifications::notify;
+use super::{DbExecutor, PooledConn};
+use crate::app::orders::{
+    GetOrder, GetOrders, OrderResponse, PlaceOrderAuthenticated, UpdateOrderStateAuthenticated,
+};
+use crate::config::config;
+use crate::models::{
+    Lot, LotStatus, NewNotification, NewOrder, NotificationPayload, Order, OrderRole, OrderState,
+    OrderUpdatedPayload, User,
+};
+use crate::prelude::*;
+use crate::utils::{CustomDateTime, CustomDecimal};
+
+// message handler implementations ↓
+
+impl Message for PlaceOrderAuthenticated {
+    type Result = Result<OrderResponse>;
+}
+
+impl Handler<PlaceOrderAuthenticated> for DbExecutor {
+    type Result = Result<OrderResponse>;
+
+    fn handle(&mut self, msg: PlaceOrderAuthenticated, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{lots, orders};
+
+        let conn = &mut self.0.get()?;
+        let buyer = msg.auth.user;
+
+        conn.transaction(|connection| {
+            // locking the lot keeps two buyers from ordering it at once
+            let lot: Lot = lots::table
+                .find(msg.lot_id)
+                .for_update()
+                .select(Lot::as_select())
+                .first(connection)?;
+
+            if lot.user_id == buyer.id {
+                return Err(Error::UnprocessableEntity(json!({
+                    "error": "You cannot order your own lot",
+                })));
+            }
+            // drafts stay private to their seller
+            if lot.status == LotStatus::Drafted.as_str()
+                || lot.status == LotStatus::Deleted.as_str()
+            {
+                return Err(Error::NotFound(json!({
+                    "error": "requested record was not found",
+                })));
+            }
+            if lot.status != LotStatus::ForSale.as_str() {
+                return Err(Error::UnprocessableEntity(json!({
+                    "error": "only lots for sale can be ordered",
+                })));
+            }
+            // an order takes the whole lot, so the lot could not stay up for the rest
+            if lot.quantity > 1 {
+                return Err(Error::UnprocessableEntity(json!({
+                    "error": "lots with a quantity of more than one cannot be ordered",
+                })));
+            }
+            let price = lot.asking_price.clone().ok_or_else(|| {
+                Error::UnprocessableEntity(json!({
+                    "error": "the lot has no asking price",
+                }))
+            })?;
+            if blocked_between(buyer.id, lot.user_id, connection)? {
+                return Err(Error::Forbidden(json!({
+                    "error": "orders between these users are blocked",
+                })));
+            }
+
+            let order = diesel::insert_into(orders::table)
+                .values(NewOrder {
+                    lot_id: lot.id,
+                    buyer_id: buyer.id,
+                    seller_id: lot.user_id,
+                    price,
+                    currency_symbol: lot.currency_symbol.clone(),
+                    shipping_address: serde_json::to_value(&msg.shipping_address).unwrap(),
+                })
+                .returning(Order::as_returning())
+                .get_result(connection)?;
+
+            sync_lot_status(&order, connection)?;
+            notify_other_party(&order, &lot, &buyer, connection)?;
+
+            order_response(order, lot.title, buyer.id, connection)
+        })
+    }
+}
+
+impl Message for UpdateOrderStateAuthenticated {
+    type Result = Result<OrderResponse>;
+}
+
+impl Handler<UpdateOrderStateAuthenticated> for DbExecutor {
+    type Result = Result<OrderResponse>;
+
+    fn handle(
+        &mut self,
+        msg: UpdateOrderStateAuthenticated,
+        _: &mut Self::Context,
+    ) -> Self::Result {
+        use crate::schema::{lots, orders};
+
+        let conn = &mut self.0.get()?;
+        let user = msg.auth.user;
+
+        conn.transaction(|connection| {
+            let order: Order = orders::table
+                .find(msg.order_id)
+                .for_update()
+                .select(Order::as_select())
+                .first(connection)?;
+
+            // to anyone but the buyer and seller the order does not exist
+            let role = order.role(user.id).ok_or_else(|| {
+                Error::NotFound(json!({
+                    "error": "requested record was not found",
+                }))
+            })?;
+
+            let state = OrderState::parse(&order.state).ok_or(Error::InternalServerError)?;
+            if !state.can_become(msg.state, role) {
+                return Err(Error::UnprocessableEntity(json!({
+                    "error": format!(
+                        "the {} cannot mark an order that is {} {}",
+                        role.as_str(),
+                        state.as_str(),
+                        msg.state.as_str(),
+                    ),
+                })));
+            }
+
+            let order: Order = diesel::update(orders::table.find(order.id))
+                .set((
+                    orders::state.eq(msg.state.as_str()),
+                    orders::tracking_number.eq(msg.tracking_number.or(order.tracking_number)),
+                ))
+                .returning(Order::as_returning())
+                .get_result(connection)?;
+
+            let lot: Lot = lots::table
+                .find(order.lot_id)
+                .select(Lot::as_select())
+                .first(connection)?;
+
+            sync_lot_status(&order, connection)?;
+            notify_other_party(&order, &lot, &user, connection)?;
+
+            order_response(order, lot.title, user.id, connection)
+        })
+    }
+}
+
+impl Message for GetOrders {
+    type Result = Result<Vec<OrderResponse>>;
+}
+
+impl Handler<GetOrders> for DbExecutor {
+    type Result = Result<Vec<OrderResponse>>;
+
+    fn handle(&mut self, msg: GetOrders, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{lots, orders::dsl::*};
+
+        let conn = &mut self.0.get()?;
+        let viewer = msg.auth.user.id;
+
+        let limit = config().limits.page_size(msg.first.map(i64::from), 20);
+
+        let mut query = match msg.role {
+            Some(OrderRole::Buyer) => orders.filter(buyer_id.eq(viewer)).into_boxed(),
+            Some(OrderRole::Seller) => orders.filter(seller_id.eq(viewer)).into_boxed(),
+            None => orders
+                .filter(buyer_id.eq(viewer).or(seller_id.eq(viewer)))
+                .into_boxed(),
+        };
+
+        if !msg.states.is_empty() {
+            let states: Vec<&str> = msg.states.iter().map(|s| s.as_str()).collect();
+            query = query.filter(state.eq_any(states));
+        }
+
+        // newest first, with the id keeping orders placed at once in a stable order
+        if let Some(after) = msg.after {
+            let cursor_created_at = orders
+                .filter(buyer_id.eq(viewer).or(seller_id.eq(viewer)))
+                .find(after)
+                .select(created_at)
+                .first::<chrono::NaiveDateTime>(conn)?;
+
+            query = query.filter(
+                created_at
+                    .lt(cursor_created_at)
+                    .or(created_at.eq(cursor_created_at).and(id.gt(after))),
+            );
+        }
+
+        let page: Vec<Order> = query
+            .order((created_at.desc(), id))
+            .limit(limit)
+            .select(Order::as_select())
+            .load(conn)?;
+
+        page.into_iter()
+            .map(|order| {
+                let lot_title = lots::table
+                    .find(order.lot_id)
+                    .select(lots::title)
+                    .first::<String>(conn)?;
+                order_response(order, lot_title, viewer, conn)
+            })
+            .collect()
+    }
+}
+
+impl Message for GetOrder {
+    type Result = Result<OrderResponse>;
+}
+
+impl Handler<GetOrder> for DbExecutor {
+    type Result = Result<OrderResponse>;
+
+    fn handle(&mut self, msg: GetOrder, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{lots, orders};
+
+        let conn = &mut self.0.get()?;
+        let viewer = msg.auth.user.id;
+
+        let order: Order = orders::table
+            .find(msg.order_id)
+            .filter(orders::buyer_id.eq(viewer).or(orders::seller_id.eq(viewer)))
+            .select(Order::as_select())
+            .first(conn)?;
+
+        let lot_title = lots::table
+            .find(order.lot_id)
+            .select(lots::title)
+            .first::<String>(conn)?;
+
+        order_response(order, lot_title, viewer, conn)
+    }
+}
+
+// the lot follows the state of its order, see OrderState::lot_status
+fn sync_lot_status(order: &Order, conn: &mut PooledConn) -> Result<()> {
+    use crate::schema::lots::dsl::*;
+
+    let order_state = OrderState::parse(&order.state).ok_or(Error::InternalServerError)?;
+
+    diesel::update(lots.find(order.lot_id))
+        .set(status.eq(order_state.lot_status().as_str()))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+fn notify_other_party(order: &Order, lot: &Lot, actor: &User, conn: &mut PooledConn) -> Result<()> {
+    let payload = NotificationPayload::OrderUpdated(OrderUpdatedPayload {
+        order_id: order.id.to_string(),
+        lot_id: lot.id.to_string(),
+        title: lot.title.clone(),
+        state: order.state.clone(),
+        username: actor.username.clone(),
+    });
+    notify(
+        vec![NewNotification::new(order.other_party(actor.id), payload)],
+        conn,
+    )?;
+
+    Ok(())
+}
+
+fn order_response(
+    order: Order,
+    lot_title: String,
+    viewer: Uuid,
+    conn: &mut PooledConn,
+) -> Result<OrderResponse> {
+    use crate::schema::users;
+
+    let role = order.role(viewer).ok_or(Error::InternalServerError)?;
+    let state = OrderState::parse(&order.state).ok_or(Error::InternalServerError)?;
+
+    let buyer: User = users::table.find(order.buyer_id).first(conn)?;
+    let seller: User = users::table.find(order.seller_id).first(conn)?;
+
+    Ok(OrderResponse {
+        id: order.id.to_string(),
+        lot_id: order.lot_id.to_string(),
+        lot_title,
+        buyer: profile(&buyer, viewer, conn)?,
+        seller: profile(&seller, viewer, conn)?,
+        role,
+        state,
+        price: CustomDecimal(order.price),
+        currency_symbol: order.currency_symbol,
+        shipping_address: serde_json::from_value(order.shipping_address)
+            .map_err(|_| Error::InternalServerError)?,
+        tracking_number: order.tracking_number,
+        created_at: CustomDateTime(order.created_at),
+        updated_at: CustomDateTime(order.updated_at),
+    })
+}
@@ -1,11 +1,18 @@
+use std::collections::HashMap;
+
 use actix::prelude::*;
 use diesel::prelude::*;
+use uuid::Uuid;
 
+use super::notifications::notify;
 use super::DbExecutor;
 use crate::app::profiles::{
-    FollowProfile, GetProfile, ProfileResponse, ProfileResponseInner, UnfollowProfile,
+    FollowProfile, GetProfile, GetProfilesByIds, ProfileResponse, ProfileResponseInner,
+    UnfollowProfile,
+};
+use crate::models::{
+    Follower, NewFollower, NewFollowerPayload, NewNotification, NotificationPayload, User,
 };
-use crate::models::{Follower, NewFollower, User};
 use crate::prelude::*;
 
 // message handler implementations ↓
@@ -48,6 +55,47 @@ impl Handler<GetProfile> for DbExecutor {
     }
 }
 
+impl Message for GetProfilesByIds {
+    type Result = Result<HashMap<Uuid, ProfileResponseInner>>;
+}
+
+impl Handler<GetProfilesByIds> for DbExecutor {
+    type Result = Result<HashMap<Uuid, ProfileResponseInner>>;
+
+    fn handle(&mut self, msg: GetProfilesByIds, _: &mut Self::Context) -> Self::Result {
+        let conn = &mut self.0.get()?;
+
+        let found: Vec<User> = {
+            use crate::schema::users::dsl::*;
+            users.filter(id.eq_any(&msg.user_ids)).load(conn)?
+        };
+
+        use crate::schema::followers::dsl::*;
+
+        let followed: Vec<Uuid> = match msg.viewer_id {
+            Some(viewer_id) => followers
+                .filter(user_id.eq_any(&msg.user_ids))
+                .filter(follower_id.eq(viewer_id))
+                .select(user_id)
+                .load(conn)?,
+            None => Vec::new(),
+        };
+
+        Ok(found
+            .into_iter()
+            .map(|user| {
+                let profile = ProfileResponseInner {
+                    following: followed.contains(&user.id),
+                    username: user.username,
+                    bio: user.bio,
+                    image: user.image,
+                };
+                (user.id, profile)
+            })
+            .collect())
+    }
+}
+
 impl Message for FollowProfile {
     type Result = Result<ProfileResponse>;
 }
@@ -79,6 +127,16 @@ impl Handler<FollowProfile> for DbExecutor {
             })
             .execute(conn)?;
 
+        notify(
+            vec![NewNotification::new(
+                user_a.id,
+                NotificationPayload::NewFollower(NewFollowerPayload {
+                    username: user_b.username,
+                }),
+            )],
+            conn,
+        )?;
+
         Ok(ProfileResponse {
             profile: ProfileResponseInner {
                 username: user_a.username,
@@ -0,0 +1,192 @@
+use actix::prelude::*;
+use bigdecimal::{BigDecimal, RoundingMode};
+use diesel::dsl::avg;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use super::messages::profile;
+use super::{DbExecutor, PooledConn};
+use crate::app::profiles::ProfileResponseInner;
+use crate::app::reviews::{
+    GetProfileStats, GetReviews, ProfileStats, ReviewOrderAuthenticated, ReviewResponse,
+};
+use crate::config::config;
+use crate::models::{NewReview, Order, OrderState, Review, User};
+use crate::prelude::*;
+use crate::utils::{CustomDateTime, CustomDecimal};
+
+// message handler implementations ↓
+
+impl Message for ReviewOrderAuthenticated {
+    type Result = Result<ReviewResponse>;
+}
+
+impl Handler<ReviewOrderAuthenticated> for DbExecutor {
+    type Result = Result<ReviewResponse>;
+
+    fn handle(&mut self, msg: ReviewOrderAuthenticated, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{orders, reviews};
+
+        let conn = &mut self.0.get()?;
+        let reviewer = msg.auth.user;
+
+        let order: Order = orders::table
+            .find(msg.order_id)
+            .filter(
+                orders::buyer_id
+                    .eq(reviewer.id)
+                    .or(orders::seller_id.eq(reviewer.id)),
+            )
+            .select(Order::as_select())
+            .first(conn)?;
+
+        if order.state != OrderState::Delivered.as_str() {
+            return Err(Error::UnprocessableEntity(json!({
+                "error": "only delivered orders can be reviewed",
+            })));
+        }
+
+        let review: Option<Review> = diesel::insert_into(reviews::table)
+            .values(NewReview {
+                order_id: order.id,
+                reviewer_id: reviewer.id,
+                reviewee_id: order.other_party(reviewer.id),
+                rating: msg.rating,
+                body: msg.body,
+            })
+            .on_conflict_do_nothing()
+            .returning(Review::as_returning())
+            .get_result(conn)
+            .optional()?;
+
+        let review = review.ok_or_else(|| {
+            Error::UnprocessableEntity(json!({
+                "error": "You already reviewed this order",
+            }))
+        })?;
+
+        let reviewer_profile = profile(&reviewer, reviewer.id, conn)?;
+        review_response(review, order, reviewer_profile, conn)
+    }
+}
+
+impl Message for GetReviews {
+    type Result = Result<Vec<ReviewResponse>>;
+}
+
+impl Handler<GetReviews> for DbExecutor {
+    type Result = Result<Vec<ReviewResponse>>;
+
+    fn handle(&mut self, msg: GetReviews, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{orders, reviews::dsl::*, users};
+
+        let conn = &mut self.0.get()?;
+        // nobody follows anyone as the nil user
+        let viewer = msg.auth.map(|auth| auth.user.id).unwrap_or_default();
+
+        let reviewee: User = users::table
+            .filter(users::username.eq(msg.username))
+            .first(conn)?;
+
+        let limit = config().limits.page_size(msg.first.map(i64::from), 20);
+
+        let mut query = reviews.filter(reviewee_id.eq(reviewee.id)).into_boxed();
+
+        // newest first, with the id keeping reviews left at once in a stable order
+        if let Some(after) = msg.after {
+            let cursor_created_at = reviews
+                .filter(reviewee_id.eq(reviewee.id))
+                .find(after)
+                .select(created_at)
+                .first::<chrono::NaiveDateTime>(conn)?;
+
+            query = query.filter(
+                created_at
+                    .lt(cursor_created_at)
+                    .or(created_at.eq(cursor_created_at).and(id.gt(after))),
+            );
+        }
+
+        let page: Vec<Review> = query
+            .order((created_at.desc(), id))
+            .limit(limit)
+            .select(Review::as_select())
+            .load(conn)?;
+
+        page.into_iter()
+            .map(|review| {
+                let order: Order = orders::table
+                    .find(review.order_id)
+                    .select(Order::as_select())
+                    .first(conn)?;
+                let reviewer: User = users::table.find(review.reviewer_id).first(conn)?;
+                let reviewer_profile = profile(&reviewer, viewer, conn)?;
+                review_response(review, order, reviewer_profile, conn)
+            })
+            .collect()
+    }
+}
+
+impl Message for GetProfileStats {
+    type Result = Result<ProfileStats>;
+}
+
+impl Handler<GetProfileStats> for DbExecutor {
+    type Result = Result<ProfileStats>;
+
+    fn handle(&mut self, msg: GetProfileStats, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{orders, reviews, users};
+
+        let conn = &mut self.0.get()?;
+
+        let user_id = users::table
+            .filter(users::username.eq(msg.username))
+            .select(users::id)
+            .first::<Uuid>(conn)?;
+
+        let (average_rating, review_count) = reviews::table
+            .filter(reviews::reviewee_id.eq(user_id))
+            .select((avg(reviews::rating), diesel::dsl::count_star()))
+            .first::<(Option<BigDecimal>, i64)>(conn)?;
+
+        let completed_sales = orders::table
+            .filter(orders::seller_id.eq(user_id))
+            .filter(orders::state.eq(OrderState::Delivered.as_str()))
+            .count()
+            .get_result(conn)?;
+
+        Ok(ProfileStats {
+            average_rating: average_rating
+                .map(|rating| CustomDecimal(rating.with_scale_round(2, RoundingMode::HalfUp))),
+            review_count,
+            completed_sales,
+        })
+    }
+}
+
+fn review_response(
+    review: Review,
+    order: Order,
+    reviewer: ProfileResponseInner,
+    conn: &mut PooledConn,
+) -> Result<ReviewResponse> {
+    use crate::schema::lots;
+
+    let lot_title = lots::table
+        .find(order.lot_id)
+        .select(lots::title)
+        .first::<String>(conn)?;
+
+    Ok(ReviewResponse {
+        id: review.id.to_string(),
+        order_id: review.order_id.to_string(),
+        lot_title,
+        rating: review.rating,
+        body: review.body,
+        reviewer,
+        reviewer_role: order
+            .role(review.reviewer_id)
+            .ok_or(Error::InternalServerError)?,
+        created_at: CustomDateTime(review.created_at),
+    })
+}
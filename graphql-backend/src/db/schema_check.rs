@@ -0,0 +1,151 @@
+// checks schema.rs against a test database with the migrations applied, see tests::TestDatabase,
+// so that a migration without `diesel print-schema` run after it fails the tests
+
+use std::collections::BTreeMap;
+
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::Connection;
+use diesel_migrations::MigrationHarness;
+
+use super::{Conn, MIGRATIONS};
+use crate::tests::{server_url, TestDatabase};
+
+// column name -> (udt name, nullable)
+type Columns = BTreeMap<String, (String, bool)>;
+
+#[derive(QueryableByName)]
+struct DatabaseColumn {
+    #[diesel(sql_type = Text)]
+    table_name: String,
+    #[diesel(sql_type = Text)]
+    column_name: String,
+    #[diesel(sql_type = Text)]
+    udt_name: String,
+    #[diesel(sql_type = Text)]
+    is_nullable: String,
+}
+
+// the tables of the table! blocks in schema.rs
+fn schema_tables() -> BTreeMap<String, Columns> {
+    let mut tables = BTreeMap::new();
+    let mut table: Option<(String, Columns)> = None;
+
+    for line in include_str!("../schema.rs").lines().map(str::trim) {
+        if let Some((name, columns)) = table.as_mut() {
+            if line == "}" {
+                let (name, columns) = table.take().unwrap();
+                tables.insert(name, columns);
+            } else if let Some((column, sql_type)) = line.split_once(" -> ") {
+                columns.insert(column.to_string(), udt_name(sql_type.trim_end_matches(',')));
+            } else if !line.ends_with('{') {
+                panic!("could not read the line of table {}: {}", name, line);
+            }
+        } else if line.ends_with('{') && !line.starts_with("table!") {
+            let name = line.split([' ', '(']).next().unwrap();
+            table = Some((name.to_string(), Columns::new()));
+        }
+    }
+
+    tables
+}
+
+// the name postgres gives the diesel sql type, and whether it is nullable
+fn udt_name(sql_type: &str) -> (String, bool) {
+    if let Some(inner) = sql_type
+        .strip_prefix("Nullable<")
+        .and_then(|inner| inner.strip_suffix('>'))
+    {
+        return (udt_name(inner).0, true);
+    }
+    if let Some(inner) = sql_type
+        .strip_prefix("Array<")
+        .and_then(|inner| inner.strip_suffix('>'))
+    {
+        return (format!("_{}", udt_name(inner).0), false);
+    }
+
+    let name = match sql_type {
+        "Bool" => "bool",
+        "Int2" | "SmallInt" => "int2",
+        "Int4" | "Integer" => "int4",
+        "Int8" | "BigInt" => "int8",
+        "Float4" => "float4",
+        "Float8" => "float8",
+        "Numeric" => "numeric",
+        "Text" => "text",
+        "Varchar" => "varchar",
+        "Bytea" => "bytea",
+        "Uuid" => "uuid",
+        "Date" => "date",
+        "Timestamp" => "timestamp",
+        "Timestamptz" => "timestamptz",
+        "Json" => "json",
+        "Jsonb" => "jsonb",
+        other => panic!("schema_check does not know the sql type {}", other),
+    };
+    (name.to_string(), false)
+}
+
+fn database_tables(conn: &mut Conn) -> BTreeMap<String, Columns> {
+    let columns = diesel::sql_query(
+        "SELECT table_name::text, column_name::text, udt_name::text, is_nullable::text \
+         FROM information_schema.columns \
+         WHERE table_schema = 'public' AND table_name <> '__diesel_schema_migrations'",
+    )
+    .load::<DatabaseColumn>(conn)
+    .expect("could not read the columns of the database");
+
+    let mut tables: BTreeMap<String, Columns> = BTreeMap::new();
+    for column in columns {
+        tables.entry(column.table_name).or_default().insert(
+            column.column_name,
+            (column.udt_name, column.is_nullable == "YES"),
+        );
+    }
+    tables
+}
+
+#[test]
+fn schema_matches_migrations() {
+    let database = TestDatabase::create(&server_url());
+    let conn = &mut Conn::establish(&database.url).expect("could not connect to the test database");
+    conn.run_pending_migrations(MIGRATIONS)
+        .expect("could not apply the migrations");
+
+    let schema = schema_tables();
+    let database = database_tables(conn);
+
+    let mut differences = Vec::new();
+    for name in database.keys().filter(|name| !schema.contains_key(*name)) {
+        differences.push(format!("table {} is missing from schema.rs", name));
+    }
+    for (name, columns) in &schema {
+        let Some(database_columns) = database.get(name) else {
+            differences.push(format!("table {} is not in the database", name));
+            continue;
+        };
+        for column in database_columns
+            .keys()
+            .filter(|c| !columns.contains_key(*c))
+        {
+            differences.push(format!("{}.{} is missing from schema.rs", name, column));
+        }
+        for (column, expected) in columns {
+            match database_columns.get(column) {
+                None => differences.push(format!("{}.{} is not in the database", name, column)),
+                Some(actual) if actual != expected => differences.push(format!(
+                    "{}.{} is {:?} in schema.rs, but {:?} in the database",
+                    name, column, expected, actual
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    assert!(
+        differences.is_empty(),
+        "schema.rs is out of date, run `diesel print-schema`:\n{}",
+        differences.join("\n")
+    );
+}
@@ -42,8 +42,8 @@ impl Handler<RegisterUser> for DbExecutor {
 
         let new_user = NewUser {
             username: msg.username.trim().to_string(),
-            email: msg.email.clone(),
-            password: HASHER.hash(&msg.password)?,
+            email: Some(msg.email.clone()),
+            password: Some(HASHER.hash(&msg.password)?),
             bio: None,
             image: None,
         };
@@ -77,23 +77,32 @@ impl Handler<LoginUser> for DbExecutor {
             .first(conn)
             .map_err(|_| Error::Unauthorized(get_random_message()))?;
 
-        let checker = HashBuilder::from_phc(&stored_user.password)?;
+        // accounts created with a wallet and given an email later have no password yet
+        let stored_password = stored_user
+            .password
+            .as_deref()
+            .ok_or_else(|| Error::Unauthorized(get_random_message()))?;
+        let checker = HashBuilder::from_phc(stored_password)?;
         let provided_password_raw = &msg.password;
 
         if !checker.is_valid(provided_password_raw) {
             return Err(Error::Unauthorized(get_random_message()));
         }
 
-        if stored_user.email_verified == false {
+        if !stored_user.email_verified {
             return Err(Error::Unauthorized("email not verified".to_string()));
         }
 
+        if stored_user.is_disabled {
+            return Err(Error::Unauthorized("account disabled".to_string()));
+        }
+
         if checker.needs_update(Some(PWD_SCHEME_VERSION)) {
             let new_password = HASHER.hash(provided_password_raw)?;
             let updated_user = diesel::update(users.find(stored_user.id))
                 .set(password.eq(new_password))
                 .get_result::<User>(conn)
-                .map_err(|e| Error::from(e))?;
+                .map_err(Error::from)?;
 
             return Ok(updated_user.into());
         }
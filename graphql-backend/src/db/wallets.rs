@@ -0,0 +1,217 @@
+use actix::prelude::*;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+
+use super::{DbExecutor, PooledConn};
+use crate::app::users::UserResponse;
+use crate::app::wallets::{GetWallets, IssueSiweNonce, LinkWallet, SiweSignin, WalletResponse};
+use crate::config::config;
+use crate::models::{NewSiweNonce, NewUser, NewWallet, User, Wallet};
+use crate::prelude::*;
+use crate::utils::siwe::{siwe_domain, SiweMessage};
+
+// the message, once its signature, domain, validity and nonce checked out
+// the nonce is used up, so that the message cannot be replayed
+fn verify_siwe(message: &str, signature: &str, conn: &mut PooledConn) -> Result<SiweMessage> {
+    use crate::schema::siwe_nonces::dsl::*;
+
+    let siwe = SiweMessage::parse(message).map_err(Error::Unauthorized)?;
+    siwe.verify(message, signature, siwe_domain())
+        .map_err(Error::Unauthorized)?;
+
+    let used = diesel::delete(
+        siwe_nonces
+            .filter(nonce.eq(&siwe.nonce))
+            .filter(expires_at.gt(diesel::dsl::now)),
+    )
+    .execute(conn)?;
+    if used == 0 {
+        return Err(Error::Unauthorized(
+            "the nonce is unknown, used or expired".to_string(),
+        ));
+    }
+
+    Ok(siwe)
+}
+
+// message handler implementations ↓
+
+impl Message for IssueSiweNonce {
+    type Result = Result<String>;
+}
+
+impl Handler<IssueSiweNonce> for DbExecutor {
+    type Result = Result<String>;
+
+    fn handle(&mut self, _: IssueSiweNonce, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::siwe_nonces::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        // nonces nobody signed in with are cleaned up as new ones are handed out
+        diesel::delete(siwe_nonces.filter(expires_at.le(diesel::dsl::now))).execute(conn)?;
+
+        let new_nonce = NewSiweNonce {
+            nonce: thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(17)
+                .map(char::from)
+                .collect(),
+            expires_at: (Utc::now() + Duration::minutes(config().auth.siwe_nonce_minutes)).naive_utc(),
+        };
+        diesel::insert_into(siwe_nonces)
+            .values(&new_nonce)
+            .execute(conn)?;
+
+        Ok(new_nonce.nonce)
+    }
+}
+
+impl Message for SiweSignin {
+    type Result = Result<UserResponse>;
+}
+
+impl Handler<SiweSignin> for DbExecutor {
+    type Result = Result<UserResponse>;
+
+    fn handle(&mut self, msg: SiweSignin, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{users, wallets};
+
+        let conn = &mut self.0.get()?;
+
+        conn.transaction(|connection| {
+            let siwe = verify_siwe(&msg.message, &msg.signature, connection)?;
+
+            let existing: Option<User> = wallets::table
+                .inner_join(users::table)
+                .filter(wallets::address.eq(&siwe.address))
+                .select(users::all_columns)
+                .first(connection)
+                .optional()?;
+            if let Some(user) = existing {
+                if user.is_disabled {
+                    return Err(Error::Unauthorized("account disabled".to_string()));
+                }
+                // the wallet is remembered on the chain it last signed in on
+                diesel::update(wallets::table.filter(wallets::address.eq(&siwe.address)))
+                    .set(wallets::chain_id.eq(siwe.chain_id))
+                    .execute(connection)?;
+                return Ok(user.into());
+            }
+
+            // a username from the address, the whole of it if the short one is taken
+            let candidates = [
+                format!("wallet_{}", &siwe.address[2..10]),
+                format!("wallet_{}", &siwe.address[2..]),
+            ];
+            let mut username = None;
+            for candidate in candidates {
+                let taken = diesel::select(diesel::dsl::exists(
+                    users::table.filter(users::username.eq(&candidate)),
+                ))
+                .get_result::<bool>(connection)?;
+                if !taken {
+                    username = Some(candidate);
+                    break;
+                }
+            }
+            let username = username.ok_or_else(|| {
+                Error::UnprocessableEntity(
+                    json!({ "error": "no username is left for this wallet" }),
+                )
+            })?;
+
+            let user: User = diesel::insert_into(users::table)
+                .values(NewUser {
+                    username,
+                    email: None,
+                    password: None,
+                    bio: None,
+                    image: None,
+                })
+                .get_result(connection)?;
+
+            diesel::insert_into(wallets::table)
+                .values(NewWallet {
+                    user_id: user.id,
+                    address: siwe.address,
+                    chain_id: siwe.chain_id,
+                })
+                .execute(connection)?;
+
+            Ok(user.into())
+        })
+    }
+}
+
+impl Message for LinkWallet {
+    type Result = Result<WalletResponse>;
+}
+
+impl Handler<LinkWallet> for DbExecutor {
+    type Result = Result<WalletResponse>;
+
+    fn handle(&mut self, msg: LinkWallet, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::wallets::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        conn.transaction(|connection| {
+            let siwe = verify_siwe(&msg.message, &msg.signature, connection)?;
+
+            let existing: Option<Wallet> = wallets
+                .filter(address.eq(&siwe.address))
+                .select(Wallet::as_select())
+                .first(connection)
+                .optional()?;
+
+            let wallet = match existing {
+                // linking a wallet twice is fine, it is remembered on the chain it last signed on
+                Some(wallet) if wallet.user_id == msg.auth.user.id => {
+                    diesel::update(wallets.find(wallet.id))
+                        .set(chain_id.eq(siwe.chain_id))
+                        .returning(Wallet::as_returning())
+                        .get_result(connection)?
+                }
+                Some(_) => {
+                    return Err(Error::UnprocessableEntity(json!({
+                        "error": "this wallet is linked to another account"
+                    })))
+                }
+                None => diesel::insert_into(wallets)
+                    .values(NewWallet {
+                        user_id: msg.auth.user.id,
+                        address: siwe.address,
+                        chain_id: siwe.chain_id,
+                    })
+                    .returning(Wallet::as_returning())
+                    .get_result(connection)?,
+            };
+
+            Ok(wallet.into())
+        })
+    }
+}
+
+impl Message for GetWallets {
+    type Result = Result<Vec<WalletResponse>>;
+}
+
+impl Handler<GetWallets> for DbExecutor {
+    type Result = Result<Vec<WalletResponse>>;
+
+    fn handle(&mut self, msg: GetWallets, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::wallets::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let linked: Vec<Wallet> = wallets
+            .filter(user_id.eq(msg.user_id))
+            .order(created_at.asc())
+            .select(Wallet::as_select())
+            .load(conn)?;
+
+        Ok(linked.into_iter().map(WalletResponse::from).collect())
+    }
+}
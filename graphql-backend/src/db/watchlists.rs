@@ -0,0 +1,209 @@
+use actix::prelude::*;
+use diesel::prelude::*;
+
+use super::lots::filter::{check_meta_data_filters, check_price_filter};
+use super::notifications::notify;
+use super::DbExecutor;
+use crate::app::watchlists::{
+    DeleteSavedSearch, GetSavedSearches, GetWatchedLots, SaveSearchAuthenticated, UnwatchLot,
+    WatchLot,
+};
+use crate::models::{
+    Lot, LotImage, LotStatus, LotWatchedPayload, LotWithImages, NewNotification, NewSavedSearch,
+    NewWatchedLot, NotificationPayload, SavedSearch,
+};
+use crate::prelude::*;
+
+// message handler implementations ↓
+
+impl Message for WatchLot {
+    type Result = Result<bool>;
+}
+
+impl Handler<WatchLot> for DbExecutor {
+    type Result = Result<bool>;
+
+    fn handle(&mut self, msg: WatchLot, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{lots, watched_lots};
+
+        let conn = &mut self.0.get()?;
+
+        // only lots on the market can be watched
+        let lot = lots::table
+            .find(msg.lot_id)
+            .filter(lots::status.eq(LotStatus::ForSale.as_str()))
+            .select(Lot::as_select())
+            .first(conn)?;
+
+        if lot.user_id == msg.auth.user.id {
+            return Err(Error::UnprocessableEntity(json!({
+                "error": "cannot watch your own lot",
+            })));
+        }
+
+        let inserted = diesel::insert_into(watched_lots::table)
+            .values(NewWatchedLot {
+                user_id: msg.auth.user.id,
+                lot_id: lot.id,
+                last_status: lot.status,
+                last_asking_price: lot.asking_price,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        if inserted > 0 {
+            notify(
+                vec![NewNotification::new(
+                    lot.user_id,
+                    NotificationPayload::LotWatched(LotWatchedPayload {
+                        lot_id: lot.id.to_string(),
+                        title: lot.title,
+                        username: msg.auth.user.username,
+                    }),
+                )],
+                conn,
+            )?;
+        }
+
+        Ok(true)
+    }
+}
+
+impl Message for UnwatchLot {
+    type Result = Result<bool>;
+}
+
+impl Handler<UnwatchLot> for DbExecutor {
+    type Result = Result<bool>;
+
+    fn handle(&mut self, msg: UnwatchLot, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::watched_lots::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let deleted = diesel::delete(
+            watched_lots
+                .filter(user_id.eq(msg.auth.user.id))
+                .filter(lot_id.eq(msg.lot_id)),
+        )
+        .execute(conn)?;
+
+        Ok(deleted > 0)
+    }
+}
+
+impl Message for GetWatchedLots {
+    type Result = Result<Vec<LotWithImages>>;
+}
+
+impl Handler<GetWatchedLots> for DbExecutor {
+    type Result = Result<Vec<LotWithImages>>;
+
+    fn handle(&mut self, msg: GetWatchedLots, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::{lots, watched_lots};
+
+        let conn = &mut self.0.get()?;
+
+        // most recently watched first
+        let watched: Vec<Lot> = lots::table
+            .inner_join(watched_lots::table)
+            .filter(watched_lots::user_id.eq(msg.auth.user.id))
+            .filter(lots::status.ne(LotStatus::Deleted.as_str()))
+            .order((watched_lots::created_at.desc(), lots::id))
+            .select(Lot::as_select())
+            .load(conn)?;
+
+        let images = LotImage::belonging_to(&watched)
+            .select(LotImage::as_select())
+            .load(conn)?;
+
+        Ok(images
+            .grouped_by(&watched)
+            .into_iter()
+            .zip(watched)
+            .map(|(imgs, lot)| LotWithImages {
+                lot,
+                images: imgs,
+                display_price: None,
+            })
+            .collect())
+    }
+}
+
+impl Message for SaveSearchAuthenticated {
+    type Result = Result<SavedSearch>;
+}
+
+impl Handler<SaveSearchAuthenticated> for DbExecutor {
+    type Result = Result<SavedSearch>;
+
+    fn handle(&mut self, msg: SaveSearchAuthenticated, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::saved_searches::dsl::*;
+
+        check_price_filter(&msg.search.params)?;
+        check_meta_data_filters(&msg.search.params)?;
+
+        let conn = &mut self.0.get()?;
+
+        let new_search = NewSavedSearch {
+            user_id: msg.auth.user.id,
+            name: msg.search.name,
+            params: serde_json::to_value(msg.search.params).unwrap(),
+        };
+
+        let saved = diesel::insert_into(saved_searches)
+            .values(&new_search)
+            .on_conflict((user_id, name))
+            .do_update()
+            .set(params.eq(&new_search.params))
+            .returning(SavedSearch::as_returning())
+            .get_result(conn)?;
+
+        Ok(saved)
+    }
+}
+
+impl Message for DeleteSavedSearch {
+    type Result = Result<bool>;
+}
+
+impl Handler<DeleteSavedSearch> for DbExecutor {
+    type Result = Result<bool>;
+
+    fn handle(&mut self, msg: DeleteSavedSearch, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::saved_searches::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let deleted = diesel::delete(
+            saved_searches
+                .filter(user_id.eq(msg.auth.user.id))
+                .filter(id.eq(msg.id)),
+        )
+        .execute(conn)?;
+
+        Ok(deleted > 0)
+    }
+}
+
+impl Message for GetSavedSearches {
+    type Result = Result<Vec<SavedSearch>>;
+}
+
+impl Handler<GetSavedSearches> for DbExecutor {
+    type Result = Result<Vec<SavedSearch>>;
+
+    fn handle(&mut self, msg: GetSavedSearches, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::saved_searches::dsl::*;
+
+        let conn = &mut self.0.get()?;
+
+        let searches = saved_searches
+            .filter(user_id.eq(msg.auth.user.id))
+            .order(name)
+            .select(SavedSearch::as_select())
+            .load(conn)?;
+
+        Ok(searches)
+    }
+}
@@ -17,7 +17,26 @@ pub struct ValidationError {
     key: String,
 }
 
+impl ValidationError {
+    pub fn new(key: String, message: String) -> Self {
+        ValidationError { message, key }
+    }
+
+    // the error of a row of an uploaded file, i.e. "line 3 title", or "line 3" for the whole row
+    pub fn on_line(self, line: u64) -> Self {
+        let key = match self.key.as_str() {
+            "" => format!("line {}", line),
+            key => format!("line {} {}", line, key),
+        };
+        ValidationError {
+            message: self.message,
+            key,
+        }
+    }
+}
+
 #[derive(Fail, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     // 401
     #[fail(display = "{}", _0)]
@@ -80,13 +99,16 @@ impl From<JwtError> for Error {
 impl From<DieselError> for Error {
     fn from(error: DieselError) -> Self {
         match error {
-            DieselError::DatabaseError(kind, info) => {
-                if let DatabaseErrorKind::UniqueViolation = kind {
-                    let message = info.details().unwrap_or_else(|| info.message()).to_string();
-                    return Error::UnprocessableEntity(json!({ "error": message }));
-                }
-                Error::InternalServerError
+            DieselError::DatabaseError(
+                DatabaseErrorKind::UniqueViolation
+                | DatabaseErrorKind::ForeignKeyViolation
+                | DatabaseErrorKind::CheckViolation,
+                info,
+            ) => {
+                let message = info.details().unwrap_or_else(|| info.message()).to_string();
+                Error::UnprocessableEntity(json!({ "error": message }))
             }
+            DieselError::DatabaseError(_, _) => Error::InternalServerError,
             DieselError::NotFound => {
                 Error::NotFound(json!({ "error": "requested record was not found" }))
             }
@@ -1,4 +1,6 @@
 #![allow(unused_must_use)]
+// the failure derive expands impls inside a const block
+#![allow(non_local_definitions)]
 
 #[macro_use]
 extern crate diesel;
@@ -13,21 +15,50 @@ extern crate serde_derive;
 extern crate serde_json;
 
 mod app;
+mod cli;
+mod config;
 mod db;
 mod error;
 mod models;
 mod prelude;
 mod schema;
+#[cfg(test)]
+mod tests;
 mod utils;
 
 use std::env;
 
+use clap::Parser;
+
 fn main() {
     dotenv::dotenv().ok();
 
     if env::var("RUST_LOG").ok().is_none() {
         env::set_var("RUST_LOG", "graphql_backend=debug,actix_web=info");
     }
-    env_logger::init();
-    app::start_server();
+    let telemetry = utils::telemetry::init();
+
+    let cli = cli::Cli::parse();
+    if let Err(errors) = config::load(cli.config) {
+        eprintln!("the config is not valid:");
+        for error in errors {
+            eprintln!("  {}", error);
+        }
+        telemetry.shutdown();
+        std::process::exit(1);
+    }
+
+    let res = match cli.command {
+        None => app::start_server(false).map_err(|e| e.to_string()),
+        Some(cli::Command::Serve { migrate }) => {
+            app::start_server(migrate).map_err(|e| e.to_string())
+        }
+        Some(command) => cli::run(command),
+    };
+
+    telemetry.shutdown();
+    if let Err(e) = res {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
\ No newline at end of file
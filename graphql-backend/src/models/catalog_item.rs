@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::schema::catalog_items;
+
+#[derive(async_graphql::SimpleObject, Debug, Queryable, Identifiable, Selectable, Serialize, Deserialize)]
+#[graphql(complex)] // NOTE: If you want the `ComplexObject` macro to take effect, this `complex` attribute is required.
+pub struct CatalogItem {
+    #[graphql(skip)]
+    pub id: Uuid,
+    pub source: String,
+    pub external_id: String,
+    pub name: String,
+    pub theme: Option<String>,
+    pub year: Option<i32>,
+    pub image_url: Option<String>,
+    pub piece_count: Option<i32>,
+    #[graphql(skip)]
+    pub created_at: NaiveDateTime,
+    #[graphql(skip)]
+    pub updated_at: NaiveDateTime,
+}
+
+#[async_graphql::ComplexObject]
+impl CatalogItem {
+    async fn id(&self) -> String {
+        self.id.to_string()
+    }
+    async fn created_at(&self) -> String {
+        self.created_at.to_string()
+    }
+    async fn updated_at(&self) -> String {
+        self.updated_at.to_string()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = catalog_items)]
+pub struct NewCatalogItem {
+    pub source: String,
+    pub external_id: String,
+    pub name: String,
+    pub theme: Option<String>,
+    pub year: Option<i32>,
+    pub image_url: Option<String>,
+    pub piece_count: Option<i32>,
+}
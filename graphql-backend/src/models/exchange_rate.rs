@@ -0,0 +1,23 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+
+use crate::schema::exchange_rates;
+
+// one unit of the base currency is worth rate units of the quote currency
+#[derive(Debug, Queryable, Selectable)]
+pub struct ExchangeRate {
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub rate: BigDecimal,
+    pub recorded_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = exchange_rates)]
+pub struct NewExchangeRate {
+    pub base_symbol: String,
+    pub quote_symbol: String,
+    pub source: String,
+    pub rate: BigDecimal,
+    pub recorded_at: NaiveDateTime,
+}
@@ -1,11 +1,16 @@
+use async_graphql::{dataloader::DataLoader, ErrorExtensions};
+use bigdecimal::BigDecimal;
 use chrono::NaiveDateTime;
 use uuid::Uuid;
 
+use crate::app::{catalog::GetCatalogItem, exchange_rates::ConvertedAmount, marketplace::{GetMarketplaceListing, MarketplaceListing}, profiles::{ProfileKey, ProfileLoader, ProfileResponseInner}, AppState};
+use crate::models::CatalogItem;
+use crate::prelude::Error;
 use crate::schema::{lots::{self}, lot_images};
+use crate::utils::{auth::viewer, CustomDecimal};
 
 #[derive(async_graphql::SimpleObject, Debug, Queryable, Identifiable, Selectable, Serialize, Deserialize)]
 #[graphql(complex)] // NOTE: If you want the `ComplexObject` macro to take effect, this `complex` attribute is required.
-
 pub struct Lot {
     #[graphql(skip)]
     pub id: Uuid,
@@ -22,6 +27,28 @@ pub struct Lot {
     #[graphql(skip)]
     pub updated_at: NaiveDateTime,
     pub status: String,
+    #[graphql(skip)]
+    pub asking_price: Option<BigDecimal>,
+    pub currency_symbol: String,
+    pub quantity: i32,
+    #[graphql(skip)]
+    pub catalog_item_id: Option<Uuid>,
+    #[graphql(skip)]
+    pub listed_at: Option<NaiveDateTime>,
+    pub nft_address: Option<String>,
+    #[graphql(skip)]
+    pub token_id: Option<BigDecimal>,
+    // when the lot was put in the trash
+    #[graphql(skip)]
+    pub deleted_at: Option<NaiveDateTime>,
+    // the status a restored lot gets back
+    #[graphql(skip)]
+    pub status_before_delete: Option<String>,
+    // the views counted, see RecordLotView
+    pub view_count: i32,
+    // the transaction the lot was last put up for sale in, see MatchAlerts
+    #[graphql(skip)]
+    pub listed_xid: Option<i64>,
 }
 
 #[async_graphql::ComplexObject]
@@ -38,6 +65,94 @@ impl Lot {
     async fn updated_at(&self) -> String {
         self.updated_at.to_string()
     }
+    async fn listed_at(&self) -> Option<String> {
+        self.listed_at.map(|listed_at| listed_at.to_string())
+    }
+    async fn deleted_at(&self) -> Option<String> {
+        self.deleted_at.map(|deleted_at| deleted_at.to_string())
+    }
+    async fn asking_price(&self) -> Option<CustomDecimal> {
+        self.asking_price.clone().map(CustomDecimal)
+    }
+    async fn token_id(&self) -> Option<CustomDecimal> {
+        self.token_id.clone().map(CustomDecimal)
+    }
+    // the listing of the lot's NFT on the Marketplace contract, as far as it has been indexed
+    async fn marketplace_listing<'ctx>(
+        &self,
+        ctx: &async_graphql::Context<'ctx>,
+    ) -> async_graphql::Result<Option<MarketplaceListing>> {
+        let (Some(nft_address), Some(token_id)) = (&self.nft_address, &self.token_id) else {
+            return Ok(None);
+        };
+
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state
+            .db
+            .send(GetMarketplaceListing {
+                nft_address: nft_address.clone(),
+                token_id: token_id.clone(),
+            })
+            .await??;
+
+        Ok(res)
+    }
+    async fn catalog_item_id(&self) -> Option<String> {
+        self.catalog_item_id.map(|item_id| item_id.to_string())
+    }
+    async fn catalog_item<'ctx>(
+        &self,
+        ctx: &async_graphql::Context<'ctx>,
+    ) -> async_graphql::Result<Option<CatalogItem>> {
+        let Some(item_id) = self.catalog_item_id else {
+            return Ok(None);
+        };
+
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state.db.send(GetCatalogItem { id: item_id }).await??;
+
+        Ok(Some(res))
+    }
+    // the profile of the owner, with whether the viewer follows them
+    // looked up along with the sellers of the other lots of the request
+    async fn seller<'ctx>(
+        &self,
+        ctx: &async_graphql::Context<'ctx>,
+    ) -> async_graphql::Result<ProfileResponseInner> {
+        let state = ctx.data_unchecked::<AppState>();
+        let viewer_id = viewer(state, ctx).await.map(|auth| auth.user.id);
+        let res = ctx
+            .data_unchecked::<DataLoader<ProfileLoader>>()
+            .load_one(ProfileKey {
+                user_id: self.user_id,
+                viewer_id,
+            })
+            .await?
+            .ok_or_else(|| {
+                Error::NotFound(json!({ "error": "requested record was not found" })).extend()
+            })?;
+
+        Ok(res)
+    }
+    // typed view of the meta data for lots in the set category
+    async fn lego_set(&self) -> Option<LegoSetMetaData> {
+        match (self.category.as_str(), &self.meta_data) {
+            ("set", Some(meta_data)) => serde_json::from_value(meta_data.clone()).ok(),
+            _ => None,
+        }
+    }
+}
+
+// see schemas/meta_data/set.json
+#[derive(async_graphql::SimpleObject, Debug, Serialize, Deserialize)]
+pub struct LegoSetMetaData {
+    pub set_number: Option<String>,
+    pub theme: Option<String>,
+    pub year: Option<i32>,
+    pub piece_count: Option<i32>,
+    pub minifig_count: Option<i32>,
+    pub has_box: Option<bool>,
+    pub has_instructions: Option<bool>,
 }
 
 #[derive(async_graphql::SimpleObject, Debug, Queryable, Identifiable, Associations, Selectable, Serialize, Deserialize)]
@@ -77,6 +192,9 @@ pub struct LotWithImages {
     #[serde(flatten)]
     pub lot: Lot,
     pub images: Vec<LotImage>,
+    // the asking price in the display currency of the query, if it asked for one
+    #[serde(skip)]
+    pub display_price: Option<ConvertedAmount>,
 }
 
 
@@ -90,6 +208,14 @@ pub struct NewLot {
     pub external_id: Option<String>,
     pub description: String,
     pub meta_data: serde_json::Value,
+    pub asking_price: Option<BigDecimal>,
+    pub currency_symbol: Option<String>,
+    pub quantity: Option<i32>,
+    pub catalog_item_id: Option<Uuid>,
+    pub nft_address: Option<String>,
+    pub token_id: Option<BigDecimal>,
+    // drafted when not set
+    pub status: Option<String>,
 }
 
 
@@ -101,7 +227,7 @@ pub struct NewLotImage {
     pub is_thumbnail: bool,
 }
 
-#[derive(Debug, Identifiable, AsChangeset)]
+#[derive(Debug, Default, Identifiable, AsChangeset)]
 #[diesel(table_name = lots)]
 pub struct UpdateLot {
     pub id: Uuid,
@@ -111,8 +237,16 @@ pub struct UpdateLot {
     pub external_id: Option<String>,
     pub description: Option<String>,
     pub status: Option<String>,
+    pub asking_price: Option<BigDecimal>,
+    pub currency_symbol: Option<String>,
+    pub quantity: Option<i32>,
+    pub meta_data: Option<serde_json::Value>,
+    pub catalog_item_id: Option<Uuid>,
+    pub nft_address: Option<String>,
+    pub token_id: Option<BigDecimal>,
 }
 
+#[allow(dead_code)]
 pub enum LotStatus {
     Cancelled,
     Deleted,
@@ -0,0 +1,35 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::schema::lot_mints;
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+pub struct LotMint {
+    pub id: Uuid,
+    pub lot_id: Uuid,
+    pub chain_id: i64,
+    pub nft_address: String,
+    pub token_id: BigDecimal,
+    pub recipient: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub log_index: i32,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = lot_mints)]
+pub struct NewLotMint {
+    pub lot_id: Uuid,
+    pub chain_id: i64,
+    pub nft_address: String,
+    pub token_id: BigDecimal,
+    pub recipient: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub log_index: i32,
+}
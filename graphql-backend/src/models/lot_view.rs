@@ -0,0 +1,12 @@
+use uuid::Uuid;
+
+use crate::schema::lot_views;
+
+// a view of a lot by a user, or by the fingerprint of an anonymous visitor
+#[derive(Debug, Insertable)]
+#[diesel(table_name = lot_views)]
+pub struct NewLotView {
+    pub lot_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub fingerprint: Option<String>,
+}
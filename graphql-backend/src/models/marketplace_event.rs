@@ -0,0 +1,78 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::schema::{chain_cursors, marketplace_events};
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+pub struct MarketplaceEvent {
+    pub id: Uuid,
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub log_index: i32,
+    pub event: String,
+    pub account: String,
+    pub nft_address: String,
+    pub token_id: BigDecimal,
+    pub price: Option<BigDecimal>,
+    pub lot_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = marketplace_events)]
+pub struct NewMarketplaceEvent {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub tx_hash: String,
+    pub log_index: i32,
+    pub event: String,
+    pub account: String,
+    pub nft_address: String,
+    pub token_id: BigDecimal,
+    pub price: Option<BigDecimal>,
+    pub lot_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketplaceEventKind {
+    Listed,
+    Canceled,
+    Bought,
+}
+
+impl MarketplaceEventKind {
+    // the event names of the Marketplace contract
+    pub fn as_str(&self) -> &str {
+        match self {
+            MarketplaceEventKind::Listed => "ItemListed",
+            MarketplaceEventKind::Canceled => "ItemCanceled",
+            MarketplaceEventKind::Bought => "ItemBought",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            MarketplaceEventKind::Listed,
+            MarketplaceEventKind::Canceled,
+            MarketplaceEventKind::Bought,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == name)
+    }
+}
+
+#[derive(Debug, Queryable, Insertable, Selectable)]
+#[diesel(table_name = chain_cursors)]
+pub struct ChainCursor {
+    pub chain_id: i64,
+    pub contract_address: String,
+    pub block_number: i64,
+    pub block_hash: Option<String>,
+}
@@ -0,0 +1,82 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::schema::{conversations, messages, user_blocks};
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+pub struct Conversation {
+    pub id: Uuid,
+    pub user_a_id: Uuid,
+    pub user_b_id: Uuid,
+    pub lot_id: Option<Uuid>,
+    pub last_message_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Conversation {
+    pub fn includes(&self, user_id: Uuid) -> bool {
+        self.user_a_id == user_id || self.user_b_id == user_id
+    }
+
+    // the participant that is not the given user
+    pub fn other_participant(&self, user_id: Uuid) -> Uuid {
+        if self.user_a_id == user_id {
+            self.user_b_id
+        } else {
+            self.user_a_id
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = conversations)]
+pub struct NewConversation {
+    pub user_a_id: Uuid,
+    pub user_b_id: Uuid,
+    pub lot_id: Option<Uuid>,
+}
+
+impl NewConversation {
+    // participants are stored in uuid order, see the direct_messages migration
+    pub fn between(user: Uuid, other_user: Uuid, lot_id: Option<Uuid>) -> Self {
+        let (user_a_id, user_b_id) = if user < other_user {
+            (user, other_user)
+        } else {
+            (other_user, user)
+        };
+
+        NewConversation {
+            user_a_id,
+            user_b_id,
+            lot_id,
+        }
+    }
+}
+
+#[derive(Debug, Queryable, Identifiable, Associations, Selectable)]
+#[diesel(belongs_to(Conversation))]
+pub struct Message {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub sender_id: Uuid,
+    pub body: String,
+    pub read_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = messages)]
+pub struct NewMessage {
+    pub conversation_id: Uuid,
+    pub sender_id: Uuid,
+    pub body: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = user_blocks)]
+pub struct NewUserBlock {
+    pub blocker_id: Uuid,
+    pub blocked_id: Uuid,
+}
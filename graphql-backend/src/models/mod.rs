@@ -1,9 +1,23 @@
 mod article;
 mod article_tag;
+mod catalog_item;
 mod comment;
+mod exchange_rate;
 mod follower;
 mod user;
 mod lot;
+mod lot_mint;
+mod lot_view;
+mod marketplace_event;
+mod message;
+mod notification;
+mod order;
 mod price;
+mod review;
+mod wallet;
+mod watchlist;
 
-pub use self::{article::*, article_tag::*, comment::*, follower::*, user::*, lot::*};
+pub use self::{
+    article::*, article_tag::*, catalog_item::*, comment::*, exchange_rate::*, follower::*, lot::*, lot_mint::*, lot_view::*, marketplace_event::*, message::*,
+    notification::*, order::*, price::*, review::*, user::*, wallet::*, watchlist::*,
+};
@@ -0,0 +1,264 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::schema::{notification_preferences, notifications};
+use crate::utils::CustomDecimal;
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub read_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub emailed_at: Option<NaiveDateTime>,
+}
+
+// every field is derived from the stored columns
+#[async_graphql::Object]
+impl Notification {
+    async fn id(&self) -> String {
+        self.id.to_string()
+    }
+    async fn kind(&self) -> async_graphql::Result<NotificationKind> {
+        NotificationKind::parse(&self.kind)
+            .ok_or_else(|| format!("unknown notification kind {}", self.kind).into())
+    }
+    async fn payload(&self) -> async_graphql::Result<NotificationPayload> {
+        Ok(self.typed_payload()?)
+    }
+    async fn read(&self) -> bool {
+        self.read_at.is_some()
+    }
+    async fn read_at(&self) -> Option<String> {
+        self.read_at.map(|read_at| read_at.to_string())
+    }
+    async fn created_at(&self) -> String {
+        self.created_at.to_string()
+    }
+}
+
+impl Notification {
+    pub fn typed_payload(&self) -> Result<NotificationPayload, String> {
+        let kind = NotificationKind::parse(&self.kind)
+            .ok_or_else(|| format!("unknown notification kind {}", self.kind))?;
+        NotificationPayload::from_value(kind, self.payload.clone()).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = notifications)]
+pub struct NewNotification {
+    pub user_id: Uuid,
+    pub kind: String,
+    pub payload: serde_json::Value,
+}
+
+impl NewNotification {
+    pub fn new(user_id: Uuid, payload: NotificationPayload) -> Self {
+        NewNotification {
+            user_id,
+            kind: payload.kind().as_str().to_string(),
+            payload: serde_json::to_value(payload).unwrap(),
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NotificationKind {
+    NewFollower,
+    ArticleComment,
+    ArticleFavorite,
+    LotWatched,
+    WatchedLotChanged,
+    SavedSearchMatch,
+    OrderUpdated,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 7] = [
+        NotificationKind::NewFollower,
+        NotificationKind::ArticleComment,
+        NotificationKind::ArticleFavorite,
+        NotificationKind::LotWatched,
+        NotificationKind::WatchedLotChanged,
+        NotificationKind::SavedSearchMatch,
+        NotificationKind::OrderUpdated,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            NotificationKind::NewFollower => "new follower",
+            NotificationKind::ArticleComment => "article comment",
+            NotificationKind::ArticleFavorite => "article favorite",
+            NotificationKind::LotWatched => "lot watched",
+            NotificationKind::WatchedLotChanged => "watched lot changed",
+            NotificationKind::SavedSearchMatch => "saved search match",
+            NotificationKind::OrderUpdated => "order updated",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        NotificationKind::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == kind)
+    }
+}
+
+// the payload is stored without a tag, the kind column says which one it is
+#[derive(async_graphql::Union, Debug, Serialize)]
+#[serde(untagged)]
+pub enum NotificationPayload {
+    NewFollower(NewFollowerPayload),
+    ArticleComment(ArticleCommentPayload),
+    ArticleFavorite(ArticleFavoritePayload),
+    LotWatched(LotWatchedPayload),
+    WatchedLotChanged(WatchedLotChangedPayload),
+    SavedSearchMatch(SavedSearchMatchPayload),
+    OrderUpdated(OrderUpdatedPayload),
+}
+
+impl NotificationPayload {
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            NotificationPayload::NewFollower(_) => NotificationKind::NewFollower,
+            NotificationPayload::ArticleComment(_) => NotificationKind::ArticleComment,
+            NotificationPayload::ArticleFavorite(_) => NotificationKind::ArticleFavorite,
+            NotificationPayload::LotWatched(_) => NotificationKind::LotWatched,
+            NotificationPayload::WatchedLotChanged(_) => NotificationKind::WatchedLotChanged,
+            NotificationPayload::SavedSearchMatch(_) => NotificationKind::SavedSearchMatch,
+            NotificationPayload::OrderUpdated(_) => NotificationKind::OrderUpdated,
+        }
+    }
+
+    pub fn from_value(
+        kind: NotificationKind,
+        value: serde_json::Value,
+    ) -> serde_json::Result<NotificationPayload> {
+        Ok(match kind {
+            NotificationKind::NewFollower => {
+                NotificationPayload::NewFollower(serde_json::from_value(value)?)
+            }
+            NotificationKind::ArticleComment => {
+                NotificationPayload::ArticleComment(serde_json::from_value(value)?)
+            }
+            NotificationKind::ArticleFavorite => {
+                NotificationPayload::ArticleFavorite(serde_json::from_value(value)?)
+            }
+            NotificationKind::LotWatched => {
+                NotificationPayload::LotWatched(serde_json::from_value(value)?)
+            }
+            NotificationKind::WatchedLotChanged => {
+                NotificationPayload::WatchedLotChanged(serde_json::from_value(value)?)
+            }
+            NotificationKind::SavedSearchMatch => {
+                NotificationPayload::SavedSearchMatch(serde_json::from_value(value)?)
+            }
+            NotificationKind::OrderUpdated => {
+                NotificationPayload::OrderUpdated(serde_json::from_value(value)?)
+            }
+        })
+    }
+
+    // one line description, i.e. for the email digest
+    pub fn summary(&self) -> String {
+        match self {
+            NotificationPayload::NewFollower(p) => format!("{} started following you", p.username),
+            NotificationPayload::ArticleComment(p) => {
+                format!("{} commented on \"{}\"", p.username, p.title)
+            }
+            NotificationPayload::ArticleFavorite(p) => {
+                format!("{} favorited \"{}\"", p.username, p.title)
+            }
+            NotificationPayload::LotWatched(p) => format!("{} is watching \"{}\"", p.username, p.title),
+            NotificationPayload::WatchedLotChanged(p) if p.status != p.old_status => {
+                format!("\"{}\" is now {}", p.title, p.status)
+            }
+            NotificationPayload::WatchedLotChanged(p) => match p.asking_price {
+                Some(ref price) => {
+                    format!("\"{}\" now asks {} {}", p.title, price.0.normalized(), p.currency_symbol)
+                }
+                None => format!("\"{}\" no longer has an asking price", p.title),
+            },
+            NotificationPayload::SavedSearchMatch(p) => {
+                format!("\"{}\" matches your saved search \"{}\"", p.title, p.saved_search_name)
+            }
+            NotificationPayload::OrderUpdated(p) if p.state == "awaiting payment" => {
+                format!("{} ordered \"{}\"", p.username, p.title)
+            }
+            NotificationPayload::OrderUpdated(p) => {
+                format!("{} marked the order for \"{}\" {}", p.username, p.title, p.state)
+            }
+        }
+    }
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize, Deserialize)]
+pub struct NewFollowerPayload {
+    pub username: String,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize, Deserialize)]
+pub struct ArticleCommentPayload {
+    pub slug: String,
+    pub title: String,
+    pub comment_id: i32,
+    pub username: String,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize, Deserialize)]
+pub struct ArticleFavoritePayload {
+    pub slug: String,
+    pub title: String,
+    pub username: String,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize, Deserialize)]
+pub struct LotWatchedPayload {
+    pub lot_id: String,
+    pub title: String,
+    pub username: String,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize, Deserialize)]
+pub struct WatchedLotChangedPayload {
+    pub lot_id: String,
+    pub title: String,
+    pub old_status: String,
+    pub status: String,
+    pub old_asking_price: Option<CustomDecimal>,
+    pub asking_price: Option<CustomDecimal>,
+    pub currency_symbol: String,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Serialize, Deserialize)]
+pub struct SavedSearchMatchPayload {
+    pub saved_search_id: String,
+    pub saved_search_name: String,
+    pub lot_id: String,
+    pub title: String,
+    pub asking_price: Option<CustomDecimal>,
+    pub currency_symbol: String,
+}
+
+// sent to the other party whenever a buyer or seller moves an order along
+#[derive(async_graphql::SimpleObject, Debug, Serialize, Deserialize)]
+pub struct OrderUpdatedPayload {
+    pub order_id: String,
+    pub lot_id: String,
+    pub title: String,
+    pub state: String,
+    pub username: String,
+}
+
+#[derive(Debug, Queryable, Insertable, Selectable)]
+#[diesel(table_name = notification_preferences)]
+pub struct NotificationPreference {
+    pub user_id: Uuid,
+    pub kind: String,
+    pub enabled: bool,
+    pub email_digest: bool,
+}
@@ -0,0 +1,148 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::models::LotStatus;
+use crate::schema::orders;
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+pub struct Order {
+    pub id: Uuid,
+    pub lot_id: Uuid,
+    pub buyer_id: Uuid,
+    pub seller_id: Uuid,
+    pub price: BigDecimal,
+    pub currency_symbol: String,
+    pub shipping_address: serde_json::Value,
+    pub tracking_number: Option<String>,
+    pub state: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Order {
+    // the part the user plays in the order, if any
+    pub fn role(&self, user_id: Uuid) -> Option<OrderRole> {
+        if self.buyer_id == user_id {
+            Some(OrderRole::Buyer)
+        } else if self.seller_id == user_id {
+            Some(OrderRole::Seller)
+        } else {
+            None
+        }
+    }
+
+    // the party that is not the given user
+    pub fn other_party(&self, user_id: Uuid) -> Uuid {
+        if self.buyer_id == user_id {
+            self.seller_id
+        } else {
+            self.buyer_id
+        }
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = orders)]
+pub struct NewOrder {
+    pub lot_id: Uuid,
+    pub buyer_id: Uuid,
+    pub seller_id: Uuid,
+    pub price: BigDecimal,
+    pub currency_symbol: String,
+    pub shipping_address: serde_json::Value,
+}
+
+#[derive(async_graphql::Enum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderState {
+    AwaitingPayment,
+    Paid,
+    Shipped,
+    Delivered,
+    Disputed,
+    Refunded,
+    Cancelled,
+}
+
+impl OrderState {
+    pub const ALL: [OrderState; 7] = [
+        OrderState::AwaitingPayment,
+        OrderState::Paid,
+        OrderState::Shipped,
+        OrderState::Delivered,
+        OrderState::Disputed,
+        OrderState::Refunded,
+        OrderState::Cancelled,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            OrderState::AwaitingPayment => "awaiting payment",
+            OrderState::Paid => "paid",
+            OrderState::Shipped => "shipped",
+            OrderState::Delivered => "delivered",
+            OrderState::Disputed => "disputed",
+            OrderState::Refunded => "refunded",
+            OrderState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(state: &str) -> Option<Self> {
+        OrderState::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == state)
+    }
+
+    // open orders hold on to their lot, see the orders migration
+    pub fn is_open(&self) -> bool {
+        !matches!(self, OrderState::Refunded | OrderState::Cancelled)
+    }
+
+    // the status the lot of an order in this state has
+    // a cancelled order puts the lot back up for sale, a refunded one leaves it to the seller to relist
+    pub fn lot_status(&self) -> LotStatus {
+        match self {
+            OrderState::AwaitingPayment => LotStatus::Pending,
+            OrderState::Paid
+            | OrderState::Shipped
+            | OrderState::Delivered
+            | OrderState::Disputed => LotStatus::Sold,
+            OrderState::Refunded => LotStatus::Cancelled,
+            OrderState::Cancelled => LotStatus::ForSale,
+        }
+    }
+
+    // the seller confirms payment, ships and refunds, the buyer confirms delivery and disputes
+    pub fn can_become(&self, next: OrderState, role: OrderRole) -> bool {
+        use OrderState::*;
+
+        match (self, next) {
+            (AwaitingPayment, Cancelled) => true,
+            (AwaitingPayment, Paid) | (Paid, Shipped) | (Paid, Refunded) | (Disputed, Refunded) => {
+                role == OrderRole::Seller
+            }
+            (Shipped, Delivered)
+            | (Shipped, Disputed)
+            | (Delivered, Disputed)
+            | (Disputed, Delivered) => role == OrderRole::Buyer,
+            _ => false,
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderRole {
+    Buyer,
+    Seller,
+}
+
+impl OrderRole {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OrderRole::Buyer => "buyer",
+            OrderRole::Seller => "seller",
+        }
+    }
+}
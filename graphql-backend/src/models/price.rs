@@ -1,12 +1,55 @@
+use bigdecimal::BigDecimal;
 use chrono::NaiveDateTime;
 
+use crate::app::{catalog::GetCatalogItemForPrice, AppState};
+use crate::models::CatalogItem;
 use crate::schema::prices;
+use crate::utils::CustomDecimal;
 
-#[derive(Debug, Queryable)]
+#[derive(async_graphql::SimpleObject, Debug, Queryable, Selectable, Serialize)]
+#[graphql(complex)] // NOTE: If you want the `ComplexObject` macro to take effect, this `complex` attribute is required.
 pub struct Price {
     pub external_id: String,
     pub source: String,
     pub currency_symbol: String,
+    #[graphql(skip)]
+    pub amount: BigDecimal,
+    #[graphql(skip)]
     pub recorded_at: NaiveDateTime,
-    pub amount: f64,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = prices)]
+pub struct NewPrice {
+    pub external_id: String,
+    pub source: String,
+    pub currency_symbol: String,
+    pub amount: BigDecimal,
+    pub recorded_at: NaiveDateTime,
+}
+
+#[async_graphql::ComplexObject]
+impl Price {
+    async fn amount(&self) -> CustomDecimal {
+        CustomDecimal(self.amount.clone())
+    }
+    async fn recorded_at(&self) -> String {
+        self.recorded_at.to_string()
+    }
+    // the catalog item the price was recorded for, if it is in the catalog
+    async fn catalog_item<'ctx>(
+        &self,
+        ctx: &async_graphql::Context<'ctx>,
+    ) -> async_graphql::Result<Option<CatalogItem>> {
+        let state = ctx.data_unchecked::<AppState>();
+        let res = state
+            .db
+            .send(GetCatalogItemForPrice {
+                external_id: self.external_id.clone(),
+                source: self.source.clone(),
+            })
+            .await??;
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,26 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::schema::reviews;
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+pub struct Review {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub reviewer_id: Uuid,
+    pub reviewee_id: Uuid,
+    pub rating: i32,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = reviews)]
+pub struct NewReview {
+    pub order_id: Uuid,
+    pub reviewer_id: Uuid,
+    pub reviewee_id: Uuid,
+    pub rating: i32,
+    pub body: String,
+}
@@ -3,25 +3,28 @@ use uuid::Uuid;
 
 use crate::schema::users;
 
-#[derive(Debug, Queryable, Identifiable)]
+#[derive(Debug, Clone, Queryable, Identifiable)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
-    pub email: String,
+    // none for accounts created by signing in with a wallet
+    pub email: Option<String>,
     pub email_verified: bool,
-    pub password: String,
+    pub password: Option<String>,
     pub bio: Option<String>,
     pub image: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub is_admin: bool,
+    pub is_disabled: bool,
 }
 
 #[derive(Debug, Insertable)]
 #[diesel(table_name = users)]
 pub struct NewUser {
     pub username: String,
-    pub email: String,
-    pub password: String,
+    pub email: Option<String>,
+    pub password: Option<String>,
     pub bio: Option<String>,
     pub image: Option<String>,
 }
@@ -36,3 +39,12 @@ pub struct UserChange {
     pub bio: Option<String>,
     pub image: Option<String>,
 }
+
+// the flags of a user that only admins can change
+#[derive(Debug, AsChangeset)]
+#[diesel(table_name = users)]
+pub struct UserFlagsChange {
+    pub email_verified: Option<bool>,
+    pub is_admin: Option<bool>,
+    pub is_disabled: Option<bool>,
+}
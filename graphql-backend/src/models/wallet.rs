@@ -0,0 +1,29 @@
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::schema::{siwe_nonces, wallets};
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+pub struct Wallet {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub address: String,
+    pub chain_id: i64,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = wallets)]
+pub struct NewWallet {
+    pub user_id: Uuid,
+    pub address: String,
+    pub chain_id: i64,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = siwe_nonces)]
+pub struct NewSiweNonce {
+    pub nonce: String,
+    pub expires_at: NaiveDateTime,
+}
@@ -0,0 +1,68 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::schema::{saved_searches, watched_lots};
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(primary_key(user_id, lot_id))]
+pub struct WatchedLot {
+    pub user_id: Uuid,
+    pub lot_id: Uuid,
+    pub last_status: String,
+    pub last_asking_price: Option<BigDecimal>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = watched_lots)]
+pub struct NewWatchedLot {
+    pub user_id: Uuid,
+    pub lot_id: Uuid,
+    pub last_status: String,
+    pub last_asking_price: Option<BigDecimal>,
+}
+
+#[derive(async_graphql::SimpleObject, Debug, Queryable, Identifiable, Selectable, Serialize)]
+#[graphql(complex)] // NOTE: If you want the `ComplexObject` macro to take effect, this `complex` attribute is required.
+#[diesel(table_name = saved_searches)]
+pub struct SavedSearch {
+    #[graphql(skip)]
+    pub id: Uuid,
+    #[graphql(skip)]
+    pub user_id: Uuid,
+    pub name: String,
+    // the serialized FilterLots
+    pub params: serde_json::Value,
+    #[graphql(skip)]
+    pub last_matched_at: NaiveDateTime,
+    #[graphql(skip)]
+    pub created_at: NaiveDateTime,
+    #[graphql(skip)]
+    pub updated_at: NaiveDateTime,
+    // the lots listed in transactions from this one on are not matched yet, see MatchAlerts
+    #[graphql(skip)]
+    pub last_matched_xid: i64,
+}
+
+#[async_graphql::ComplexObject]
+impl SavedSearch {
+    async fn id(&self) -> String {
+        self.id.to_string()
+    }
+    async fn created_at(&self) -> String {
+        self.created_at.to_string()
+    }
+    async fn updated_at(&self) -> String {
+        self.updated_at.to_string()
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = saved_searches)]
+pub struct NewSavedSearch {
+    pub user_id: Uuid,
+    pub name: String,
+    pub params: serde_json::Value,
+}
@@ -20,6 +20,32 @@ table! {
     }
 }
 
+table! {
+    catalog_items (id) {
+        id -> Uuid,
+        source -> Text,
+        external_id -> Text,
+        name -> Text,
+        theme -> Nullable<Text>,
+        year -> Nullable<Int4>,
+        image_url -> Nullable<Text>,
+        piece_count -> Nullable<Int4>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    chain_cursors (chain_id, contract_address) {
+        chain_id -> Int8,
+        contract_address -> Text,
+        block_number -> Int8,
+        block_hash -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     comments (id) {
         id -> Int4,
@@ -31,6 +57,18 @@ table! {
     }
 }
 
+table! {
+    conversations (id) {
+        id -> Uuid,
+        user_a_id -> Uuid,
+        user_b_id -> Uuid,
+        lot_id -> Nullable<Uuid>,
+        last_message_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     currencies (id) {
         id -> Uuid,
@@ -38,6 +76,19 @@ table! {
         symbol -> Text,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        decimals -> Int4,
+    }
+}
+
+table! {
+    exchange_rates (base_symbol, quote_symbol, recorded_at, source) {
+        base_symbol -> Text,
+        quote_symbol -> Text,
+        source -> Text,
+        rate -> Numeric,
+        recorded_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -70,6 +121,23 @@ table! {
     }
 }
 
+table! {
+    lot_mints (id) {
+        id -> Uuid,
+        lot_id -> Uuid,
+        chain_id -> Int8,
+        nft_address -> Text,
+        token_id -> Numeric,
+        recipient -> Text,
+        block_number -> Int8,
+        block_hash -> Text,
+        tx_hash -> Text,
+        log_index -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     lot_statuses (description) {
         description -> Text,
@@ -78,6 +146,16 @@ table! {
     }
 }
 
+table! {
+    lot_views (id) {
+        id -> Uuid,
+        lot_id -> Uuid,
+        user_id -> Nullable<Uuid>,
+        fingerprint -> Nullable<Text>,
+        viewed_at -> Timestamp,
+    }
+}
+
 table! {
     lots (id) {
         id -> Uuid,
@@ -91,6 +169,97 @@ table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         status -> Text,
+        asking_price -> Nullable<Numeric>,
+        currency_symbol -> Text,
+        quantity -> Int4,
+        catalog_item_id -> Nullable<Uuid>,
+        listed_at -> Nullable<Timestamp>,
+        nft_address -> Nullable<Text>,
+        token_id -> Nullable<Numeric>,
+        deleted_at -> Nullable<Timestamp>,
+        status_before_delete -> Nullable<Text>,
+        view_count -> Int4,
+        listed_xid -> Nullable<Int8>,
+    }
+}
+
+table! {
+    marketplace_events (id) {
+        id -> Uuid,
+        chain_id -> Int8,
+        contract_address -> Text,
+        block_number -> Int8,
+        block_hash -> Text,
+        tx_hash -> Text,
+        log_index -> Int4,
+        event -> Text,
+        account -> Text,
+        nft_address -> Text,
+        token_id -> Numeric,
+        price -> Nullable<Numeric>,
+        lot_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    messages (id) {
+        id -> Uuid,
+        conversation_id -> Uuid,
+        sender_id -> Uuid,
+        body -> Text,
+        read_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    notification_preferences (user_id, kind) {
+        user_id -> Uuid,
+        kind -> Text,
+        enabled -> Bool,
+        email_digest -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    notifications (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        kind -> Text,
+        payload -> Jsonb,
+        read_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        emailed_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    order_states (description) {
+        description -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    orders (id) {
+        id -> Uuid,
+        lot_id -> Uuid,
+        buyer_id -> Uuid,
+        seller_id -> Uuid,
+        price -> Numeric,
+        currency_symbol -> Text,
+        shipping_address -> Jsonb,
+        tracking_number -> Nullable<Text>,
+        state -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -106,17 +275,84 @@ table! {
     }
 }
 
+table! {
+    reviews (id) {
+        id -> Uuid,
+        order_id -> Uuid,
+        reviewer_id -> Uuid,
+        reviewee_id -> Uuid,
+        rating -> Int4,
+        body -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    saved_searches (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        params -> Jsonb,
+        last_matched_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        last_matched_xid -> Int8,
+    }
+}
+
+table! {
+    siwe_nonces (nonce) {
+        nonce -> Text,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    user_blocks (blocker_id, blocked_id) {
+        blocker_id -> Uuid,
+        blocked_id -> Uuid,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     users (id) {
         id -> Uuid,
         username -> Text,
-        email -> Varchar,
+        email -> Nullable<Varchar>,
         email_verified -> Bool,
-        password -> Text,
+        password -> Nullable<Text>,
         bio -> Nullable<Text>,
         image -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        is_admin -> Bool,
+        is_disabled -> Bool,
+    }
+}
+
+table! {
+    wallets (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        address -> Text,
+        chain_id -> Int8,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    watched_lots (user_id, lot_id) {
+        user_id -> Uuid,
+        lot_id -> Uuid,
+        last_status -> Text,
+        last_asking_price -> Nullable<Numeric>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -124,22 +360,57 @@ joinable!(article_tags -> articles (article_id));
 joinable!(articles -> users (author_id));
 joinable!(comments -> articles (article_id));
 joinable!(comments -> users (user_id));
+joinable!(conversations -> lots (lot_id));
 joinable!(favorite_articles -> articles (article_id));
 joinable!(favorite_articles -> users (user_id));
 joinable!(lot_images -> lots (lot_id));
+joinable!(lot_mints -> lots (lot_id));
+joinable!(lot_views -> lots (lot_id));
+joinable!(lot_views -> users (user_id));
+joinable!(lots -> catalog_items (catalog_item_id));
 joinable!(lots -> lot_statuses (status));
 joinable!(lots -> users (user_id));
+joinable!(marketplace_events -> lots (lot_id));
+joinable!(messages -> conversations (conversation_id));
+joinable!(messages -> users (sender_id));
+joinable!(notification_preferences -> users (user_id));
+joinable!(notifications -> users (user_id));
+joinable!(orders -> lots (lot_id));
+joinable!(orders -> order_states (state));
+joinable!(reviews -> orders (order_id));
+joinable!(saved_searches -> users (user_id));
+joinable!(wallets -> users (user_id));
+joinable!(watched_lots -> lots (lot_id));
+joinable!(watched_lots -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
     article_tags,
     articles,
+    catalog_items,
+    chain_cursors,
     comments,
+    conversations,
     currencies,
+    exchange_rates,
     favorite_articles,
     followers,
     lot_images,
+    lot_mints,
     lot_statuses,
+    lot_views,
     lots,
+    marketplace_events,
+    messages,
+    notification_preferences,
+    notifications,
+    order_states,
+    orders,
     prices,
+    reviews,
+    saved_searches,
+    siwe_nonces,
+    user_blocks,
     users,
+    wallets,
+    watched_lots,
 );
@@ -0,0 +1,65 @@
+use diesel::prelude::*;
+use diesel::sql_types::Uuid as SqlUuid;
+
+use super::TestApp;
+use crate::app::alerts::MatchAlerts;
+use crate::db::Conn;
+
+const SAVE_SEARCH: &str = "mutation($params: SaveSearch!) { saveSearch(params: $params) { name } }";
+const CREATE: &str =
+    "mutation($params: CreateLot!) { createLot(params: $params) { lot { id } } }";
+const NOTIFICATIONS: &str = "{ notifications { kind } }";
+
+#[actix_rt::test]
+async fn a_listing_that_commits_after_the_matcher_ran_is_matched_the_next_time() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    app.query(
+        Some(&alice.token),
+        SAVE_SEARCH,
+        json!({ "params": { "name": "bricks", "params": {
+            "categories": ["part"],
+            "conditions": [],
+            "terms": [],
+            "statuses": [],
+        } } }),
+    )
+    .await;
+    let data = app
+        .query(
+            Some(&bob.token),
+            CREATE,
+            json!({ "params": {
+                "category": "part",
+                "condition": "used",
+                "title": "Red bricks",
+                "description": "a lot",
+                "images": [],
+                "metaData": {},
+                "askingPrice": "10",
+            } }),
+        )
+        .await;
+    let id: uuid::Uuid = data["createLot"]["lot"]["id"].as_str().unwrap().parse().unwrap();
+
+    // the listing is open while the matcher runs
+    let conn = &mut Conn::establish(&app.database.url).unwrap();
+    diesel::sql_query("BEGIN").execute(conn).unwrap();
+    diesel::sql_query("UPDATE lots SET status = 'for sale' WHERE id = $1")
+        .bind::<SqlUuid, _>(id)
+        .execute(conn)
+        .unwrap();
+    app.db.send(MatchAlerts).await.unwrap().unwrap();
+    diesel::sql_query("COMMIT").execute(conn).unwrap();
+
+    let data = app.query(Some(&alice.token), NOTIFICATIONS, json!({})).await;
+    assert_eq!(data["notifications"], json!([]));
+
+    app.db.send(MatchAlerts).await.unwrap().unwrap();
+    let data = app.query(Some(&alice.token), NOTIFICATIONS, json!({})).await;
+    assert_eq!(
+        data["notifications"],
+        json!([{ "kind": "SAVED_SEARCH_MATCH" }])
+    );
+}
@@ -0,0 +1,226 @@
+use super::{TestApp, TestUser};
+
+const CREATE: &str = "mutation($params: CreateArticle!) {
+    createActicle(params: $params) { article { slug title tagList author { username } } }
+}";
+const UPDATE: &str = "mutation($slug: String!, $params: UpdateArticle!) {
+    updateActicle(slug: $slug, params: $params) { article { slug title body } }
+}";
+const DELETE: &str = "mutation($slug: String!) { deleteActicle(slug: $slug) }";
+const GET: &str = "query($slug: String!) {
+    getArticle(slug: $slug) { article { title favorited favoritesCount } }
+}";
+const FAVORITE: &str = "mutation($slug: String!) {
+    favoriteActicle(slug: $slug) { article { favorited favoritesCount } }
+}";
+const UNFAVORITE: &str = "mutation($slug: String!) {
+    unfavoriteActicle(slug: $slug) { article { favorited favoritesCount } }
+}";
+const ADD_COMMENT: &str = "mutation($slug: String!, $body: String!) {
+    addComment(slug: $slug, comment: { body: $body }) { comment { id body author { username } } }
+}";
+const DELETE_COMMENT: &str =
+    "mutation($slug: String!, $id: Int!) { deleteComment(slug: $slug, commentId: $id) }";
+const COMMENTS: &str = "query($slug: String!) { getComments(slug: $slug) { comments { body } } }";
+
+// the slug of a new article by the user
+async fn create_article(app: &TestApp, author: &TestUser, title: &str) -> String {
+    let data = app
+        .query(
+            Some(&author.token),
+            CREATE,
+            json!({ "params": {
+                "title": title,
+                "description": "a description",
+                "body": "a body",
+                "tagList": ["lego", "castle"],
+            } }),
+        )
+        .await;
+    data["createActicle"]["article"]["slug"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[actix_rt::test]
+async fn articles_are_created_read_updated_and_deleted_by_their_author() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let slug = create_article(&app, &alice, "Castle builds").await;
+
+    let data = app.query(None, GET, json!({ "slug": slug })).await;
+    assert_eq!(data["getArticle"]["article"]["title"], "Castle builds");
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            "{ getArticles(filter: { author: \"alice\" }) { articlesCount articles { title } } }",
+            json!({}),
+        )
+        .await;
+    assert_eq!(data["getArticles"]["articlesCount"], 1);
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            UPDATE,
+            json!({ "slug": slug, "params": { "body": "a new body" } }),
+        )
+        .await;
+    assert_eq!(data["updateActicle"]["article"]["body"], "a new body");
+
+    app.query(Some(&alice.token), DELETE, json!({ "slug": slug }))
+        .await;
+    let missing = app.execute(None, GET, json!({ "slug": slug })).await;
+    assert!(!missing.errors.is_empty());
+}
+
+#[actix_rt::test]
+async fn only_the_author_updates_or_deletes_an_article() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let slug = create_article(&app, &alice, "Castle builds").await;
+
+    let update = app
+        .query_error(
+            Some(&bob.token),
+            UPDATE,
+            json!({ "slug": slug, "params": { "title": "Taken over" } }),
+        )
+        .await;
+    assert!(update.starts_with("Forbidden"), "{}", update);
+
+    let delete = app
+        .query_error(Some(&bob.token), DELETE, json!({ "slug": slug }))
+        .await;
+    assert!(delete.starts_with("Forbidden"), "{}", delete);
+
+    let data = app.query(None, GET, json!({ "slug": slug })).await;
+    assert_eq!(data["getArticle"]["article"]["title"], "Castle builds");
+}
+
+#[actix_rt::test]
+async fn favorites_are_counted_per_user() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let slug = create_article(&app, &alice, "Castle builds").await;
+
+    let data = app
+        .query(Some(&bob.token), FAVORITE, json!({ "slug": slug }))
+        .await;
+    assert_eq!(data["favoriteActicle"]["article"]["favorited"], true);
+    assert_eq!(data["favoriteActicle"]["article"]["favoritesCount"], 1);
+
+    let data = app
+        .query(Some(&alice.token), GET, json!({ "slug": slug }))
+        .await;
+    assert_eq!(data["getArticle"]["article"]["favorited"], false);
+    assert_eq!(data["getArticle"]["article"]["favoritesCount"], 1);
+
+    let data = app
+        .query(
+            None,
+            "{ getArticles(filter: { favorited: \"bob\" }) { articlesCount } }",
+            json!({}),
+        )
+        .await;
+    assert_eq!(data["getArticles"]["articlesCount"], 1);
+
+    let data = app
+        .query(Some(&bob.token), UNFAVORITE, json!({ "slug": slug }))
+        .await;
+    assert_eq!(data["unfavoriteActicle"]["article"]["favorited"], false);
+    assert_eq!(data["unfavoriteActicle"]["article"]["favoritesCount"], 0);
+}
+
+#[actix_rt::test]
+async fn comments_are_added_and_deleted_by_their_author() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let slug = create_article(&app, &alice, "Castle builds").await;
+
+    let data = app
+        .query(
+            Some(&bob.token),
+            ADD_COMMENT,
+            json!({ "slug": slug, "body": "nice castle" }),
+        )
+        .await;
+    assert_eq!(data["addComment"]["comment"]["author"]["username"], "bob");
+    let id = data["addComment"]["comment"]["id"].as_i64().unwrap();
+
+    let data = app.query(None, COMMENTS, json!({ "slug": slug })).await;
+    assert_eq!(
+        data["getComments"]["comments"],
+        json!([{ "body": "nice castle" }])
+    );
+
+    let forbidden = app
+        .query_error(
+            Some(&alice.token),
+            DELETE_COMMENT,
+            json!({ "slug": slug, "id": id }),
+        )
+        .await;
+    assert!(forbidden.starts_with("Forbidden"), "{}", forbidden);
+
+    app.query(
+        Some(&bob.token),
+        DELETE_COMMENT,
+        json!({ "slug": slug, "id": id }),
+    )
+    .await;
+    let data = app.query(None, COMMENTS, json!({ "slug": slug })).await;
+    assert_eq!(data["getComments"]["comments"], json!([]));
+}
+
+#[actix_rt::test]
+async fn comments_are_deleted_only_through_the_slug_of_their_article() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let castle = create_article(&app, &alice, "Castle builds").await;
+    let space = create_article(&app, &alice, "Space builds").await;
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            ADD_COMMENT,
+            json!({ "slug": castle, "body": "nice castle" }),
+        )
+        .await;
+    let id = data["addComment"]["comment"]["id"].as_i64().unwrap();
+
+    let not_found = app
+        .query_error(
+            Some(&alice.token),
+            DELETE_COMMENT,
+            json!({ "slug": space, "id": id }),
+        )
+        .await;
+    assert!(not_found.starts_with("Not Found"), "{}", not_found);
+    let data = app.query(None, COMMENTS, json!({ "slug": castle })).await;
+    assert_eq!(
+        data["getComments"]["comments"],
+        json!([{ "body": "nice castle" }])
+    );
+}
+
+#[actix_rt::test]
+async fn comments_need_a_user() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let slug = create_article(&app, &alice, "Castle builds").await;
+
+    let message = app
+        .query_error(
+            None,
+            ADD_COMMENT,
+            json!({ "slug": slug, "body": "anonymous" }),
+        )
+        .await;
+    assert_eq!(message, "no authorization was provided");
+}
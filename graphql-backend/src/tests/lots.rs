@@ -0,0 +1,1045 @@
+use async_graphql::{Request, Variables};
+
+use super::{TestApp, TestUser};
+use crate::app::admin::{ImpersonateUser, SetUserFlags};
+use crate::app::lots::{CreateLot, CreateLotAuthenticated, PurgeDeletedLots, RefreshTrendingLots};
+use crate::error::Error;
+use crate::utils::auth::Fingerprint;
+
+const CREATE: &str =
+    "mutation($params: CreateLot!) { createLot(params: $params) { lot { id title status userId } } }";
+const UPDATE: &str = "mutation($params: UpdateLot!) {
+    updateLot(params: $params) { lot { title status askingPrice } }
+}";
+const DELETE: &str = "mutation($id: String!) { deleteLot(lotId: $id) }";
+const RESTORE: &str =
+    "mutation($id: String!) { restoreLot(lotId: $id) { lot { title status deletedAt } } }";
+const TRASH: &str = "{ trash { lot { title status deletedAt } } }";
+const USER_LOTS: &str =
+    "query($params: FilterLots!) { getUserLots(params: $params) { lot { title } } }";
+const LOT: &str = "query($id: String!) { lot(id: $id) { lot { title viewCount } } }";
+const PUBLIC_LOT: &str =
+    "query($id: String!) { lot(id: $id) { lot { title status seller { username following } } } }";
+const PUBLIC_LOTS_FOR_SALE: &str = "query($params: FilterLots!) {
+    lotsForSale(params: $params) { lot { title seller { username } } }
+}";
+const TRENDING: &str = "query($category: String, $window: TrendingWindow) {
+    trendingLots(category: $category, window: $window) { lot { title } }
+}";
+const IMPORT: &str = "mutation($params: ImportLots!) { importLots(params: $params) { valid imported } }";
+const EXPORT: &str = "query($format: LotFileFormat!) { exportLots(format: $format) }";
+const IMPORTED_LOTS: &str = "query($params: FilterLots!) {
+    getUserLots(params: $params) {
+        lot { title status askingPrice currencySymbol }
+        images { imageUrl isThumbnail }
+    }
+}";
+const LOTS_FOR_SALE: &str =
+    "query($params: FilterLots!) { getLotsForSale(params: $params) { lot { title } } }";
+const LOT_IDS: &str = "query($params: FilterLots!) { getUserLots(params: $params) { lot { id } } }";
+const FACETS: &str = "query($params: FilterLots!) {
+    lotFacets(params: $params, ownerOnly: true) {
+        categories { value count }
+        conditions { value count }
+        statuses { value count }
+    }
+}";
+const SEND_MESSAGE: &str =
+    "mutation($params: SendMessage!) { sendMessage(params: $params) { conversationId } }";
+const CONVERSATIONS: &str = "{ conversations { id lotId participant { username } } }";
+const MESSAGES: &str = "query($id: String!) { messages(conversationId: $id) { body } }";
+
+// the id of a new lot of the user
+async fn create_lot(app: &TestApp, owner: &TestUser, title: &str, category: &str) -> String {
+    let data = app
+        .query(
+            Some(&owner.token),
+            CREATE,
+            json!({ "params": {
+                "category": category,
+                "condition": "used",
+                "title": title,
+                "description": "a lot",
+                "images": [],
+                "metaData": {},
+            } }),
+        )
+        .await;
+    data["createLot"]["lot"]["id"].as_str().unwrap().to_string()
+}
+
+// puts a lot of the owner up for sale
+async fn list_for_sale(app: &TestApp, owner: &TestUser, id: &str) {
+    app.query(
+        Some(&owner.token),
+        UPDATE,
+        json!({ "params": {
+            "lotId": id,
+            "status": "for sale",
+            "askingPrice": "10",
+            "deletedImageIds": [],
+        } }),
+    )
+    .await;
+}
+
+// fetches a lot as an anonymous visitor with the fingerprint
+async fn view_anonymously(app: &TestApp, id: &str, fingerprint: &str) {
+    let request = Request::new(LOT)
+        .variables(Variables::from_json(json!({ "id": id })))
+        .data(Fingerprint(fingerprint.to_string()));
+    let response = app.schema.execute(request).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+}
+
+async fn view_count(app: &TestApp, owner: &TestUser, id: &str) -> i64 {
+    let data = app.query(Some(&owner.token), LOT, json!({ "id": id })).await;
+    data["lot"]["lot"]["viewCount"].as_i64().unwrap()
+}
+
+// no filter, with the given terms, categories and statuses
+fn filter(terms: &[&str], categories: &[&str], statuses: &[&str]) -> serde_json::Value {
+    json!({ "params": {
+        "categories": categories,
+        "conditions": [],
+        "terms": terms,
+        "statuses": statuses,
+    } })
+}
+
+fn import(format: &str, lots: &str, dry_run: bool) -> serde_json::Value {
+    json!({ "params": { "format": format, "lots": lots, "dryRun": dry_run } })
+}
+
+// the lines of an export, sorted to compare exports of lots imported in another order
+async fn export(app: &TestApp, owner: &TestUser, format: &str) -> (String, Vec<String>) {
+    let data = app
+        .query(Some(&owner.token), EXPORT, json!({ "format": format }))
+        .await;
+    let file = data["exportLots"].as_str().unwrap().to_string();
+    let mut lines: Vec<String> = file.lines().map(str::to_string).collect();
+    lines.sort();
+    (file, lines)
+}
+
+fn titles(data: &serde_json::Value, field: &str) -> Vec<String> {
+    let mut titles: Vec<String> = data[field]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|lot| lot["lot"]["title"].as_str().unwrap().to_string())
+        .collect();
+    titles.sort();
+    titles
+}
+
+#[actix_rt::test]
+async fn lots_are_created_updated_and_deleted_by_their_owner() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            CREATE,
+            json!({ "params": {
+                "category": "part",
+                "condition": "new",
+                "title": "Red bricks",
+                "description": "a bag of red bricks",
+                "images": [],
+                "metaData": {},
+            } }),
+        )
+        .await;
+    assert_eq!(data["createLot"]["lot"]["title"], "Red bricks");
+    let id = data["createLot"]["lot"]["id"].as_str().unwrap();
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            UPDATE,
+            json!({ "params": { "lotId": id, "title": "Dark red bricks", "deletedImageIds": [] } }),
+        )
+        .await;
+    assert_eq!(data["updateLot"]["lot"]["title"], "Dark red bricks");
+
+    let data = app
+        .query(Some(&alice.token), DELETE, json!({ "id": id }))
+        .await;
+    assert_eq!(data["deleteLot"], 1);
+    let data = app
+        .query(Some(&alice.token), USER_LOTS, filter(&[], &[], &[]))
+        .await;
+    assert_eq!(data["getUserLots"], json!([]));
+
+    let data = app
+        .query(Some(&alice.token), DELETE, json!({ "id": id }))
+        .await;
+    assert_eq!(data["deleteLot"], 0);
+}
+
+#[actix_rt::test]
+async fn deleted_lots_are_restored_from_the_trash_with_their_status() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let id = create_lot(&app, &alice, "Red bricks", "part").await;
+    app.query(
+        Some(&alice.token),
+        UPDATE,
+        json!({ "params": {
+            "lotId": id,
+            "status": "for sale",
+            "askingPrice": "12.50",
+            "deletedImageIds": [],
+        } }),
+    )
+    .await;
+
+    app.query(Some(&alice.token), DELETE, json!({ "id": id }))
+        .await;
+    let data = app.query(Some(&alice.token), TRASH, json!({})).await;
+    assert_eq!(data["trash"][0]["lot"]["title"], "Red bricks");
+    assert_eq!(data["trash"][0]["lot"]["status"], "deleted");
+    assert!(data["trash"][0]["lot"]["deletedAt"].is_string());
+
+    let update = app
+        .query_error(
+            Some(&alice.token),
+            UPDATE,
+            json!({ "params": { "lotId": id, "title": "Dark red bricks", "deletedImageIds": [] } }),
+        )
+        .await;
+    assert!(update.starts_with("Not Found"), "{}", update);
+
+    let data = app
+        .query(Some(&alice.token), RESTORE, json!({ "id": id }))
+        .await;
+    assert_eq!(data["restoreLot"]["lot"]["status"], "for sale");
+    assert_eq!(data["restoreLot"]["lot"]["deletedAt"], json!(null));
+    let data = app.query(Some(&alice.token), TRASH, json!({})).await;
+    assert_eq!(data["trash"], json!([]));
+    let data = app
+        .query(Some(&alice.token), LOTS_FOR_SALE, filter(&[], &[], &[]))
+        .await;
+    assert_eq!(titles(&data, "getLotsForSale"), ["Red bricks"]);
+
+    let message = app
+        .query_error(Some(&alice.token), RESTORE, json!({ "id": id }))
+        .await;
+    assert!(message.starts_with("Not Found"), "{}", message);
+}
+
+#[actix_rt::test]
+async fn lots_are_purged_once_they_have_been_in_the_trash_long_enough() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let id = create_lot(&app, &alice, "Red bricks", "part").await;
+    create_lot(&app, &alice, "Blue bricks", "part").await;
+    app.query(Some(&alice.token), DELETE, json!({ "id": id }))
+        .await;
+
+    let purged = app.db.send(PurgeDeletedLots { days: 30 }).await.unwrap();
+    assert_eq!(purged.unwrap(), 0);
+    let data = app.query(Some(&alice.token), TRASH, json!({})).await;
+    assert_eq!(data["trash"][0]["lot"]["title"], "Red bricks");
+
+    let purged = app.db.send(PurgeDeletedLots { days: 0 }).await.unwrap();
+    assert_eq!(purged.unwrap(), 1);
+    let data = app.query(Some(&alice.token), TRASH, json!({})).await;
+    assert_eq!(data["trash"], json!([]));
+    let data = app
+        .query(Some(&alice.token), USER_LOTS, filter(&[], &[], &[]))
+        .await;
+    assert_eq!(titles(&data, "getUserLots"), ["Blue bricks"]);
+
+    let message = app
+        .query_error(Some(&alice.token), RESTORE, json!({ "id": id }))
+        .await;
+    assert!(message.starts_with("Not Found"), "{}", message);
+}
+
+#[actix_rt::test]
+async fn the_conversations_about_a_purged_lot_become_the_direct_conversation() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let carol = app.signup("carol").await;
+    let red = create_lot(&app, &alice, "Red bricks", "part").await;
+    let blue = create_lot(&app, &alice, "Blue bricks", "part").await;
+    list_for_sale(&app, &alice, &red).await;
+    list_for_sale(&app, &alice, &blue).await;
+
+    // bob already has a direct conversation with alice and one about each lot
+    for params in [
+        json!({ "recipient": "alice", "body": "hello" }),
+        json!({ "lotId": red, "body": "about the red bricks" }),
+        json!({ "lotId": blue, "body": "about the blue bricks" }),
+    ] {
+        app.query(Some(&bob.token), SEND_MESSAGE, json!({ "params": params }))
+            .await;
+    }
+    // carol only wrote about one of them
+    app.query(
+        Some(&carol.token),
+        SEND_MESSAGE,
+        json!({ "params": { "lotId": red, "body": "still available?" } }),
+    )
+    .await;
+
+    for id in [&red, &blue] {
+        app.query(Some(&alice.token), DELETE, json!({ "id": id }))
+            .await;
+    }
+    let purged = app.db.send(PurgeDeletedLots { days: 0 }).await.unwrap();
+    assert_eq!(purged.unwrap(), 2);
+
+    let data = app.query(Some(&bob.token), CONVERSATIONS, json!({})).await;
+    let conversations = data["conversations"].as_array().unwrap();
+    assert_eq!(conversations.len(), 1, "{}", data);
+    assert_eq!(conversations[0]["lotId"], json!(null));
+    let data = app
+        .query(
+            Some(&bob.token),
+            MESSAGES,
+            json!({ "id": conversations[0]["id"] }),
+        )
+        .await;
+    assert_eq!(
+        data["messages"],
+        json!([
+            { "body": "about the blue bricks" },
+            { "body": "about the red bricks" },
+            { "body": "hello" },
+        ])
+    );
+
+    let data = app.query(Some(&carol.token), CONVERSATIONS, json!({})).await;
+    assert_eq!(
+        data["conversations"][0]["participant"]["username"],
+        "alice"
+    );
+    assert_eq!(data["conversations"][0]["lotId"], json!(null));
+
+    // alice sees the same two conversations
+    let data = app.query(Some(&alice.token), CONVERSATIONS, json!({})).await;
+    assert_eq!(data["conversations"].as_array().unwrap().len(), 2);
+}
+
+#[actix_rt::test]
+async fn lots_are_listed_for_sale_with_an_asking_price() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let id = create_lot(&app, &alice, "Red bricks", "part").await;
+
+    let message = app
+        .query_error(
+            Some(&alice.token),
+            UPDATE,
+            json!({ "params": { "lotId": id, "status": "for sale", "deletedImageIds": [] } }),
+        )
+        .await;
+    assert!(
+        message.contains("an asking price is required"),
+        "{}",
+        message
+    );
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            UPDATE,
+            json!({ "params": {
+                "lotId": id,
+                "status": "for sale",
+                "askingPrice": "12.50",
+                "deletedImageIds": [],
+            } }),
+        )
+        .await;
+    assert_eq!(data["updateLot"]["lot"]["status"], "for sale");
+}
+
+#[actix_rt::test]
+async fn only_the_owner_updates_deletes_or_restores_a_lot() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let id = create_lot(&app, &alice, "Red bricks", "part").await;
+
+    let update = app
+        .query_error(
+            Some(&bob.token),
+            UPDATE,
+            json!({ "params": { "lotId": id, "title": "Taken over", "deletedImageIds": [] } }),
+        )
+        .await;
+    assert!(update.starts_with("Not Found"), "{}", update);
+
+    let data = app
+        .query(Some(&bob.token), DELETE, json!({ "id": id }))
+        .await;
+    assert_eq!(data["deleteLot"], 0);
+
+    let data = app
+        .query(Some(&alice.token), USER_LOTS, filter(&[], &[], &[]))
+        .await;
+    assert_eq!(titles(&data, "getUserLots"), ["Red bricks"]);
+
+    let anonymous = app.query_error(None, DELETE, json!({ "id": id })).await;
+    assert_eq!(anonymous, "no authorization was provided");
+
+    app.query(Some(&alice.token), DELETE, json!({ "id": id }))
+        .await;
+    let restore = app
+        .query_error(Some(&bob.token), RESTORE, json!({ "id": id }))
+        .await;
+    assert!(restore.starts_with("Not Found"), "{}", restore);
+    let data = app.query(Some(&bob.token), TRASH, json!({})).await;
+    assert_eq!(data["trash"], json!([]));
+}
+
+#[actix_rt::test]
+async fn lots_are_filtered_by_owner_category_terms_and_status() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    create_lot(&app, &alice, "Red bricks", "part").await;
+    create_lot(&app, &alice, "Blue bricks", "part").await;
+    create_lot(&app, &alice, "Castle minifigure", "minifig").await;
+    let for_sale = create_lot(&app, &bob, "Green bricks", "part").await;
+    app.query(
+        Some(&bob.token),
+        UPDATE,
+        json!({ "params": {
+            "lotId": for_sale,
+            "status": "for sale",
+            "askingPrice": "3",
+            "deletedImageIds": [],
+        } }),
+    )
+    .await;
+
+    let data = app
+        .query(Some(&alice.token), USER_LOTS, filter(&[], &[], &[]))
+        .await;
+    assert_eq!(
+        titles(&data, "getUserLots"),
+        ["Blue bricks", "Castle minifigure", "Red bricks"]
+    );
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            USER_LOTS,
+            filter(&[], &["minifig"], &[]),
+        )
+        .await;
+    assert_eq!(titles(&data, "getUserLots"), ["Castle minifigure"]);
+
+    let data = app
+        .query(Some(&alice.token), USER_LOTS, filter(&["red"], &[], &[]))
+        .await;
+    assert_eq!(titles(&data, "getUserLots"), ["Red bricks"]);
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            LOTS_FOR_SALE,
+            filter(&["bricks"], &[], &[]),
+        )
+        .await;
+    assert_eq!(titles(&data, "getLotsForSale"), ["Green bricks"]);
+}
+
+#[actix_rt::test]
+async fn each_facet_is_counted_without_its_own_selection_but_with_the_other_filters() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    for (title, category, condition, for_sale) in [
+        ("Red bricks", "part", "used", true),
+        ("Yellow bricks", "part", "used", false),
+        ("Blue bricks", "part", "new", true),
+        ("Castle", "set", "used", true),
+        ("Tower", "set", "used", true),
+        ("Ship", "set", "new", false),
+    ] {
+        let id = create_lot(&app, &alice, title, category).await;
+        app.query(
+            Some(&alice.token),
+            UPDATE,
+            json!({ "params": { "lotId": id, "condition": condition, "deletedImageIds": [] } }),
+        )
+        .await;
+        if for_sale {
+            list_for_sale(&app, &alice, &id).await;
+        }
+    }
+
+    let params = json!({ "params": {
+        "categories": ["part"],
+        "conditions": ["used"],
+        "terms": [],
+        "statuses": ["for sale"],
+    } });
+    // only the red bricks match all three selections
+    let data = app
+        .query(Some(&alice.token), USER_LOTS, params.clone())
+        .await;
+    assert_eq!(titles(&data, "getUserLots"), ["Red bricks"]);
+
+    let data = app.query(Some(&alice.token), FACETS, params).await;
+    let facets = &data["lotFacets"];
+    // used lots for sale, whatever their category
+    assert_eq!(
+        facets["categories"],
+        json!([{ "value": "set", "count": 2 }, { "value": "part", "count": 1 }])
+    );
+    // parts for sale, whatever their condition
+    assert_eq!(
+        facets["conditions"],
+        json!([{ "value": "new", "count": 1 }, { "value": "used", "count": 1 }])
+    );
+    // used parts, whatever their status
+    assert_eq!(
+        facets["statuses"],
+        json!([{ "value": "drafted", "count": 1 }, { "value": "for sale", "count": 1 }])
+    );
+}
+
+#[actix_rt::test]
+async fn views_of_a_lot_count_once_per_viewer() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let carol = app.signup("carol").await;
+    let id = create_lot(&app, &alice, "Red bricks", "part").await;
+
+    let draft = app
+        .query_error(Some(&bob.token), LOT, json!({ "id": id }))
+        .await;
+    assert!(draft.starts_with("Not Found"), "{}", draft);
+    list_for_sale(&app, &alice, &id).await;
+
+    for viewer in [&bob, &bob, &carol, &alice] {
+        let data = app
+            .query(Some(&viewer.token), LOT, json!({ "id": id }))
+            .await;
+        assert_eq!(data["lot"]["lot"]["title"], "Red bricks");
+    }
+    assert_eq!(view_count(&app, &alice, &id).await, 2);
+
+    view_anonymously(&app, &id, "first visitor").await;
+    view_anonymously(&app, &id, "first visitor").await;
+    view_anonymously(&app, &id, "second visitor").await;
+    app.query(None, LOT, json!({ "id": id })).await;
+    assert_eq!(view_count(&app, &alice, &id).await, 4);
+}
+
+#[actix_rt::test]
+async fn trending_lots_are_ranked_by_their_recent_views_watches_and_orders() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let viewed = create_lot(&app, &alice, "Red bricks", "part").await;
+    let watched = create_lot(&app, &alice, "Castle set", "set").await;
+    let drafted = create_lot(&app, &alice, "Blue bricks", "part").await;
+    list_for_sale(&app, &alice, &viewed).await;
+    list_for_sale(&app, &alice, &watched).await;
+
+    for fingerprint in ["first visitor", "second visitor"] {
+        view_anonymously(&app, &viewed, fingerprint).await;
+    }
+    app.query(
+        Some(&bob.token),
+        "mutation($id: String!) { watchLot(lotId: $id) }",
+        json!({ "id": watched }),
+    )
+    .await;
+    app.query(Some(&alice.token), LOT, json!({ "id": drafted }))
+        .await;
+
+    let data = app.query(None, TRENDING, json!({})).await;
+    assert_eq!(data["trendingLots"], json!([]));
+
+    app.db.send(RefreshTrendingLots).await.unwrap().unwrap();
+    let data = app.query(None, TRENDING, json!({})).await;
+    assert_eq!(
+        data["trendingLots"],
+        json!([{ "lot": { "title": "Castle set" } }, { "lot": { "title": "Red bricks" } }])
+    );
+    let data = app
+        .query(None, TRENDING, json!({ "category": "part", "window": "WEEK" }))
+        .await;
+    assert_eq!(
+        data["trendingLots"],
+        json!([{ "lot": { "title": "Red bricks" } }])
+    );
+}
+
+#[actix_rt::test]
+async fn anonymous_visitors_browse_the_lots_for_sale_but_not_drafts_or_deleted_lots() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let for_sale = create_lot(&app, &alice, "Red bricks", "part").await;
+    let drafted = create_lot(&app, &alice, "Blue bricks", "part").await;
+    let deleted = create_lot(&app, &alice, "Castle set", "set").await;
+    list_for_sale(&app, &alice, &for_sale).await;
+    app.query(Some(&alice.token), DELETE, json!({ "id": deleted }))
+        .await;
+
+    let data = app
+        .query(None, PUBLIC_LOTS_FOR_SALE, filter(&[], &[], &[]))
+        .await;
+    assert_eq!(
+        data["lotsForSale"],
+        json!([{ "lot": { "title": "Red bricks", "seller": { "username": "alice" } } }])
+    );
+    let data = app
+        .query(None, PUBLIC_LOT, json!({ "id": for_sale }))
+        .await;
+    assert_eq!(data["lot"]["lot"]["seller"]["following"], false);
+
+    for id in [&drafted, &deleted] {
+        let anonymous = app.query_error(None, PUBLIC_LOT, json!({ "id": id })).await;
+        assert!(anonymous.starts_with("Not Found"), "{}", anonymous);
+        let other = app
+            .query_error(Some(&bob.token), PUBLIC_LOT, json!({ "id": id }))
+            .await;
+        assert!(other.starts_with("Not Found"), "{}", other);
+    }
+    let data = app
+        .query(Some(&alice.token), PUBLIC_LOT, json!({ "id": deleted }))
+        .await;
+    assert_eq!(data["lot"]["lot"]["status"], "deleted");
+
+    app.query(
+        Some(&bob.token),
+        "mutation { followUser(username: \"alice\") { profile { following } } }",
+        json!({}),
+    )
+    .await;
+    let data = app
+        .query(Some(&bob.token), PUBLIC_LOT, json!({ "id": for_sale }))
+        .await;
+    assert_eq!(
+        data["lot"]["lot"]["seller"],
+        json!({ "username": "alice", "following": true })
+    );
+}
+
+#[actix_rt::test]
+async fn the_sellers_of_a_page_of_lots_are_seen_as_the_viewer_follows_them() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let carol = app.signup("carol").await;
+    for (owner, title) in [
+        (&alice, "Red bricks"),
+        (&alice, "Blue bricks"),
+        (&carol, "Castle set"),
+        (&carol, "Green bricks"),
+    ] {
+        let id = create_lot(&app, owner, title, "part").await;
+        list_for_sale(&app, owner, &id).await;
+    }
+    app.query(
+        Some(&bob.token),
+        "mutation { followUser(username: \"alice\") { profile { following } } }",
+        json!({}),
+    )
+    .await;
+
+    // the title, seller and whether the viewer follows them of every lot for sale
+    let sellers = |data: serde_json::Value| {
+        let mut sellers: Vec<(String, String, bool)> = data["lotsForSale"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|lot| {
+                let seller = &lot["lot"]["seller"];
+                (
+                    lot["lot"]["title"].as_str().unwrap().to_string(),
+                    seller["username"].as_str().unwrap().to_string(),
+                    seller["following"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        sellers.sort();
+        sellers
+    };
+    let query = "query($params: FilterLots!) {
+        lotsForSale(params: $params) { lot { title seller { username following } } }
+    }";
+
+    let data = app
+        .query(Some(&bob.token), query, filter(&[], &[], &[]))
+        .await;
+    assert_eq!(
+        sellers(data),
+        [
+            ("Blue bricks".to_string(), "alice".to_string(), true),
+            ("Castle set".to_string(), "carol".to_string(), false),
+            ("Green bricks".to_string(), "carol".to_string(), false),
+            ("Red bricks".to_string(), "alice".to_string(), true),
+        ]
+    );
+
+    let data = app.query(None, query, filter(&[], &[], &[])).await;
+    assert!(sellers(data).iter().all(|(_, _, following)| !following));
+}
+
+#[actix_rt::test]
+async fn a_dry_run_import_reports_the_errors_of_each_line_and_imports_nothing() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let lots = [
+        r#"{"category":"part","condition":"used","title":"Red bricks","description":"a lot"}"#,
+        r#"{"category":"part","condition":"used","title":"","description":"a lot"}"#,
+        r#"{"category":"part","condition":"used","title":"Blue bricks","description":"a lot","currencySymbol":"XYZ"}"#,
+        r#"{"category":"part","condition":"used","title":"Green bricks""#,
+        r#"{"category":"part","condition":"used","title":"Castle","description":"a lot","status":"for sale"}"#,
+    ]
+    .join("\n");
+
+    let response = app
+        .execute(Some(&alice.token), IMPORT, import("JSON_LINES", &lots, true))
+        .await;
+    let error = serde_json::to_value(&response.errors[0]).unwrap();
+    let mut keys: Vec<&str> = error["extensions"]["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["key"].as_str().unwrap())
+        .collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        ["line 2 title", "line 3 currency_symbol", "line 4", "line 5 asking_price"]
+    );
+
+    let first_line = lots.lines().next().unwrap();
+    let data = app
+        .query(Some(&alice.token), IMPORT, import("JSON_LINES", first_line, true))
+        .await;
+    assert_eq!(data["importLots"], json!({ "valid": 1, "imported": 0 }));
+    let data = app
+        .query(Some(&alice.token), USER_LOTS, filter(&[], &[], &[]))
+        .await;
+    assert_eq!(data["getUserLots"], json!([]));
+}
+
+#[actix_rt::test]
+async fn imported_lots_are_exported_and_imported_again_the_same() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let carol = app.signup("carol").await;
+    let lots = "category,condition,title,description,status,askingPrice,images,thumbnail\n\
+        part,used,Red bricks,\"a lot, sorted\",for sale,12.5,https://img.test/a.jpg https://img.test/b.jpg,https://img.test/b.jpg\n\
+        part,new,Blue bricks,a lot,,,,\n";
+
+    let data = app
+        .query(Some(&alice.token), IMPORT, import("CSV", lots, false))
+        .await;
+    assert_eq!(data["importLots"], json!({ "valid": 2, "imported": 2 }));
+    let data = app
+        .query(Some(&alice.token), IMPORTED_LOTS, filter(&["red"], &[], &[]))
+        .await;
+    let lot = &data["getUserLots"][0];
+    assert_eq!(
+        lot["lot"],
+        json!({ "title": "Red bricks", "status": "for sale", "askingPrice": "12.5", "currencySymbol": "USD" })
+    );
+    let mut thumbnails: Vec<(String, bool)> = lot["images"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|image| {
+            (
+                image["imageUrl"].as_str().unwrap().to_string(),
+                image["isThumbnail"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    thumbnails.sort();
+    assert_eq!(
+        thumbnails,
+        [
+            ("https://img.test/a.jpg".to_string(), false),
+            ("https://img.test/b.jpg".to_string(), true)
+        ]
+    );
+    let data = app
+        .query(Some(&alice.token), IMPORTED_LOTS, filter(&["blue"], &[], &[]))
+        .await;
+    assert_eq!(data["getUserLots"][0]["lot"]["status"], "drafted");
+
+    // lots in the trash are left out
+    let deleted = create_lot(&app, &alice, "Castle set", "set").await;
+    app.query(Some(&alice.token), DELETE, json!({ "id": deleted }))
+        .await;
+
+    let (file, alice_lines) = export(&app, &alice, "JSON_LINES").await;
+    assert_eq!(alice_lines.len(), 2);
+    app.query(Some(&bob.token), IMPORT, import("JSON_LINES", &file, false))
+        .await;
+    let (_, bob_lines) = export(&app, &bob, "JSON_LINES").await;
+    assert_eq!(bob_lines, alice_lines);
+
+    let (file, alice_lines) = export(&app, &alice, "CSV").await;
+    app.query(Some(&carol.token), IMPORT, import("CSV", &file, false))
+        .await;
+    let (_, carol_lines) = export(&app, &carol, "CSV").await;
+    assert_eq!(carol_lines, alice_lines);
+}
+
+#[actix_rt::test]
+async fn lots_are_filtered_and_sorted_by_price_within_one_currency() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let dollars = create_lot(&app, &alice, "Red bricks", "part").await;
+    let ether = create_lot(&app, &alice, "Blue bricks", "part").await;
+    list_for_sale(&app, &alice, &dollars).await;
+    app.query(
+        Some(&alice.token),
+        UPDATE,
+        json!({ "params": {
+            "lotId": ether,
+            "status": "for sale",
+            "askingPrice": "5",
+            "currencySymbol": "ETH",
+            "deletedImageIds": [],
+        } }),
+    )
+    .await;
+
+    for params in [
+        json!({ "maxPrice": "100" }),
+        json!({ "sort": "PRICE_ASC" }),
+    ] {
+        let mut variables = filter(&[], &[], &[]);
+        variables["params"]
+            .as_object_mut()
+            .unwrap()
+            .extend(params.as_object().unwrap().clone());
+        let message = app
+            .query_error(Some(&alice.token), USER_LOTS, variables)
+            .await;
+        assert!(message.starts_with("Unprocessable Entity"), "{}", message);
+    }
+
+    let mut variables = filter(&[], &[], &[]);
+    variables["params"]["maxPrice"] = json!("100");
+    variables["params"]["currencySymbol"] = json!("USD");
+    let data = app.query(Some(&alice.token), USER_LOTS, variables).await;
+    assert_eq!(titles(&data, "getUserLots"), ["Red bricks"]);
+}
+
+#[actix_rt::test]
+async fn paging_through_lots_with_equal_sort_keys_lists_each_lot_once() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    // imported in one transaction, the lots share their created and updated times
+    let mut file = String::from("category,condition,title,description,status,askingPrice\n");
+    for i in 0..25 {
+        let title = ["Red bricks", "Blue bricks", "Green bricks"][i % 3];
+        file.push_str(&format!("part,used,{},a lot,for sale,{}\n", title, i % 4));
+    }
+    let data = app
+        .query(Some(&alice.token), IMPORT, import("CSV", &file, false))
+        .await;
+    assert_eq!(data["importLots"]["imported"], 25);
+
+    let ids = |data: &serde_json::Value| -> Vec<String> {
+        data["getUserLots"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|lot| lot["lot"]["id"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    for sort in [
+        "NEWEST",
+        "OLDEST",
+        "RECENTLY_UPDATED",
+        "PRICE_ASC",
+        "PRICE_DESC",
+        "RELEVANCE",
+        "TITLE_ASC",
+    ] {
+        let mut variables = filter(&["bricks"], &[], &[]);
+        variables["params"]["sort"] = json!(sort);
+        variables["params"]["currencySymbol"] = json!("USD");
+
+        // without a page or limit every lot comes at once
+        let data = app
+            .query(Some(&alice.token), LOT_IDS, variables.clone())
+            .await;
+        let all = ids(&data);
+        assert_eq!(all.len(), 25, "{}", sort);
+
+        let mut paged: Vec<String> = Vec::new();
+        variables["params"]["limit"] = json!(4);
+        loop {
+            let data = app
+                .query(Some(&alice.token), LOT_IDS, variables.clone())
+                .await;
+            let page = ids(&data);
+            let Some(last) = page.last() else {
+                break;
+            };
+            variables["params"]["after"] = json!(last);
+            paged.extend(page);
+        }
+        assert_eq!(paged, all, "{}", sort);
+    }
+}
+
+#[actix_rt::test]
+async fn a_lot_that_skipped_validation_with_a_malformed_catalog_item_id_is_refused() {
+    let app = TestApp::start().await;
+    app.signup("alice").await;
+    let auth = app
+        .db
+        .send(ImpersonateUser {
+            username: "alice".to_string(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    let lot = CreateLot {
+        category: "part".to_string(),
+        condition: "used".to_string(),
+        title: "Red bricks".to_string(),
+        external_id: None,
+        description: "a lot".to_string(),
+        images: vec![],
+        meta_data: json!({}),
+        asking_price: None,
+        currency_symbol: None,
+        quantity: None,
+        catalog_item_id: Some("not-a-uuid".to_string()),
+        nft_address: None,
+        token_id: None,
+    };
+    let res = app.db.send(CreateLotAuthenticated { auth, lot }).await.unwrap();
+    assert!(matches!(res, Err(Error::UnprocessableEntity(_))), "{:?}", res);
+}
+
+#[actix_rt::test]
+async fn lots_are_filtered_by_a_meta_data_value_or_range() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    for (title, meta_data) in [
+        ("Red bricks", json!({ "theme": "Star Wars", "year": 2001 })),
+        ("Blue bricks", json!({ "theme": "City", "year": 2010 })),
+    ] {
+        let id = create_lot(&app, &alice, title, "part").await;
+        app.query(
+            Some(&alice.token),
+            UPDATE,
+            json!({ "params": { "lotId": id, "metaData": meta_data, "deletedImageIds": [] } }),
+        )
+        .await;
+    }
+    let with_meta_data = |filters: serde_json::Value| {
+        let mut variables = filter(&[], &[], &[]);
+        variables["params"]["metaData"] = filters;
+        variables
+    };
+
+    for (filters, expected) in [
+        (json!([{ "key": "theme", "equals": "Star Wars" }]), vec!["Red bricks"]),
+        (json!([{ "key": "year", "min": 2005 }]), vec!["Blue bricks"]),
+        (json!([{ "key": "theme", "equals": "Castle" }]), vec![]),
+        (json!([{ "key": "theme", "equals": "City" }, { "key": "year", "max": 2005 }]), vec![]),
+    ] {
+        let data = app
+            .query(Some(&alice.token), USER_LOTS, with_meta_data(filters.clone()))
+            .await;
+        assert_eq!(titles(&data, "getUserLots"), expected, "{}", filters);
+    }
+
+    // what Postgres cannot take as a JSON path or value is a validation error
+    for filters in [
+        json!([{ "key": "the\0me" }]),
+        json!([{ "key": "theme", "equals": "Star\0Wars" }]),
+        json!([{ "key": "theme", "equals": { "name": "Star Wars" } }]),
+    ] {
+        let message = app
+            .query_error(Some(&alice.token), USER_LOTS, with_meta_data(filters.clone()))
+            .await;
+        assert_eq!(message, "Validation Errors", "{}", filters);
+    }
+}
+
+#[actix_rt::test]
+async fn lots_picked_from_the_catalog_take_the_item_number_of_the_item() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    app.db
+        .send(SetUserFlags {
+            username: "alice".to_string(),
+            email_verified: None,
+            is_admin: Some(true),
+            is_disabled: None,
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    app.query(
+        Some(&alice.token),
+        "mutation($params: ImportCatalog!) { importCatalog(params: $params) { imported } }",
+        json!({ "params": {
+            "format": "REBRICKABLE",
+            "items": "set_num,name\n10030-1,Star Destroyer\n6080-1,King's Castle\n",
+        } }),
+    )
+    .await;
+    let mut item_ids = Vec::new();
+    for name in ["Destroyer", "Castle"] {
+        let data = app
+            .query(
+                None,
+                "query($query: String!) { searchCatalog(query: $query) { id } }",
+                json!({ "query": name }),
+            )
+            .await;
+        item_ids.push(data["searchCatalog"][0]["id"].as_str().unwrap().to_string());
+    }
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            "mutation($params: CreateLot!) { createLot(params: $params) { lot { id externalId } } }",
+            json!({ "params": {
+                "category": "set",
+                "condition": "used",
+                "title": "A set",
+                "description": "a lot",
+                "images": [],
+                "metaData": {},
+                "catalogItemId": item_ids[0],
+            } }),
+        )
+        .await;
+    assert_eq!(data["createLot"]["lot"]["externalId"], "10030-1");
+    let id = data["createLot"]["lot"]["id"].as_str().unwrap();
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            "mutation($params: UpdateLot!) { updateLot(params: $params) { lot { externalId } } }",
+            json!({ "params": {
+                "lotId": id,
+                "catalogItemId": item_ids[1],
+                "deletedImageIds": [],
+            } }),
+        )
+        .await;
+    assert_eq!(data["updateLot"]["lot"]["externalId"], "6080-1");
+}
@@ -0,0 +1,79 @@
+use super::{TestApp, TestUser};
+
+const SEND_MESSAGE: &str =
+    "mutation($params: SendMessage!) { sendMessage(params: $params) { conversationId } }";
+const MESSAGES: &str = "query($id: String!) { messages(conversationId: $id) { body } }";
+const MARK_READ: &str = "mutation($id: String!) { markConversationRead(conversationId: $id) }";
+const CONVERSATIONS: &str = "{ conversations { id } }";
+const BLOCK: &str = "mutation($username: String!) { blockUser(username: $username) }";
+const UNBLOCK: &str = "mutation($username: String!) { unblockUser(username: $username) }";
+
+fn to(recipient: &str, body: &str) -> serde_json::Value {
+    json!({ "params": { "recipient": recipient, "body": body } })
+}
+
+// the error of a message from the sender to the recipient
+async fn refused(app: &TestApp, sender: &TestUser, recipient: &str) -> String {
+    app.query_error(Some(&sender.token), SEND_MESSAGE, to(recipient, "hello"))
+        .await
+}
+
+#[actix_rt::test]
+async fn a_conversation_does_not_exist_for_anyone_but_its_participants() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    let carol = app.signup("carol").await;
+    let data = app
+        .query(Some(&bob.token), SEND_MESSAGE, to("alice", "hello"))
+        .await;
+    let id = data["sendMessage"]["conversationId"].clone();
+
+    let message = app
+        .query_error(Some(&carol.token), MESSAGES, json!({ "id": id }))
+        .await;
+    assert!(message.starts_with("Not Found"), "{}", message);
+    let message = app
+        .query_error(Some(&carol.token), MARK_READ, json!({ "id": id }))
+        .await;
+    assert!(message.starts_with("Not Found"), "{}", message);
+
+    // the message is still unread for alice
+    let data = app
+        .query(Some(&alice.token), MESSAGES, json!({ "id": id }))
+        .await;
+    assert_eq!(data["messages"], json!([{ "body": "hello" }]));
+    let data = app
+        .query(Some(&alice.token), MARK_READ, json!({ "id": id }))
+        .await;
+    assert_eq!(data["markConversationRead"], 1);
+}
+
+#[actix_rt::test]
+async fn a_block_either_way_stops_a_conversation_from_being_started() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+
+    app.query(Some(&alice.token), BLOCK, json!({ "username": "bob" }))
+        .await;
+    let message = refused(&app, &bob, "alice").await;
+    assert!(message.starts_with("Forbidden"), "{}", message);
+    let message = refused(&app, &alice, "bob").await;
+    assert!(message.starts_with("Forbidden"), "{}", message);
+
+    app.query(Some(&alice.token), UNBLOCK, json!({ "username": "bob" }))
+        .await;
+    app.query(Some(&bob.token), BLOCK, json!({ "username": "alice" }))
+        .await;
+    let message = refused(&app, &bob, "alice").await;
+    assert!(message.starts_with("Forbidden"), "{}", message);
+    let message = refused(&app, &alice, "bob").await;
+    assert!(message.starts_with("Forbidden"), "{}", message);
+
+    // no empty conversation is left behind either
+    for user in [&alice, &bob] {
+        let data = app.query(Some(&user.token), CONVERSATIONS, json!({})).await;
+        assert_eq!(data["conversations"], json!([]));
+    }
+}
@@ -0,0 +1,222 @@
+// the integration tests, which run GraphQL operations against the schema in-process
+// every test gets a database of its own on the server of TEST_DATABASE_URL, or of
+// DATABASE_URL, with the migrations applied, and drops it when it is done
+// without either url set the tests fail, so that tests that did not run cannot pass
+
+mod alerts;
+mod articles;
+mod lots;
+mod messages;
+mod orders;
+mod profiles;
+mod users;
+mod wallets;
+
+use std::env;
+use std::sync::mpsc;
+use std::thread;
+
+use actix::prelude::*;
+use async_graphql::{Request, Variables};
+use diesel::prelude::*;
+use diesel_migrations::MigrationHarness;
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+
+use crate::{
+    app::{admin::SetUserFlags, build_schema, GraphqlSchema},
+    config,
+    db::{new_pool, Conn, Db, MIGRATIONS},
+    utils::auth::{Token, Viewer},
+};
+
+pub const PASSWORD: &str = "Test-pass1";
+
+pub struct TestApp {
+    pub schema: GraphqlSchema,
+    pub db: Db,
+    // the executors take their messages from the arbiter of the system they are started
+    // in, which runs apart from the test, as the server's runs apart from its workers
+    // the validators that block on a db message would wait on themselves otherwise
+    system: System,
+    // dropped along with the app
+    database: TestDatabase,
+}
+
+// a signed up user with a verified email
+pub struct TestUser {
+    pub email: String,
+    pub token: String,
+}
+
+impl TestApp {
+    pub async fn start() -> TestApp {
+        config::load_for_tests();
+
+        let database = TestDatabase::create(&server_url());
+        let conn =
+            &mut Conn::establish(&database.url).expect("could not connect to the test database");
+        conn.run_pending_migrations(MIGRATIONS)
+            .expect("could not apply the migrations");
+
+        let pool = new_pool(database.url.clone(), 4).expect("could not create the pool");
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let system = System::new();
+            system.block_on(async move {
+                sender.send((Db::start(2, pool), System::current())).ok();
+            });
+            system.run().ok();
+        });
+        let (db, system) = receiver.recv().expect("the db executors did not start");
+
+        TestApp {
+            schema: build_schema(db.clone()),
+            db,
+            system,
+            database,
+        }
+    }
+
+    // runs an operation, as a user when a token is given
+    pub async fn execute(
+        &self,
+        token: Option<&str>,
+        query: &str,
+        variables: JsonValue,
+    ) -> async_graphql::Response {
+        let mut request = Request::new(query)
+            .variables(Variables::from_json(variables))
+            .data(Viewer::default());
+        if let Some(token) = token {
+            request = request.data(Token(token.to_string()));
+        }
+        self.schema.execute(request).await
+    }
+
+    // the data of an operation that has to succeed
+    pub async fn query(&self, token: Option<&str>, query: &str, variables: JsonValue) -> JsonValue {
+        let response = self.execute(token, query, variables).await;
+        assert!(
+            response.errors.is_empty(),
+            "{} failed: {:?}",
+            query,
+            response.errors
+        );
+        response.data.into_json().unwrap()
+    }
+
+    // the message of the first error of an operation that has to fail
+    pub async fn query_error(
+        &self,
+        token: Option<&str>,
+        query: &str,
+        variables: JsonValue,
+    ) -> String {
+        let response = self.execute(token, query, variables).await;
+        match response.errors.first() {
+            Some(error) => error.message.clone(),
+            None => panic!("{} did not fail: {:?}", query, response.data),
+        }
+    }
+
+    // signs up a user and verifies their email, as following the link would
+    pub async fn signup(&self, username: &str) -> TestUser {
+        let email = format!("{}@example.com", username);
+        let data = self
+            .query(
+                None,
+                "mutation($params: RegisterUser!) { signup(params: $params) { user { token } } }",
+                json!({ "params": { "username": username, "email": email, "password": PASSWORD } }),
+            )
+            .await;
+
+        self.db
+            .send(SetUserFlags {
+                username: username.to_string(),
+                email_verified: Some(true),
+                is_admin: None,
+                is_disabled: None,
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        TestUser {
+            email,
+            token: data["signup"]["user"]["token"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        }
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        self.system.stop();
+    }
+}
+
+// the url of the database server the test databases are created on
+pub fn server_url() -> String {
+    dotenv::dotenv().ok();
+    env::var("TEST_DATABASE_URL")
+        .or_else(|_| env::var("DATABASE_URL"))
+        .expect("set TEST_DATABASE_URL, or DATABASE_URL, to a database server to run the tests on")
+}
+
+// a database named after a random uuid, dropped along with the connections left to it
+pub struct TestDatabase {
+    server_url: String,
+    name: String,
+    pub url: String,
+}
+
+impl TestDatabase {
+    pub fn create(server_url: &str) -> Self {
+        let name = format!("test_{}", Uuid::new_v4().simple());
+        let database = TestDatabase {
+            server_url: database_url(server_url, "postgres"),
+            url: database_url(server_url, &name),
+            name,
+        };
+
+        let conn = &mut Conn::establish(&database.server_url)
+            .expect("could not connect to the postgres database of the test database server");
+        diesel::sql_query(format!("CREATE DATABASE {}", database.name))
+            .execute(conn)
+            .expect("could not create the test database");
+
+        database
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        let dropped = Conn::establish(&self.server_url)
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| {
+                diesel::sql_query(format!(
+                    "DROP DATABASE IF EXISTS {} WITH (FORCE)",
+                    self.name
+                ))
+                .execute(&mut conn)
+                .map_err(|e| e.to_string())
+            });
+        if let Err(e) = dropped {
+            eprintln!("could not drop the test database {}: {}", self.name, e);
+        }
+    }
+}
+
+// the url with another database name, keeping its parameters
+fn database_url(url: &str, name: &str) -> String {
+    let (server, database) = url
+        .rsplit_once('/')
+        .expect("the database url has no database name");
+    match database.split_once('?') {
+        Some((_, parameters)) => format!("{}/{}?{}", server, name, parameters),
+        None => format!("{}/{}", server, name),
+    }
+}
@@ -0,0 +1,142 @@
+use super::TestApp;
+
+const CREATE: &str =
+    "mutation($params: CreateLot!) { createLot(params: $params) { lot { id } } }";
+const LIST: &str = "mutation($params: UpdateLot!) { updateLot(params: $params) { lot { status } } }";
+const PLACE_ORDER: &str =
+    "mutation($params: PlaceOrder!) { placeOrder(params: $params) { id state } }";
+const UPDATE_STATE: &str =
+    "mutation($params: UpdateOrderState!) { updateOrderState(params: $params) { state } }";
+const REVIEW: &str = "mutation($params: ReviewOrder!) { reviewOrder(params: $params) { rating } }";
+
+#[actix_rt::test]
+async fn lots_of_more_than_one_are_not_ordered() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+
+    let mut ids = Vec::new();
+    for quantity in [5, 1] {
+        let data = app
+            .query(
+                Some(&alice.token),
+                CREATE,
+                json!({ "params": {
+                    "category": "part",
+                    "condition": "used",
+                    "title": "Red bricks",
+                    "description": "a lot",
+                    "images": [],
+                    "metaData": {},
+                    "quantity": quantity,
+                } }),
+            )
+            .await;
+        let id = data["createLot"]["lot"]["id"].as_str().unwrap().to_string();
+        app.query(
+            Some(&alice.token),
+            LIST,
+            json!({ "params": {
+                "lotId": id,
+                "status": "for sale",
+                "askingPrice": "10",
+                "deletedImageIds": [],
+            } }),
+        )
+        .await;
+        ids.push(id);
+    }
+
+    let order = |id: &str| {
+        json!({ "params": { "lotId": id, "shippingAddress": {
+            "name": "Bob",
+            "line1": "Main street 1",
+            "city": "Utrecht",
+            "postalCode": "1234 AB",
+            "country": "NL",
+        } } })
+    };
+    let message = app
+        .query_error(Some(&bob.token), PLACE_ORDER, order(&ids[0]))
+        .await;
+    assert!(message.starts_with("Unprocessable Entity"), "{}", message);
+    let data = app.query(Some(&bob.token), PLACE_ORDER, order(&ids[1])).await;
+    assert_eq!(data["placeOrder"]["state"], "AWAITING_PAYMENT");
+}
+
+#[actix_rt::test]
+async fn an_order_is_reviewed_once_it_is_delivered_and_only_once() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+
+    let data = app
+        .query(
+            Some(&alice.token),
+            CREATE,
+            json!({ "params": {
+                "category": "part",
+                "condition": "used",
+                "title": "Red bricks",
+                "description": "a lot",
+                "images": [],
+                "metaData": {},
+            } }),
+        )
+        .await;
+    let lot_id = data["createLot"]["lot"]["id"].as_str().unwrap().to_string();
+    app.query(
+        Some(&alice.token),
+        LIST,
+        json!({ "params": {
+            "lotId": lot_id,
+            "status": "for sale",
+            "askingPrice": "10",
+            "deletedImageIds": [],
+        } }),
+    )
+    .await;
+    let data = app
+        .query(
+            Some(&bob.token),
+            PLACE_ORDER,
+            json!({ "params": { "lotId": lot_id, "shippingAddress": {
+                "name": "Bob",
+                "line1": "Main street 1",
+                "city": "Utrecht",
+                "postalCode": "1234 AB",
+                "country": "NL",
+            } } }),
+        )
+        .await;
+    let order_id = data["placeOrder"]["id"].clone();
+    let review = json!({ "params": { "orderId": order_id, "rating": 5, "body": "fast" } });
+
+    for state in ["PAID", "SHIPPED"] {
+        app.query(
+            Some(&alice.token),
+            UPDATE_STATE,
+            json!({ "params": { "orderId": order_id, "state": state } }),
+        )
+        .await;
+    }
+    let message = app
+        .query_error(Some(&bob.token), REVIEW, review.clone())
+        .await;
+    assert!(message.contains("only delivered orders"), "{}", message);
+
+    app.query(
+        Some(&bob.token),
+        UPDATE_STATE,
+        json!({ "params": { "orderId": order_id, "state": "DELIVERED" } }),
+    )
+    .await;
+    let data = app.query(Some(&bob.token), REVIEW, review.clone()).await;
+    assert_eq!(data["reviewOrder"]["rating"], 5);
+    let message = app.query_error(Some(&bob.token), REVIEW, review.clone()).await;
+    assert!(message.contains("already reviewed"), "{}", message);
+
+    // the seller still reviews the buyer once
+    let data = app.query(Some(&alice.token), REVIEW, review).await;
+    assert_eq!(data["reviewOrder"]["rating"], 5);
+}
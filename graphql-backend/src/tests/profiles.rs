@@ -0,0 +1,67 @@
+use super::TestApp;
+
+const FOLLOW: &str =
+    "mutation($username: String!) { followUser(username: $username) { profile { username following } } }";
+const UNFOLLOW: &str =
+    "mutation($username: String!) { unfollowUser(username: $username) { profile { username following } } }";
+const PROFILE: &str =
+    "query($username: String!) { getProfile(username: $username) { profile { following } } }";
+const FEED: &str = "{ getArticleFeed(params: {}) { articlesCount articles { title } } }";
+
+#[actix_rt::test]
+async fn following_a_user_shows_on_their_profile_and_in_the_feed() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+    let bob = app.signup("bob").await;
+    app.query(
+        Some(&alice.token),
+        "mutation { createActicle(params: { title: \"Castle builds\", description: \"d\", body: \"b\", tagList: [\"lego\"] }) { article { slug } } }",
+        json!({}),
+    )
+    .await;
+
+    let data = app.query(Some(&bob.token), FEED, json!({})).await;
+    assert_eq!(data["getArticleFeed"]["articlesCount"], 0);
+
+    let data = app
+        .query(Some(&bob.token), FOLLOW, json!({ "username": "alice" }))
+        .await;
+    assert_eq!(data["followUser"]["profile"]["following"], true);
+
+    let data = app
+        .query(Some(&bob.token), PROFILE, json!({ "username": "alice" }))
+        .await;
+    assert_eq!(data["getProfile"]["profile"]["following"], true);
+    let data = app
+        .query(Some(&alice.token), PROFILE, json!({ "username": "bob" }))
+        .await;
+    assert_eq!(data["getProfile"]["profile"]["following"], false);
+
+    let data = app.query(Some(&bob.token), FEED, json!({})).await;
+    assert_eq!(
+        data["getArticleFeed"]["articles"],
+        json!([{ "title": "Castle builds" }])
+    );
+
+    let data = app
+        .query(Some(&bob.token), UNFOLLOW, json!({ "username": "alice" }))
+        .await;
+    assert_eq!(data["unfollowUser"]["profile"]["following"], false);
+    let data = app.query(Some(&bob.token), FEED, json!({})).await;
+    assert_eq!(data["getArticleFeed"]["articlesCount"], 0);
+}
+
+#[actix_rt::test]
+async fn users_cannot_follow_themselves() {
+    let app = TestApp::start().await;
+    let alice = app.signup("alice").await;
+
+    let message = app
+        .query_error(Some(&alice.token), FOLLOW, json!({ "username": "alice" }))
+        .await;
+    assert!(
+        message.contains("You cannot follow yourself"),
+        "{}",
+        message
+    );
+}
@@ -0,0 +1,113 @@
+use super::{TestApp, PASSWORD};
+
+const SIGNUP: &str =
+    "mutation($params: RegisterUser!) { signup(params: $params) { user { username email token } } }";
+const SIGNIN: &str =
+    "mutation($params: LoginUser!) { signin(params: $params) { user { username token } } }";
+const CURRENT_USER: &str = "{ getCurrentUser { user { username email } } }";
+
+#[actix_rt::test]
+async fn signup_returns_a_token_for_the_new_user() {
+    let app = TestApp::start().await;
+
+    let data = app
+        .query(
+            None,
+            SIGNUP,
+            json!({ "params": { "username": "alice", "email": "alice@example.com", "password": PASSWORD } }),
+        )
+        .await;
+    assert_eq!(data["signup"]["user"]["username"], "alice");
+    assert_eq!(data["signup"]["user"]["email"], "alice@example.com");
+
+    let token = data["signup"]["user"]["token"].as_str().unwrap();
+    let data = app.query(Some(token), CURRENT_USER, json!({})).await;
+    assert_eq!(data["getCurrentUser"]["user"]["username"], "alice");
+}
+
+#[actix_rt::test]
+async fn signup_refuses_taken_usernames_and_emails_and_weak_passwords() {
+    let app = TestApp::start().await;
+    app.signup("alice").await;
+
+    let taken = app
+        .execute(
+            None,
+            SIGNUP,
+            json!({ "params": { "username": "alice", "email": "alice@example.com", "password": PASSWORD } }),
+        )
+        .await;
+    let errors = taken.errors[0]
+        .extensions
+        .as_ref()
+        .unwrap()
+        .get("errors")
+        .unwrap();
+    let errors = errors.clone().into_json().unwrap().to_string();
+    assert!(errors.contains("username already taken"), "{}", errors);
+    assert!(errors.contains("email already registered"), "{}", errors);
+
+    let message = app
+        .query_error(
+            None,
+            SIGNUP,
+            json!({ "params": { "username": "bob", "email": "bob@example.com", "password": "password" } }),
+        )
+        .await;
+    assert_eq!(message, "Validation Errors");
+}
+
+#[actix_rt::test]
+async fn signin_needs_the_right_password_and_a_verified_email() {
+    let app = TestApp::start().await;
+    app.query(
+        None,
+        SIGNUP,
+        json!({ "params": { "username": "bob", "email": "bob@example.com", "password": PASSWORD } }),
+    )
+    .await;
+
+    let unverified = app
+        .query_error(
+            None,
+            SIGNIN,
+            json!({ "params": { "email": "bob@example.com", "password": PASSWORD } }),
+        )
+        .await;
+    assert_eq!(unverified, "email not verified");
+
+    let alice = app.signup("alice").await;
+    let wrong_password = app
+        .execute(
+            None,
+            SIGNIN,
+            json!({ "params": { "email": alice.email, "password": "Wrong-pass1" } }),
+        )
+        .await;
+    assert!(!wrong_password.errors.is_empty());
+
+    let data = app
+        .query(
+            None,
+            SIGNIN,
+            json!({ "params": { "email": alice.email, "password": PASSWORD } }),
+        )
+        .await;
+    assert_eq!(data["signin"]["user"]["username"], "alice");
+    let token = data["signin"]["user"]["token"].as_str().unwrap();
+    let data = app.query(Some(token), CURRENT_USER, json!({})).await;
+    assert_eq!(data["getCurrentUser"]["user"]["username"], "alice");
+}
+
+#[actix_rt::test]
+async fn current_user_needs_a_valid_token() {
+    let app = TestApp::start().await;
+
+    let missing = app.query_error(None, CURRENT_USER, json!({})).await;
+    assert_eq!(missing, "no authorization was provided");
+
+    let invalid = app
+        .execute(Some("not a token"), CURRENT_USER, json!({}))
+        .await;
+    assert!(!invalid.errors.is_empty());
+}
@@ -0,0 +1,90 @@
+use chrono::{SecondsFormat, Utc};
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
+
+use super::TestApp;
+use crate::utils::abi::to_hex;
+use crate::utils::siwe::siwe_domain;
+
+const ADDRESS: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+// a sign-in message for a fresh nonce, and its personal_sign signature
+async fn signed_message(app: &TestApp, chain_id: i64) -> (String, String) {
+    let data = app.query(None, "{ siweNonce }", json!({})).await;
+    let message = format!(
+        "{domain} wants you to sign in with your Ethereum account:\n\
+         {ADDRESS}\n\
+         \n\
+         URI: http://{domain}\n\
+         Version: 1\n\
+         Chain ID: {chain_id}\n\
+         Nonce: {}\n\
+         Issued At: {}",
+        data["siweNonce"].as_str().unwrap(),
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        domain = siwe_domain(),
+    );
+
+    let key = SigningKey::from_slice(&[
+        0x4c, 0x08, 0x83, 0xa6, 0x91, 0x02, 0x93, 0x7d, 0x62, 0x31, 0x47, 0x1b, 0x5d, 0xbb, 0x62,
+        0x04, 0xfe, 0x51, 0x29, 0x61, 0x70, 0x82, 0x79, 0x2a, 0xe4, 0x68, 0xd0, 0x1a, 0x3f, 0x36,
+        0x23, 0x18,
+    ])
+    .unwrap();
+    let prefixed = format!("\x19Ethereum Signed Message:\n{}{}", message.len(), message);
+    let (signature, recovery_id) = key
+        .sign_prehash_recoverable(&Keccak256::digest(prefixed.as_bytes()))
+        .unwrap();
+    let mut signature = signature.to_bytes().to_vec();
+    signature.push(27 + recovery_id.to_byte());
+
+    (message, format!("0x{}", to_hex(&signature)))
+}
+
+#[actix_rt::test]
+async fn wallets_are_remembered_on_the_chain_they_last_signed_in_on() {
+    let app = TestApp::start().await;
+    let signin = "mutation($message: String!, $signature: String!) {
+        siweSignin(message: $message, signature: $signature) {
+            user { username wallets { address chainId } }
+        }
+    }";
+
+    let (message, signature) = signed_message(&app, 1).await;
+    let data = app
+        .query(
+            None,
+            signin,
+            json!({ "message": message, "signature": signature }),
+        )
+        .await;
+    assert_eq!(
+        data["siweSignin"]["user"]["wallets"],
+        json!([{ "address": ADDRESS, "chainId": 1 }])
+    );
+
+    let (message, signature) = signed_message(&app, 137).await;
+    let data = app
+        .query(
+            None,
+            signin,
+            json!({ "message": message, "signature": signature }),
+        )
+        .await;
+    assert_eq!(data["siweSignin"]["user"]["username"], "wallet_2c7536e3");
+    assert_eq!(
+        data["siweSignin"]["user"]["wallets"],
+        json!([{ "address": ADDRESS, "chainId": 137 }])
+    );
+
+    // a message is only signed in with once
+    assert_eq!(
+        app.query_error(
+            None,
+            signin,
+            json!({ "message": message, "signature": signature })
+        )
+        .await,
+        "the nonce is unknown, used or expired"
+    );
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+
+use bigdecimal::num_bigint::{BigInt, Sign};
+use bigdecimal::BigDecimal;
+use sha3::{Digest, Keccak256};
+
+// Just enough of the Solidity ABI to decode the events of our own contracts,
+// whose parameters are all static 32 byte words.
+#[derive(Debug, Deserialize)]
+struct AbiEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    name: Option<String>,
+    #[serde(default)]
+    inputs: Vec<AbiParam>,
+    #[serde(default)]
+    anonymous: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbiParam {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub indexed: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AbiEvent {
+    pub name: String,
+    pub inputs: Vec<AbiParam>,
+    // keccak256 of the signature, the first topic of every log of the event
+    pub topic: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AbiValue {
+    // lowercase 0x prefixed hex
+    Address(String),
+    Uint(BigDecimal),
+    Bool(bool),
+    Bytes32(String),
+    // only encoded as a call argument, events with strings are not decoded
+    String(String),
+}
+
+// the non anonymous events of a contract ABI as emitted by forge or solc
+pub fn events(abi: &str) -> Result<Vec<AbiEvent>, String> {
+    let entries: Vec<AbiEntry> = serde_json::from_str(abi).map_err(|e| e.to_string())?;
+
+    Ok(entries
+        .into_iter()
+        .filter(|entry| entry.kind == "event" && !entry.anonymous)
+        .filter_map(|entry| {
+            let name = entry.name?;
+            let signature = format!(
+                "{}({})",
+                name,
+                entry
+                    .inputs
+                    .iter()
+                    .map(|input| input.kind.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(",")
+            );
+            Some(AbiEvent {
+                name,
+                inputs: entry.inputs,
+                topic: format!("0x{}", to_hex(&Keccak256::digest(signature.as_bytes()))),
+            })
+        })
+        .collect())
+}
+
+impl AbiEvent {
+    // decodes the parameters of a log of this event by name
+    // indexed parameters come from the topics after the first, the others from the data
+    pub fn decode(
+        &self,
+        topics: &[String],
+        data: &str,
+    ) -> Result<HashMap<String, AbiValue>, String> {
+        if topics.first().map(|topic| topic.to_lowercase()) != Some(self.topic.clone()) {
+            return Err(format!("log is not a {} event", self.name));
+        }
+
+        let data = from_hex(data)?;
+        let mut data_words = data.chunks(32);
+        let mut indexed_topics = topics.iter().skip(1);
+        let mut values = HashMap::new();
+
+        for input in &self.inputs {
+            let word = if input.indexed {
+                from_hex(
+                    indexed_topics
+                        .next()
+                        .ok_or_else(|| format!("{} is missing topic {}", self.name, input.name))?,
+                )?
+            } else {
+                data_words
+                    .next()
+                    .ok_or_else(|| format!("{} is missing data for {}", self.name, input.name))?
+                    .to_vec()
+            };
+            if word.len() != 32 {
+                return Err(format!(
+                    "{} of {} is not a 32 byte word",
+                    input.name, self.name
+                ));
+            }
+
+            values.insert(input.name.clone(), decode_word(&input.kind, &word)?);
+        }
+
+        Ok(values)
+    }
+}
+
+fn decode_word(kind: &str, word: &[u8]) -> Result<AbiValue, String> {
+    match kind {
+        "address" => Ok(AbiValue::Address(format!("0x{}", to_hex(&word[12..])))),
+        "bool" => Ok(AbiValue::Bool(word[31] == 1)),
+        "bytes32" => Ok(AbiValue::Bytes32(format!("0x{}", to_hex(word)))),
+        kind if kind.starts_with("uint") => Ok(AbiValue::Uint(BigDecimal::from(
+            BigInt::from_bytes_be(Sign::Plus, word),
+        ))),
+        kind => Err(format!("decoding {} parameters is not supported", kind)),
+    }
+}
+
+// the calldata of a call to the function with the signature, i.e. mintTo(address,string)
+pub fn encode_call(signature: &str, args: &[AbiValue]) -> Result<String, String> {
+    let head_size = 32 * args.len();
+    let mut head = Vec::new();
+    let mut tail = Vec::new();
+
+    // strings go in the tail, with their offset from the start of the arguments in the head
+    for arg in args {
+        match arg {
+            AbiValue::String(value) => {
+                head.extend(usize_word(head_size + tail.len()));
+                tail.extend(usize_word(value.len()));
+                let mut bytes = value.as_bytes().to_vec();
+                bytes.resize(value.len().div_ceil(32) * 32, 0);
+                tail.extend(bytes);
+            }
+            value => head.extend(encode_word(value)?),
+        }
+    }
+
+    let selector = &Keccak256::digest(signature.as_bytes())[..4];
+    Ok(format!(
+        "0x{}{}{}",
+        to_hex(selector),
+        to_hex(&head),
+        to_hex(&tail)
+    ))
+}
+
+fn encode_word(value: &AbiValue) -> Result<Vec<u8>, String> {
+    let bytes = match value {
+        AbiValue::Address(address) => from_hex(address)?,
+        AbiValue::Uint(value) => {
+            if !value.is_integer() || value.sign() == Sign::Minus {
+                return Err(format!("{} is not a uint", value));
+            }
+            value
+                .with_scale(0)
+                .into_bigint_and_exponent()
+                .0
+                .to_bytes_be()
+                .1
+        }
+        AbiValue::Bool(value) => vec![*value as u8],
+        AbiValue::Bytes32(value) => from_hex(value)?,
+        AbiValue::String(_) => return Err("a string is not a single word".to_string()),
+    };
+    if bytes.len() > 32 {
+        return Err(format!("0x{} does not fit in a word", to_hex(&bytes)));
+    }
+
+    let mut word = vec![0; 32 - bytes.len()];
+    word.extend(bytes);
+    Ok(word)
+}
+
+fn usize_word(value: usize) -> Vec<u8> {
+    let mut word = vec![0; 24];
+    word.extend((value as u64).to_be_bytes());
+    word
+}
+
+// the string returned by a call, i.e. to tokenURI
+pub fn decode_string(data: &str) -> Result<String, String> {
+    let data = from_hex(data)?;
+    // offsets and lengths come from the node, they are added without overflowing
+    let slice = |at: usize, length: usize| {
+        at.checked_add(length)
+            .and_then(|end| data.get(at..end))
+            .ok_or("the returned data is too short for a string")
+    };
+    let word = |at: usize| -> Result<usize, String> {
+        let word = slice(at, 32)?;
+        if word[..24].iter().any(|byte| *byte != 0) {
+            return Err("the returned data is not a string".to_string());
+        }
+        Ok(u64::from_be_bytes(word[24..].try_into().unwrap()) as usize)
+    };
+
+    let offset = word(0)?;
+    let length = word(offset)?;
+    let start = offset
+        .checked_add(32)
+        .ok_or("the returned data is too short for a string")?;
+    let bytes = slice(start, length)?;
+    String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let hex = hex.trim_start_matches("0x");
+    if !hex.len().is_multiple_of(2) {
+        return Err(format!("{} has an odd number of hex digits", hex));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_string, encode_call, events, to_hex, AbiValue};
+    use bigdecimal::BigDecimal;
+
+    const SELLER: &str = "0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266";
+    const NFT: &str = "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512";
+
+    fn word(value: u64) -> String {
+        format!("{:064x}", value)
+    }
+
+    fn address_word(address: &str) -> String {
+        format!("{:0>64}", &address[2..])
+    }
+
+    #[test]
+    fn event_topics_are_the_hashes_of_their_signatures() {
+        let topics = |abi: &str| {
+            events(abi)
+                .unwrap()
+                .into_iter()
+                .map(|event| (event.name, event.topic))
+                .collect::<Vec<_>>()
+        };
+
+        let mut marketplace = topics(include_str!("../../abi/Marketplace.json"));
+        marketplace.sort();
+        assert_eq!(
+            marketplace,
+            [
+                (
+                    "ItemBought".to_string(),
+                    "0x263223b1dd81e51054a4e6f791d45a4a1ddb4aadcd93a2dfd892615c3fdac187"
+                        .to_string()
+                ),
+                (
+                    "ItemCanceled".to_string(),
+                    "0x9ba1a3cb55ce8d63d072a886f94d2a744f50cddf82128e897d0661f5ec623158"
+                        .to_string()
+                ),
+                (
+                    "ItemListed".to_string(),
+                    "0xd547e933094f12a9159076970143ebe73234e64480317844b0dcb36117116de4"
+                        .to_string()
+                ),
+            ]
+        );
+        assert!(topics(include_str!("../../abi/ToyNFT.json")).contains(&(
+            "Transfer".to_string(),
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef".to_string()
+        )));
+    }
+
+    #[test]
+    fn a_log_is_decoded_from_its_topics_and_data() {
+        let listed = events(include_str!("../../abi/Marketplace.json"))
+            .unwrap()
+            .into_iter()
+            .find(|event| event.name == "ItemListed")
+            .unwrap();
+        // topics of logs may come checksummed
+        let topics = vec![
+            listed.topic.to_uppercase().replacen("0X", "0x", 1),
+            format!("0x{}", address_word(SELLER)),
+            format!("0x{}", address_word(NFT)),
+            format!("0x{}", word(7)),
+        ];
+        let data = format!("0x{}", word(1_000_000_000_000_000_000));
+
+        let values = listed.decode(&topics, &data).unwrap();
+        assert_eq!(values["seller"], AbiValue::Address(SELLER.to_string()));
+        assert_eq!(values["nftAddress"], AbiValue::Address(NFT.to_string()));
+        assert_eq!(values["tokenId"], AbiValue::Uint(BigDecimal::from(7)));
+        assert_eq!(
+            values["price"],
+            AbiValue::Uint(BigDecimal::from(1_000_000_000_000_000_000u64))
+        );
+
+        assert!(listed.decode(&topics[..3], &data).is_err());
+        assert!(listed.decode(&topics, "0x").is_err());
+        assert!(listed.decode(&topics[1..], &data).is_err());
+    }
+
+    #[test]
+    fn calls_are_encoded_as_solidity_does() {
+        assert_eq!(
+            encode_call("tokenURI(uint256)", &[AbiValue::Uint(BigDecimal::from(1))]).unwrap(),
+            format!("0xc87b56dd{}", word(1))
+        );
+
+        // the string goes after the head, behind its offset and its length, padded to a word
+        let uri = "ipfs://lot";
+        assert_eq!(
+            encode_call(
+                "mintTo(address,string)",
+                &[
+                    AbiValue::Address(SELLER.to_string()),
+                    AbiValue::String(uri.to_string())
+                ]
+            )
+            .unwrap(),
+            format!(
+                "0x0075a317{}{}{}{:0<64}",
+                address_word(SELLER),
+                word(64),
+                word(10),
+                to_hex(uri.as_bytes())
+            )
+        );
+
+        assert!(encode_call("f(uint256)", &[AbiValue::Uint(BigDecimal::from(-1))]).is_err());
+        assert!(encode_call("f(uint256)", &[AbiValue::Uint("1.5".parse().unwrap())]).is_err());
+    }
+
+    #[test]
+    fn returned_strings_are_decoded() {
+        let uri = "https://example.com/metadata/lots/1";
+        let data = format!(
+            "0x{}{}{:0<128}",
+            word(32),
+            word(uri.len() as u64),
+            to_hex(uri.as_bytes())
+        );
+        assert_eq!(decode_string(&data).unwrap(), uri);
+        assert_eq!(
+            decode_string(&format!("0x{}{}", word(32), word(0))).unwrap(),
+            ""
+        );
+
+        assert!(decode_string("0x").is_err());
+        assert!(decode_string(&format!("0x{}{}", word(32), word(64))).is_err());
+        // offsets and lengths that would overflow are refused rather than panicking
+        assert!(decode_string(&format!("0x{}", word(u64::MAX))).is_err());
+        assert!(decode_string(&format!("0x{}{}", word(u64::MAX - 31), word(0))).is_err());
+        assert!(decode_string(&format!("0x{}{}", word(32), word(u64::MAX))).is_err());
+    }
+}
@@ -1,9 +1,11 @@
-use crate::app::{AppState};
+use futures::lock::Mutex;
+
+use crate::app::AppState;
 use crate::models::User;
 use crate::prelude::*;
 
 // expand this as needed
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Auth {
     pub user: User,
     pub token: String,
@@ -17,6 +19,13 @@ pub struct GenerateAuth {
 
 pub struct Token(pub String);
 
+// a hash of the address and user agent of the client, which tells anonymous visitors apart
+pub struct Fingerprint(pub String);
+
+// the user a request is made by, once a resolver has asked for it
+#[derive(Default)]
+pub struct Viewer(Mutex<Option<Option<Auth>>>);
+
 pub async fn authenticate_token<'ctx>(
     state: &AppState,
     ctx: &async_graphql::Context<'ctx>,
@@ -26,8 +35,25 @@ pub async fn authenticate_token<'ctx>(
         Ok(token) => {
             let token = token.0.clone();
             let auth = state.db.send(GenerateAuth { token }).await??;
+            // tokens handed out before the account was disabled stop working
+            if auth.user.is_disabled {
+                return Err(Error::Unauthorized("account disabled".to_string()));
+            }
             Ok(auth)
         }
         Err(_) => Err(Error::Unauthorized("no authorization was provided".to_string())),
     }
 }
+
+// the user a request is made by, if any, authenticated once however many resolvers ask
+pub async fn viewer<'ctx>(state: &AppState, ctx: &async_graphql::Context<'ctx>) -> Option<Auth> {
+    let Some(viewer) = ctx.data_opt::<Viewer>() else {
+        return authenticate_token(state, ctx).await.ok();
+    };
+
+    let mut auth = viewer.0.lock().await;
+    if auth.is_none() {
+        *auth = Some(authenticate_token(state, ctx).await.ok());
+    }
+    auth.clone().flatten()
+}
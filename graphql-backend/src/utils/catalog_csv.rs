@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use csv::StringRecord;
+
+use crate::app::catalog::CatalogFormat;
+use crate::error::ValidationError;
+use crate::models::{NewCatalogItem, NewPrice};
+use crate::prelude::*;
+
+// rows of a catalog dump, along with the rows that could not be read
+pub struct ParsedCatalog {
+    pub items: Vec<NewCatalogItem>,
+    pub errors: Vec<ValidationError>,
+}
+
+// positions of the catalog fields in the rows of a dump
+struct Columns {
+    external_id: usize,
+    name: usize,
+    year: Option<usize>,
+    theme: Option<usize>,
+    piece_count: Option<usize>,
+    image_url: Option<usize>,
+}
+
+// columns are looked up by their header, so their order in the dump does not matter
+pub fn parse_catalog(
+    format: CatalogFormat,
+    items: &str,
+    themes: Option<&str>,
+) -> Result<ParsedCatalog> {
+    let themes = match themes {
+        Some(themes) => parse_themes(themes)?,
+        None => HashMap::new(),
+    };
+
+    let mut reader = reader(items);
+    let headers = reader.headers().map_err(|e| field_error("items", e.to_string()))?.clone();
+    let columns = match format {
+        CatalogFormat::Rebrickable => Columns {
+            external_id: required_column(&headers, "set_num")?,
+            name: required_column(&headers, "name")?,
+            year: column(&headers, "year"),
+            theme: column(&headers, "theme_id"),
+            piece_count: column(&headers, "num_parts"),
+            image_url: column(&headers, "img_url"),
+        },
+        CatalogFormat::Bricklink => Columns {
+            external_id: required_column(&headers, "number")?,
+            name: required_column(&headers, "name")?,
+            year: column(&headers, "year released"),
+            theme: column(&headers, "category name"),
+            piece_count: None,
+            image_url: None,
+        },
+    };
+
+    let mut parsed = ParsedCatalog {
+        items: Vec::new(),
+        errors: Vec::new(),
+    };
+    // a dump can list an item more than once, the last row wins
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                parsed.errors.push(line_error(line, e.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+
+        match parse_item(format, &columns, &record, &themes) {
+            Ok(item) => match positions.get(&item.external_id) {
+                Some(&position) => parsed.items[position] = item,
+                None => {
+                    positions.insert(item.external_id.clone(), parsed.items.len());
+                    parsed.items.push(item);
+                }
+            },
+            Err(message) => parsed.errors.push(line_error(line, message)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_item(
+    format: CatalogFormat,
+    columns: &Columns,
+    record: &StringRecord,
+    themes: &HashMap<String, (String, Option<String>)>,
+) -> Result<NewCatalogItem, String> {
+    let field = |index: Option<usize>| {
+        index
+            .and_then(|i| record.get(i))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let number = |index: Option<usize>, name: &str| match field(index) {
+        // bricklink marks unknown years with a question mark
+        None | Some("?") => Ok(None),
+        Some(value) => value
+            .parse::<i32>()
+            .map(Some)
+            .map_err(|_| format!("{} is not a number: {}", name, value)),
+    };
+
+    let external_id = field(Some(columns.external_id)).ok_or("missing item number")?;
+    let name = field(Some(columns.name)).ok_or("missing name")?;
+    let year = number(columns.year, "year")?;
+    let piece_count = number(columns.piece_count, "piece count")?;
+
+    let (theme, image_url) = match format {
+        CatalogFormat::Rebrickable => (
+            field(columns.theme).and_then(|theme_id| root_theme(themes, theme_id)),
+            field(columns.image_url).map(String::from),
+        ),
+        // categories are nested like "Star Wars / Star Wars Episode 4/5/6"
+        CatalogFormat::Bricklink => (
+            field(columns.theme)
+                .and_then(|category| category.split(" / ").next())
+                .map(String::from),
+            Some(format!(
+                "https://img.bricklink.com/ItemImage/SN/0/{}.png",
+                external_id
+            )),
+        ),
+    };
+
+    Ok(NewCatalogItem {
+        source: format.source().to_string(),
+        external_id: external_id.to_string(),
+        name: name.to_string(),
+        theme,
+        year,
+        image_url,
+        piece_count,
+    })
+}
+
+// rows of a price list, along with the rows that could not be read
+pub struct ParsedPrices {
+    pub prices: Vec<NewPrice>,
+    pub errors: Vec<ValidationError>,
+}
+
+// a price list with the columns external_id, currency_symbol, amount and optionally recorded_at
+// prices without a recorded_at are recorded at the time of the import
+pub fn parse_prices(source: &str, prices: &str, now: NaiveDateTime) -> Result<ParsedPrices> {
+    let mut reader = reader(prices);
+    let headers = reader.headers().map_err(|e| field_error("prices", e.to_string()))?.clone();
+    let external_id = required_column(&headers, "external_id")?;
+    let currency_symbol = required_column(&headers, "currency_symbol")?;
+    let amount = required_column(&headers, "amount")?;
+    let recorded_at = column(&headers, "recorded_at");
+
+    let mut parsed = ParsedPrices {
+        prices: Vec::new(),
+        errors: Vec::new(),
+    };
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                parsed.errors.push(line_error(line, e.to_string()));
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let field = |index: usize| {
+            record
+                .get(index)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+
+        let price = (|| {
+            let amount = field(amount).ok_or("missing amount")?;
+            Ok(NewPrice {
+                external_id: field(external_id).ok_or("missing item number")?.to_string(),
+                source: source.to_string(),
+                currency_symbol: field(currency_symbol)
+                    .ok_or("missing currency")?
+                    .to_uppercase(),
+                amount: BigDecimal::from_str(amount)
+                    .map_err(|_| format!("amount is not a number: {}", amount))?,
+                recorded_at: match recorded_at.and_then(field) {
+                    Some(value) => parse_time(value)
+                        .ok_or_else(|| format!("recorded_at is not a date: {}", value))?,
+                    None => now,
+                },
+            })
+        })();
+        match price {
+            Ok(price) => parsed.prices.push(price),
+            Err(message) => parsed.errors.push(line_error(line, message)),
+        }
+    }
+
+    Ok(parsed)
+}
+
+// dates like 2023-06-04, times like 2023-06-04 12:00:00 or 2023-06-04T12:00:00Z
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.naive_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.into()))
+        .ok()
+}
+
+// rebrickable themes by id, with their name and parent theme id
+fn parse_themes(themes: &str) -> Result<HashMap<String, (String, Option<String>)>> {
+    let mut reader = reader(themes);
+    let headers = reader.headers().map_err(|e| field_error("themes", e.to_string()))?.clone();
+    let id = required_column(&headers, "id")?;
+    let name = required_column(&headers, "name")?;
+    let parent_id = column(&headers, "parent_id");
+
+    let mut parsed = HashMap::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| field_error("themes", e.to_string()))?;
+        let field = |index: usize| record.get(index).map(str::trim).unwrap_or_default();
+
+        let parent = parent_id.map(field).filter(|parent| !parent.is_empty());
+        parsed.insert(
+            field(id).to_string(),
+            (field(name).to_string(), parent.map(String::from)),
+        );
+    }
+
+    Ok(parsed)
+}
+
+// sub themes such as "Ultimate Collector Series" are filed under their top level theme
+fn root_theme(themes: &HashMap<String, (String, Option<String>)>, theme_id: &str) -> Option<String> {
+    let mut theme = themes.get(theme_id)?;
+    // bounded in case the dump has a cycle
+    for _ in 0..themes.len() {
+        match theme.1.as_ref().and_then(|parent| themes.get(parent)) {
+            Some(parent) => theme = parent,
+            None => break,
+        }
+    }
+    Some(theme.0.clone())
+}
+
+// bricklink downloads are tab separated and unquoted, rebrickable dumps are regular CSV
+fn reader(data: &str) -> csv::Reader<&[u8]> {
+    let first_line = data.lines().next().unwrap_or_default();
+    let mut builder = csv::ReaderBuilder::new();
+    if first_line.contains('\t') {
+        builder.delimiter(b'\t').quoting(false);
+    }
+    builder.flexible(true).from_reader(data.as_bytes())
+}
+
+fn column(headers: &StringRecord, name: &str) -> Option<usize> {
+    headers
+        .iter()
+        .position(|header| header.trim().eq_ignore_ascii_case(name))
+}
+
+fn required_column(headers: &StringRecord, name: &str) -> Result<usize> {
+    column(headers, name).ok_or_else(|| field_error("header", format!("missing column {}", name)))
+}
+
+fn field_error(key: &str, message: String) -> Error {
+    Error::ValidationErrors(vec![ValidationError::new(key.to_string(), message)])
+}
+
+fn line_error(line: u64, message: String) -> ValidationError {
+    ValidationError::new(format!("line {}", line), message)
+}
@@ -1,5 +1,7 @@
+use bigdecimal::BigDecimal;
 use chrono::NaiveDateTime;
 use serde::{Serialize, Serializer};
+use std::str::FromStr;
 
 // The Serialize trait is not impl'd for NaiveDateTime
 // This is a custom wrapper type to get around that
@@ -21,4 +23,30 @@ impl ScalarType for CustomDateTime {
   fn to_value(&self) -> Value {
     Value::String(self.0.format("%Y-%m-%dT%H:%M:%S.%3fZ").to_string())
   }
-}
\ No newline at end of file
+}
+
+// async-graphql has no scalar for the bigdecimal version diesel uses
+// Numeric amounts are sent as decimal strings so no precision is lost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomDecimal(pub BigDecimal);
+
+#[Scalar]
+impl ScalarType for CustomDecimal {
+  fn parse(value: Value) -> InputValueResult<Self> {
+    match &value {
+      Value::String(s) => Ok(CustomDecimal(BigDecimal::from_str(s)?)),
+      Value::Number(n) => Ok(CustomDecimal(BigDecimal::from_str(&n.to_string())?)),
+      _ => Err(InputValueError::expected_type(value)),
+    }
+  }
+
+  fn to_value(&self) -> Value {
+    let value = self.0.normalized();
+    // whole numbers, like amounts in wei, are sent without an exponent
+    let (_, scale) = value.as_bigint_and_exponent();
+    if scale < 0 {
+      return Value::String(value.with_scale(0).to_string());
+    }
+    Value::String(value.to_string())
+  }
+}
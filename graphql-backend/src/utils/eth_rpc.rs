@@ -0,0 +1,140 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+
+// A minimal Ethereum JSON-RPC client, i.e. for a local anvil node at http://127.0.0.1:8545
+#[derive(Debug, Clone)]
+pub struct EthRpc {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    pub address: String,
+    pub topics: Vec<String>,
+    pub data: String,
+    pub block_number: String,
+    pub block_hash: String,
+    pub transaction_hash: String,
+    pub log_index: String,
+    // set when the log was dropped by a reorg, only seen on subscriptions and filters
+    #[serde(default)]
+    pub removed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Block {
+    hash: String,
+}
+
+impl EthRpc {
+    pub fn new(url: String) -> Self {
+        EthRpc {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: JsonValue,
+    ) -> Result<Option<T>, String> {
+        let response: RpcResponse<T> = self
+            .client
+            .post(&self.url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("{} failed: {}", method, e))?
+            .json()
+            .await
+            .map_err(|e| format!("{} returned an unexpected response: {}", method, e))?;
+
+        match response.error {
+            Some(error) => Err(format!(
+                "{} failed with {}: {}",
+                method, error.code, error.message
+            )),
+            None => Ok(response.result),
+        }
+    }
+
+    pub async fn chain_id(&self) -> Result<i64, String> {
+        let chain_id: Option<String> = self.call("eth_chainId", json!([])).await?;
+        parse_quantity(&chain_id.ok_or("eth_chainId returned nothing")?)
+    }
+
+    pub async fn block_number(&self) -> Result<i64, String> {
+        let number: Option<String> = self.call("eth_blockNumber", json!([])).await?;
+        parse_quantity(&number.ok_or("eth_blockNumber returned nothing")?)
+    }
+
+    // none when the node does not know the block (yet)
+    pub async fn block_hash(&self, number: i64) -> Result<Option<String>, String> {
+        let block: Option<Block> = self
+            .call(
+                "eth_getBlockByNumber",
+                json!([format!("{:#x}", number), false]),
+            )
+            .await?;
+        Ok(block.map(|block| block.hash.to_lowercase()))
+    }
+
+    // the data returned by calling the contract with the calldata at the block
+    pub async fn eth_call(&self, to: &str, data: &str, block: i64) -> Result<String, String> {
+        let returned: Option<String> = self
+            .call(
+                "eth_call",
+                json!([{ "to": to, "data": data }, format!("{:#x}", block)]),
+            )
+            .await?;
+        returned.ok_or_else(|| "eth_call returned nothing".to_string())
+    }
+
+    // logs of the contract in the block range, both ends included, with any of the given first topics
+    pub async fn logs(
+        &self,
+        address: &str,
+        topics: &[String],
+        from_block: i64,
+        to_block: i64,
+    ) -> Result<Vec<Log>, String> {
+        let logs: Option<Vec<Log>> = self
+            .call(
+                "eth_getLogs",
+                json!([{
+                    "address": address,
+                    "fromBlock": format!("{:#x}", from_block),
+                    "toBlock": format!("{:#x}", to_block),
+                    "topics": [topics],
+                }]),
+            )
+            .await?;
+        Ok(logs.unwrap_or_default())
+    }
+}
+
+// quantities are 0x prefixed hex without leading zeroes
+pub fn parse_quantity(quantity: &str) -> Result<i64, String> {
+    i64::from_str_radix(quantity.trim_start_matches("0x"), 16)
+        .map_err(|e| format!("{} is not a quantity: {}", quantity, e))
+}
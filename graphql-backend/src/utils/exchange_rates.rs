@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::Value;
+
+use crate::error::ValidationError;
+use crate::models::{ExchangeRate, NewExchangeRate};
+use crate::prelude::*;
+
+// converted rates are rounded to this many decimals, amounts to the decimals of their currency
+const RATE_DECIMALS: i64 = 18;
+
+// the rates of a base currency at a time, as rate APIs like openexchangerates.org serve them
+// {"base": "USD", "recorded_at": "2026-10-19T12:00:00Z", "rates": {"BTC": "0.0000146", "ETH": 0.00041}}
+#[derive(Debug, Deserialize)]
+struct Snapshot {
+    base: String,
+    // if not set, is the source the snapshot was imported from
+    source: Option<String>,
+    recorded_at: DateTime<Utc>,
+    rates: BTreeMap<String, Value>,
+}
+
+// rates of the snapshots, along with the rates that could not be read
+pub struct ParsedRates {
+    pub rates: Vec<NewExchangeRate>,
+    pub errors: Vec<ValidationError>,
+}
+
+// the contents can be a single snapshot or a list of them, i.e. to backfill the history
+pub fn parse_snapshots(contents: &str, default_source: &str) -> Result<ParsedRates> {
+    let value: Value = serde_json::from_str(contents).map_err(|e| snapshot_error(e.to_string()))?;
+    let snapshots: Vec<Snapshot> = match value {
+        Value::Array(_) => serde_json::from_value(value),
+        _ => serde_json::from_value(value).map(|snapshot| vec![snapshot]),
+    }
+    .map_err(|e| snapshot_error(e.to_string()))?;
+
+    let mut parsed = ParsedRates {
+        rates: Vec::new(),
+        errors: Vec::new(),
+    };
+    for snapshot in snapshots {
+        let base = snapshot.base.to_uppercase();
+        let source = snapshot.source.unwrap_or_else(|| default_source.to_string());
+
+        for (quote, value) in snapshot.rates {
+            let quote = quote.to_uppercase();
+            // rate APIs list the base currency at 1
+            if quote == base {
+                continue;
+            }
+
+            let rate = match value {
+                Value::String(ref s) => BigDecimal::from_str(s).ok(),
+                Value::Number(ref n) => BigDecimal::from_str(&n.to_string()).ok(),
+                _ => None,
+            };
+            match rate.filter(|rate| *rate > BigDecimal::zero()) {
+                Some(rate) => parsed.rates.push(NewExchangeRate {
+                    base_symbol: base.clone(),
+                    quote_symbol: quote,
+                    source: source.clone(),
+                    rate,
+                    recorded_at: snapshot.recorded_at.naive_utc(),
+                }),
+                None => parsed.errors.push(ValidationError::new(
+                    format!("{}/{}", base, quote),
+                    format!("{} is not a positive rate", value),
+                )),
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn snapshot_error(message: String) -> Error {
+    Error::ValidationErrors(vec![ValidationError::new("snapshots".to_string(), message)])
+}
+
+#[derive(Debug)]
+pub struct Conversion {
+    pub amount: BigDecimal,
+    pub rate: BigDecimal,
+    // when the rate used was recorded, none when the currencies are the same
+    pub recorded_at: Option<NaiveDateTime>,
+}
+
+// the latest rate of every pair of currencies as of a time
+// amounts are converted by the rate of the pair, its inverse, or through a third currency
+// both have a rate with, i.e. BTC to ETH through USD
+pub struct ExchangeRates {
+    rates: HashMap<(String, String), (BigDecimal, NaiveDateTime)>,
+    // the decimals of every known currency
+    decimals: HashMap<String, i32>,
+}
+
+impl ExchangeRates {
+    pub fn new(rates: Vec<ExchangeRate>, decimals: HashMap<String, i32>) -> Self {
+        ExchangeRates {
+            rates: rates
+                .into_iter()
+                .map(|rate| {
+                    (
+                        (rate.base_symbol, rate.quote_symbol),
+                        (rate.rate, rate.recorded_at),
+                    )
+                })
+                .collect(),
+            decimals,
+        }
+    }
+
+    // the pair can be recorded both ways, i.e. by sources with different bases, the latest wins
+    fn rate(&self, from: &str, to: &str) -> Option<(BigDecimal, NaiveDateTime)> {
+        let direct = self.rates.get(&(from.to_string(), to.to_string())).cloned();
+        let inverse = self
+            .rates
+            .get(&(to.to_string(), from.to_string()))
+            .map(|(rate, recorded_at)| (rate.inverse(), *recorded_at));
+
+        match (direct, inverse) {
+            (Some(direct), Some(inverse)) if inverse.1 > direct.1 => Some(inverse),
+            (direct, inverse) => direct.or(inverse),
+        }
+    }
+
+    pub fn convert(&self, amount: &BigDecimal, from: &str, to: &str) -> Option<Conversion> {
+        let decimals = *self.decimals.get(to)? as i64;
+
+        let (rate, recorded_at) = if from == to {
+            (BigDecimal::from(1), None)
+        } else if let Some((rate, recorded_at)) = self.rate(from, to) {
+            (rate, Some(recorded_at))
+        } else {
+            // the pair of rates that is the most recent, going by the older one of the two
+            let (rate, recorded_at) = self
+                .decimals
+                .keys()
+                .filter(|via| *via != from && *via != to)
+                .filter_map(|via| {
+                    let (first, first_at) = self.rate(from, via)?;
+                    let (second, second_at) = self.rate(via, to)?;
+                    Some((first * second, first_at.min(second_at)))
+                })
+                .max_by_key(|(_, recorded_at)| *recorded_at)?;
+            (rate, Some(recorded_at))
+        };
+
+        Some(Conversion {
+            amount: (amount * &rate).with_scale_round(decimals, RoundingMode::HalfEven),
+            rate: rate
+                .with_scale_round(RATE_DECIMALS, RoundingMode::HalfEven)
+                .normalized(),
+            recorded_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_snapshots, ExchangeRates};
+    use crate::models::ExchangeRate;
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDateTime;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    fn at(day: u32) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("2026-10-{:02} 12:00:00", day), "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+    }
+
+    fn decimal(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn rate(base: &str, quote: &str, rate: &str, day: u32) -> ExchangeRate {
+        ExchangeRate {
+            base_symbol: base.to_string(),
+            quote_symbol: quote.to_string(),
+            rate: decimal(rate),
+            recorded_at: at(day),
+        }
+    }
+
+    fn rates(rates: Vec<ExchangeRate>) -> ExchangeRates {
+        let decimals = [("USD", 2), ("EUR", 2), ("ETH", 18), ("BTC", 8)]
+            .into_iter()
+            .map(|(symbol, decimals)| (symbol.to_string(), decimals))
+            .collect::<HashMap<_, _>>();
+        ExchangeRates::new(rates, decimals)
+    }
+
+    // the amount and the rate as they are shown
+    fn convert(
+        rates: &ExchangeRates,
+        amount: &str,
+        from: &str,
+        to: &str,
+    ) -> Option<(String, String)> {
+        rates
+            .convert(&decimal(amount), from, to)
+            .map(|conversion| (conversion.amount.to_string(), conversion.rate.to_string()))
+    }
+
+    #[test]
+    fn amounts_are_converted_by_the_rate_of_the_pair_or_its_inverse() {
+        let rates = rates(vec![rate("USD", "ETH", "0.0004", 1)]);
+
+        assert_eq!(
+            convert(&rates, "100", "USD", "ETH"),
+            Some(("0.040000000000000000".to_string(), "0.0004".to_string()))
+        );
+        assert_eq!(
+            convert(&rates, "2", "ETH", "USD"),
+            Some(("5000.00".to_string(), "2500".to_string()))
+        );
+        assert_eq!(
+            rates
+                .convert(&decimal("2"), "ETH", "USD")
+                .unwrap()
+                .recorded_at,
+            Some(at(1))
+        );
+
+        let same = rates.convert(&decimal("1.5"), "USD", "USD").unwrap();
+        assert_eq!(same.amount.to_string(), "1.50");
+        assert_eq!(same.recorded_at, None);
+
+        // no rate, or no such currency
+        assert!(convert(&rates, "1", "EUR", "USD").is_none());
+        assert!(convert(&rates, "1", "USD", "DOGE").is_none());
+    }
+
+    #[test]
+    fn the_latest_of_a_pair_recorded_both_ways_wins() {
+        let older_direct = rates(vec![
+            rate("USD", "ETH", "0.0004", 1),
+            rate("ETH", "USD", "2000", 2),
+        ]);
+        assert_eq!(
+            convert(&older_direct, "100", "USD", "ETH"),
+            Some(("0.050000000000000000".to_string(), "0.0005".to_string()))
+        );
+
+        let older_inverse = rates(vec![
+            rate("USD", "ETH", "0.0004", 2),
+            rate("ETH", "USD", "2000", 1),
+        ]);
+        assert_eq!(
+            convert(&older_inverse, "100", "USD", "ETH"),
+            Some(("0.040000000000000000".to_string(), "0.0004".to_string()))
+        );
+    }
+
+    #[test]
+    fn amounts_are_converted_through_the_most_recent_pair_of_rates() {
+        let rates = rates(vec![
+            rate("USD", "BTC", "0.00002", 1),
+            rate("USD", "ETH", "0.0004", 3),
+            // through EUR is more recent, going by the older of its two rates
+            rate("EUR", "BTC", "0.00001", 2),
+            rate("EUR", "ETH", "0.0004", 4),
+        ]);
+
+        let conversion = rates.convert(&decimal("1"), "BTC", "ETH").unwrap();
+        assert_eq!(conversion.amount.to_string(), "40.000000000000000000");
+        assert_eq!(conversion.rate.to_string(), "40");
+        assert_eq!(conversion.recorded_at, Some(at(2)));
+    }
+
+    #[test]
+    fn amounts_are_rounded_half_to_even_to_the_decimals_of_their_currency() {
+        let rates = rates(vec![
+            rate("USD", "EUR", "0.9", 1),
+            rate("USD", "BTC", "3", 1),
+        ]);
+
+        assert_eq!(
+            convert(&rates, "0.05", "USD", "EUR"),
+            Some(("0.04".to_string(), "0.9".to_string()))
+        );
+        assert_eq!(
+            convert(&rates, "0.15", "USD", "EUR"),
+            Some(("0.14".to_string(), "0.9".to_string()))
+        );
+        // the rate to 18 decimals
+        assert_eq!(
+            convert(&rates, "1", "BTC", "USD"),
+            Some(("0.33".to_string(), "0.333333333333333333".to_string()))
+        );
+    }
+
+    #[test]
+    fn snapshots_are_read_into_rates() {
+        let parsed = parse_snapshots(
+            r#"[
+                {"base": "usd", "recorded_at": "2026-10-01T12:00:00Z",
+                 "rates": {"USD": 1, "eth": 0.0004, "BTC": "0.00002"}},
+                {"base": "EUR", "source": "ecb", "recorded_at": "2026-10-02T12:00:00Z",
+                 "rates": {"USD": "1.08"}}
+            ]"#,
+            "import",
+        )
+        .unwrap();
+        assert!(parsed.errors.is_empty());
+
+        let read = parsed
+            .rates
+            .iter()
+            .map(|rate| {
+                (
+                    rate.base_symbol.as_str(),
+                    rate.quote_symbol.as_str(),
+                    rate.source.as_str(),
+                    rate.rate.to_string(),
+                    rate.recorded_at,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            read,
+            [
+                ("USD", "BTC", "import", "0.00002".to_string(), at(1)),
+                ("USD", "ETH", "import", "0.0004".to_string(), at(1)),
+                ("EUR", "USD", "ecb", "1.08".to_string(), at(2)),
+            ]
+        );
+
+        let single = parse_snapshots(
+            r#"{"base": "USD", "recorded_at": "2026-10-01T12:00:00Z", "rates": {"EUR": 0.92}}"#,
+            "import",
+        )
+        .unwrap();
+        assert_eq!(single.rates.len(), 1);
+    }
+
+    #[test]
+    fn rates_that_are_not_positive_numbers_are_errors() {
+        let parsed = parse_snapshots(
+            r#"{"base": "USD", "recorded_at": "2026-10-01T12:00:00Z",
+                "rates": {"EUR": 0.92, "ETH": 0, "BTC": "-1", "DOGE": "a lot", "XMR": null}}"#,
+            "import",
+        )
+        .unwrap();
+
+        assert_eq!(parsed.rates.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&parsed.errors).unwrap(),
+            json!([
+                { "key": "USD/BTC", "message": "\"-1\" is not a positive rate" },
+                { "key": "USD/DOGE", "message": "\"a lot\" is not a positive rate" },
+                { "key": "USD/ETH", "message": "0 is not a positive rate" },
+                { "key": "USD/XMR", "message": "null is not a positive rate" },
+            ])
+        );
+
+        assert!(parse_snapshots("not json", "import").is_err());
+        assert!(parse_snapshots(r#"{"base": "USD", "rates": {}}"#, "import").is_err());
+    }
+}
@@ -1,9 +1,9 @@
 use chrono::{Duration, Utc};
 use jsonwebtoken::{EncodingKey, DecodingKey};
 use jwt::{decode, encode, Header, TokenData, Validation};
-use std::env;
 use uuid::Uuid;
 
+use crate::config::config;
 use crate::models::User;
 use crate::prelude::*;
 
@@ -19,11 +19,11 @@ pub trait CanGenerateJwt {
 
 impl CanGenerateJwt for User {
     fn generate_jwt(&self) -> Result<String> {
-        let exp = (Utc::now() + Duration::days(21)).timestamp();
+        let exp = (Utc::now() + Duration::days(config().auth.token_lifetime_days)).timestamp();
         let claims = Claims { id: self.id, exp };
 
         let header = Header::default();
-        let secret = &get_secret();
+        let secret = &config().auth.jwt_secret;
         let token = encode(&header, &claims, &EncodingKey::from_secret(secret.as_ref()))?;
 
         Ok(token)
@@ -36,13 +36,9 @@ pub trait CanDecodeJwt {
 
 impl CanDecodeJwt for String {
     fn decode_jwt(&self) -> Result<TokenData<Claims>> {
-        match decode::<Claims>(&self, &DecodingKey::from_secret(get_secret().as_ref()), &Validation::default()) {
+        match decode::<Claims>(self, &DecodingKey::from_secret(config().auth.jwt_secret.as_ref()), &Validation::default()) {
             Ok(res) => Ok(res),
             Err(e) => Err(e.into()),
         }
     }
 }
-
-fn get_secret() -> String {
-    env::var("JWT_SECRET").unwrap_or_else(|_| "secret".into())
-}
@@ -0,0 +1,345 @@
+use csv::StringRecord;
+use serde_json::Value as JsonValue;
+use validator::Validate;
+
+use crate::app::lots::{CreateLot, CreateLotImage, LotFileFormat};
+use crate::error::ValidationError;
+use crate::models::{LotStatus, LotWithImages};
+use crate::prelude::*;
+use crate::utils::meta_data::validate_meta_data;
+use crate::utils::CustomDecimal;
+
+// a lot of a file, with the line it starts on and the status to give it
+pub struct LotRow {
+    pub line: u64,
+    pub lot: CreateLot,
+    pub status: Option<String>,
+}
+
+// rows of a lot file, along with the errors of the rows that do not pass
+pub struct ParsedLots {
+    pub rows: Vec<LotRow>,
+    pub errors: Vec<ValidationError>,
+}
+
+// a line of a JSON lines file, with the fields of createLot and a status
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct JsonLot {
+    category: String,
+    condition: String,
+    title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    external_id: Option<String>,
+    description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    asking_price: Option<CustomDecimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    currency_symbol: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quantity: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    catalog_item_id: Option<String>,
+    #[serde(default)]
+    meta_data: JsonValue,
+    #[serde(default)]
+    images: Vec<JsonLotImage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct JsonLotImage {
+    image_url: String,
+    #[serde(default)]
+    is_thumbnail: bool,
+}
+
+// a row of a CSV file, the columns named as the JSON fields
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CsvLot {
+    category: String,
+    condition: String,
+    title: String,
+    external_id: Option<String>,
+    description: String,
+    status: Option<String>,
+    asking_price: Option<CustomDecimal>,
+    currency_symbol: Option<String>,
+    quantity: Option<i32>,
+    catalog_item_id: Option<String>,
+    // the meta data object as JSON
+    meta_data: Option<String>,
+    // image urls separated by spaces
+    images: Option<String>,
+    // the url of the thumbnail, which does not have to be listed in images as well
+    thumbnail: Option<String>,
+}
+
+pub fn parse_lots(format: LotFileFormat, lots: &str) -> Result<ParsedLots> {
+    let mut parsed = ParsedLots {
+        rows: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let read = match format {
+        LotFileFormat::Csv => read_csv(lots)?,
+        LotFileFormat::JsonLines => read_json_lines(lots),
+    };
+    for (line, result) in read {
+        match result.and_then(|row| check_row(line, row)) {
+            Ok(row) => parsed.rows.push(row),
+            Err(errors) => parsed
+                .errors
+                .extend(errors.into_iter().map(|error| error.on_line(line))),
+        }
+    }
+
+    Ok(parsed)
+}
+
+type ReadRow = (u64, Result<(CreateLot, Option<String>), Vec<ValidationError>>);
+
+fn read_json_lines(lots: &str) -> Vec<ReadRow> {
+    lots.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let row = serde_json::from_str::<JsonLot>(line)
+                .map(|lot| {
+                    let images = lot
+                        .images
+                        .into_iter()
+                        .map(|image| CreateLotImage {
+                            image_url: image.image_url,
+                            is_thumbnail: image.is_thumbnail,
+                        })
+                        .collect();
+                    let meta_data = match lot.meta_data {
+                        JsonValue::Null => json!({}),
+                        meta_data => meta_data,
+                    };
+                    (
+                        CreateLot {
+                            category: lot.category,
+                            condition: lot.condition,
+                            title: lot.title,
+                            external_id: lot.external_id,
+                            description: lot.description,
+                            images,
+                            meta_data,
+                            asking_price: lot.asking_price,
+                            currency_symbol: lot.currency_symbol,
+                            quantity: lot.quantity,
+                            catalog_item_id: lot.catalog_item_id,
+                            nft_address: None,
+                            token_id: None,
+                        },
+                        lot.status,
+                    )
+                })
+                .map_err(|e| vec![row_error(e.to_string())]);
+            (index as u64 + 1, row)
+        })
+        .collect()
+}
+
+fn read_csv(lots: &str) -> Result<Vec<ReadRow>> {
+    let mut reader = csv::ReaderBuilder::new().from_reader(lots.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| field_error("header", e.to_string()))?
+        .clone();
+    for required in ["category", "condition", "title", "description"] {
+        if !headers.iter().any(|header| header == required) {
+            return Err(field_error("header", format!("missing column {}", required)));
+        }
+    }
+
+    Ok(reader
+        .records()
+        .map(|result| match result {
+            Ok(record) => {
+                let line = record.position().map(|p| p.line()).unwrap_or_default();
+                (line, read_csv_row(&record, &headers))
+            }
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                (line, Err(vec![row_error(e.to_string())]))
+            }
+        })
+        .collect())
+}
+
+fn read_csv_row(
+    record: &StringRecord,
+    headers: &StringRecord,
+) -> Result<(CreateLot, Option<String>), Vec<ValidationError>> {
+    let lot: CsvLot = record
+        .deserialize(Some(headers))
+        .map_err(|e| vec![row_error(e.to_string())])?;
+
+    let meta_data = match lot.meta_data.as_deref().map(str::trim) {
+        None | Some("") => json!({}),
+        Some(meta_data) => serde_json::from_str(meta_data).map_err(|e| {
+            vec![ValidationError::new(
+                "meta_data".to_string(),
+                format!("is not JSON: {}", e),
+            )]
+        })?,
+    };
+
+    let thumbnail = lot.thumbnail.as_deref().map(str::trim).filter(|url| !url.is_empty());
+    let mut images: Vec<CreateLotImage> = lot
+        .images
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|url| CreateLotImage {
+            image_url: url.to_string(),
+            is_thumbnail: Some(url) == thumbnail,
+        })
+        .collect();
+    if let Some(url) = thumbnail.filter(|url| !images.iter().any(|image| image.image_url == *url)) {
+        images.insert(
+            0,
+            CreateLotImage {
+                image_url: url.to_string(),
+                is_thumbnail: true,
+            },
+        );
+    }
+
+    Ok((
+        CreateLot {
+            category: lot.category,
+            condition: lot.condition,
+            title: lot.title,
+            external_id: lot.external_id,
+            description: lot.description,
+            images,
+            meta_data,
+            asking_price: lot.asking_price,
+            currency_symbol: lot.currency_symbol,
+            quantity: lot.quantity,
+            catalog_item_id: lot.catalog_item_id,
+            nft_address: None,
+            token_id: None,
+        },
+        lot.status,
+    ))
+}
+
+// the checks createLot and updateLot make that need no database
+fn check_row(
+    line: u64,
+    (lot, status): (CreateLot, Option<String>),
+) -> Result<LotRow, Vec<ValidationError>> {
+    let mut errors = Vec::new();
+
+    if let Err(e) = lot.validate() {
+        if let Error::ValidationErrors(field_errors) = Error::from(e) {
+            errors.extend(field_errors);
+        }
+    }
+    if let Err(Error::ValidationErrors(meta_data_errors)) =
+        validate_meta_data(&lot.category, &lot.meta_data)
+    {
+        errors.extend(meta_data_errors);
+    }
+
+    let status = status.filter(|status| !status.trim().is_empty());
+    match status.as_deref() {
+        Some(status) if status == LotStatus::Deleted.as_str() => errors.push(ValidationError::new(
+            "status".to_string(),
+            "deleted lots are not imported".to_string(),
+        )),
+        Some(status) if status == LotStatus::ForSale.as_str() && lot.asking_price.is_none() => {
+            errors.push(ValidationError::new(
+                "asking_price".to_string(),
+                "an asking price is required to list a lot for sale".to_string(),
+            ))
+        }
+        _ => {}
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(LotRow { line, lot, status })
+}
+
+// a file with the lots, which parse_lots reads back the same
+pub fn write_lots(format: LotFileFormat, lots: Vec<LotWithImages>) -> Result<String> {
+    match format {
+        LotFileFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for LotWithImages { lot, images, .. } in lots {
+                let thumbnail = images
+                    .iter()
+                    .find(|image| image.is_thumbnail)
+                    .map(|image| image.image_url.clone());
+                let images: Vec<String> = images.into_iter().map(|image| image.image_url).collect();
+                writer
+                    .serialize(CsvLot {
+                        category: lot.category,
+                        condition: lot.condition,
+                        title: lot.title,
+                        external_id: lot.external_id,
+                        description: lot.description,
+                        status: Some(lot.status),
+                        asking_price: lot.asking_price.map(CustomDecimal),
+                        currency_symbol: Some(lot.currency_symbol),
+                        quantity: Some(lot.quantity),
+                        catalog_item_id: lot.catalog_item_id.map(|item_id| item_id.to_string()),
+                        meta_data: lot.meta_data.map(|meta_data| meta_data.to_string()),
+                        images: Some(images.join(" ")),
+                        thumbnail,
+                    })
+                    .map_err(|_| Error::InternalServerError)?;
+            }
+            let bytes = writer.into_inner().map_err(|_| Error::InternalServerError)?;
+            String::from_utf8(bytes).map_err(|_| Error::InternalServerError)
+        }
+        LotFileFormat::JsonLines => {
+            let mut file = String::new();
+            for LotWithImages { lot, images, .. } in lots {
+                let line = serde_json::to_string(&JsonLot {
+                    category: lot.category,
+                    condition: lot.condition,
+                    title: lot.title,
+                    external_id: lot.external_id,
+                    description: lot.description,
+                    status: Some(lot.status),
+                    asking_price: lot.asking_price.map(CustomDecimal),
+                    currency_symbol: Some(lot.currency_symbol),
+                    quantity: Some(lot.quantity),
+                    catalog_item_id: lot.catalog_item_id.map(|item_id| item_id.to_string()),
+                    meta_data: lot.meta_data.unwrap_or_else(|| json!({})),
+                    images: images
+                        .into_iter()
+                        .map(|image| JsonLotImage {
+                            image_url: image.image_url,
+                            is_thumbnail: image.is_thumbnail,
+                        })
+                        .collect(),
+                })
+                .map_err(|_| Error::InternalServerError)?;
+                file.push_str(&line);
+                file.push('\n');
+            }
+            Ok(file)
+        }
+    }
+}
+
+fn field_error(key: &str, message: String) -> Error {
+    Error::ValidationErrors(vec![ValidationError::new(key.to_string(), message)])
+}
+
+// an error of the row as a whole, keyed by just its line
+fn row_error(message: String) -> ValidationError {
+    ValidationError::new(String::new(), message)
+}
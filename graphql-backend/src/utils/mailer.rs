@@ -0,0 +1,23 @@
+use crate::prelude::*;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// delivers outgoing email, sending may block so callers keep it off the async runtime
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<()>;
+}
+
+// writes emails to the log instead of sending them, for development and until a provider is set up
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, email: &Email) -> Result<()> {
+        log::info!("email to {}: {}\n{}", email.to, email.subject, email.body);
+        Ok(())
+    }
+}
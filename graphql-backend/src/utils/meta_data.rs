@@ -0,0 +1,42 @@
+use jsonschema::Validator;
+use serde_json::Value as JsonValue;
+
+use crate::error::ValidationError;
+use crate::prelude::*;
+
+// Lot meta data is checked against the JSON schema of the lot's category.
+// Categories without a schema of their own only need an object.
+lazy_static! {
+    static ref SET_SCHEMA: Validator = compile(include_str!("../../schemas/meta_data/set.json"));
+    static ref DEFAULT_SCHEMA: Validator =
+        compile(include_str!("../../schemas/meta_data/default.json"));
+}
+
+fn compile(schema: &str) -> Validator {
+    let schema: JsonValue = serde_json::from_str(schema).expect("meta data schema is not JSON");
+    jsonschema::validator_for(&schema).expect("meta data schema is not a valid JSON schema")
+}
+
+fn schema_for(category: &str) -> &'static Validator {
+    match category {
+        "set" => &SET_SCHEMA,
+        _ => &DEFAULT_SCHEMA,
+    }
+}
+
+// reports every schema violation with the path to the offending value as the key, i.e. meta_data.year
+pub fn validate_meta_data(category: &str, meta_data: &JsonValue) -> Result<()> {
+    let errors: Vec<ValidationError> = schema_for(category)
+        .iter_errors(meta_data)
+        .map(|error| {
+            let path = error.instance_path().to_string().replace('/', ".");
+            ValidationError::new(format!("meta_data{}", path), error.to_string())
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::ValidationErrors(errors))
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_graphql::extensions::{
+    Extension, ExtensionContext, ExtensionFactory, NextParseQuery, NextPrepareRequest, NextRequest,
+    NextValidation,
+};
+use async_graphql::parser::types::{ExecutableDocument, Selection, SelectionSet};
+use async_graphql::{Request, Response, ServerError, ServerResult, ValidationResult, Variables};
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+    Encoder, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+
+use crate::db::{Db, PgPool};
+
+lazy_static! {
+    static ref GRAPHQL_OPERATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "graphql_operation_duration_seconds",
+        "Time to answer a GraphQL operation, by the root field it selects",
+        &["operation"]
+    )
+    .unwrap();
+    static ref GRAPHQL_OPERATION_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "graphql_operation_errors_total",
+        "GraphQL operations answered with errors, by the root field they select",
+        &["operation"]
+    )
+    .unwrap();
+    static ref DB_POOL_CONNECTIONS: IntGaugeVec = register_int_gauge_vec!(
+        "db_pool_connections",
+        "Open database connections, by whether they are idle or in use",
+        &["state"]
+    )
+    .unwrap();
+    static ref DB_POOL_MAX_CONNECTIONS: IntGauge = register_int_gauge!(
+        "db_pool_max_connections",
+        "The most database connections the pool opens"
+    )
+    .unwrap();
+    static ref DB_MAILBOX_DEPTH: IntGauge = register_int_gauge!(
+        "db_executor_mailbox_depth",
+        "Messages waiting for a db executor to pick them up"
+    )
+    .unwrap();
+}
+
+// the metrics in the prometheus text format, with the gauges as of now
+pub fn render(pool: &PgPool, db: &Db) -> String {
+    let state = pool.state();
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["idle"])
+        .set(state.idle_connections as i64);
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["in_use"])
+        .set((state.connections - state.idle_connections) as i64);
+    DB_POOL_MAX_CONNECTIONS.set(pool.max_size() as i64);
+    DB_MAILBOX_DEPTH.set(db.mailbox_depth() as i64);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+// times every GraphQL request and counts the ones with errors, labeled by the root field
+// the operation selects, which the schema bounds unlike the operation name a client picks
+// operations selecting several root fields are labeled multiple, and requests that do not
+// parse or validate are labeled invalid
+pub struct GraphqlMetrics;
+
+impl ExtensionFactory for GraphqlMetrics {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(GraphqlMetricsExtension::default())
+    }
+}
+
+#[derive(Default)]
+struct GraphqlMetricsExtension {
+    operation_name: Mutex<Option<String>>,
+    // the label of the parsed operation, used once the document passes validation
+    label: Mutex<Option<String>>,
+    validated: AtomicBool,
+}
+
+#[async_graphql::async_trait::async_trait]
+impl Extension for GraphqlMetricsExtension {
+    async fn request(&self, ctx: &ExtensionContext<'_>, next: NextRequest<'_>) -> Response {
+        let started = Instant::now();
+        let response = next.run(ctx).await;
+
+        let label = self.label.lock().unwrap().take();
+        let operation = match label {
+            Some(label) if self.validated.load(Ordering::Relaxed) => label,
+            _ => "invalid".to_string(),
+        };
+        GRAPHQL_OPERATION_SECONDS
+            .with_label_values(&[&operation])
+            .observe(started.elapsed().as_secs_f64());
+        if response.is_err() {
+            GRAPHQL_OPERATION_ERRORS
+                .with_label_values(&[&operation])
+                .inc();
+        }
+
+        response
+    }
+
+    async fn prepare_request(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        request: Request,
+        next: NextPrepareRequest<'_>,
+    ) -> ServerResult<Request> {
+        *self.operation_name.lock().unwrap() = request.operation_name.clone();
+        next.run(ctx, request).await
+    }
+
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let operation_name = self.operation_name.lock().unwrap().clone();
+        *self.label.lock().unwrap() = Some(operation_label(&document, operation_name.as_deref()));
+
+        Ok(document)
+    }
+
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        self.validated.store(true, Ordering::Relaxed);
+        Ok(result)
+    }
+}
+
+// the root field of the operation the request runs, or multiple when it selects several
+fn operation_label(document: &ExecutableDocument, operation_name: Option<&str>) -> String {
+    let operation = document
+        .operations
+        .iter()
+        .find(|(name, _)| operation_name.is_none() || name.map(|name| name.as_str()) == operation_name);
+    let Some((_, operation)) = operation else {
+        return "invalid".to_string();
+    };
+
+    let mut fields = BTreeSet::new();
+    root_fields(document, &operation.node.selection_set.node, &mut fields, 0);
+
+    let mut fields = fields.into_iter();
+    match (fields.next(), fields.next()) {
+        (Some(field), None) => field.to_string(),
+        (Some(_), Some(_)) => "multiple".to_string(),
+        (None, _) => "invalid".to_string(),
+    }
+}
+
+// the names of the fields selected at the root, through fragments, not their aliases
+// fragments are not checked for cycles before validation, hence the depth
+fn root_fields<'a>(
+    document: &'a ExecutableDocument,
+    selection_set: &'a SelectionSet,
+    fields: &mut BTreeSet<&'a str>,
+    depth: usize,
+) {
+    if depth > 8 {
+        return;
+    }
+    for selection in &selection_set.items {
+        match &selection.node {
+            Selection::Field(field) => {
+                fields.insert(field.node.name.node.as_str());
+            }
+            Selection::InlineFragment(fragment) => {
+                root_fields(document, &fragment.node.selection_set.node, fields, depth + 1)
+            }
+            Selection::FragmentSpread(spread) => {
+                if let Some(fragment) = document.fragments.get(&spread.node.fragment_name.node) {
+                    root_fields(document, &fragment.node.selection_set.node, fields, depth + 1)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::operation_label;
+    use async_graphql::parser::parse_query;
+
+    fn label(query: &str, operation_name: Option<&str>) -> String {
+        operation_label(&parse_query(query).unwrap(), operation_name)
+    }
+
+    #[test]
+    fn operations_are_labeled_by_their_root_field_rather_than_their_name() {
+        assert_eq!(label("query Anything123 { trash { id } }", None), "trash");
+        assert_eq!(label("{ a: lot(id: \"1\") { id } b: lot(id: \"2\") { id } }", None), "lot");
+        assert_eq!(
+            label("{ ...Root } fragment Root on QueryRoot { trendingLots { id } }", None),
+            "trendingLots"
+        );
+        assert_eq!(label("{ trash { id } lot(id: \"1\") { id } }", None), "multiple");
+        assert_eq!(
+            label("query A { trash { id } } query B { lot(id: \"1\") { id } }", Some("B")),
+            "lot"
+        );
+        assert_eq!(label("query A { trash { id } }", Some("C")), "invalid");
+    }
+}
@@ -1,7 +1,17 @@
+pub mod abi;
 pub mod auth;
+pub mod catalog_csv;
 pub mod custom_type;
+pub mod eth_rpc;
+pub mod exchange_rates;
 pub mod hasher;
 pub mod jwt;
+pub mod lot_file;
+pub mod mailer;
+pub mod meta_data;
+pub mod metrics;
+pub mod siwe;
+pub mod telemetry;
 
 // just to make it less of a pain to write
 pub use {self::custom_type::*, self::hasher::*};
@@ -0,0 +1,316 @@
+use chrono::{DateTime, Utc};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use sha3::{Digest, Keccak256};
+
+use super::abi::{from_hex, to_hex};
+use crate::config::config;
+
+const PREAMBLE: &str = " wants you to sign in with your Ethereum account:";
+
+// A Sign-In with Ethereum message (EIP-4361), as the siwe npm package or a wallet prepares it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiweMessage {
+    pub domain: String,
+    // lowercase 0x prefixed hex
+    pub address: String,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: i64,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>,
+}
+
+impl SiweMessage {
+    pub fn parse(message: &str) -> Result<Self, String> {
+        let mut lines = message.lines();
+
+        let domain = lines
+            .next()
+            .and_then(|line| line.strip_suffix(PREAMBLE))
+            .ok_or("not a Sign-In with Ethereum message")?;
+        // the domain may come with the scheme of the site
+        let domain = domain
+            .split_once("://")
+            .map_or(domain, |(_, domain)| domain);
+
+        let address = lines
+            .next()
+            .filter(|address| is_address(address))
+            .ok_or("the message has no address to sign in with")?
+            .to_lowercase();
+
+        let mut statement = None;
+        let mut fields = Vec::new();
+        let mut resources = false;
+        for line in lines {
+            match line.split_once(": ") {
+                _ if line.is_empty() => {}
+                Some((key, value)) if FIELDS.contains(&key) => fields.push((key, value)),
+                _ if line == "Resources:" => resources = true,
+                _ if resources && line.starts_with("- ") => {}
+                _ if fields.is_empty() && statement.is_none() => statement = Some(line.to_string()),
+                _ => return Err(format!("the message has an unexpected line: {}", line)),
+            }
+        }
+
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        let required =
+            |name: &str| field(name).ok_or_else(|| format!("the message has no {}", name));
+        let time = |name: &str| {
+            field(name)
+                .map(|value| {
+                    DateTime::parse_from_rfc3339(&value)
+                        .map(|time| time.with_timezone(&Utc))
+                        .map_err(|_| format!("{} is not an RFC 3339 time", name))
+                })
+                .transpose()
+        };
+
+        Ok(SiweMessage {
+            domain: domain.to_string(),
+            address,
+            statement,
+            uri: required("URI")?,
+            version: required("Version")?,
+            chain_id: required("Chain ID")?
+                .parse()
+                .map_err(|_| "Chain ID is not a number".to_string())?,
+            nonce: required("Nonce")?,
+            issued_at: time("Issued At")?.ok_or("the message has no Issued At")?,
+            expiration_time: time("Expiration Time")?,
+            not_before: time("Not Before")?,
+        })
+    }
+
+    // checks that the message is meant for this domain, valid now, and signed by its address
+    pub fn verify(&self, message: &str, signature: &str, domain: &str) -> Result<(), String> {
+        let now = Utc::now();
+
+        if self.domain != domain {
+            return Err(format!("the message is meant for {}", self.domain));
+        }
+        if self.version != "1" {
+            return Err(format!("version {} is not supported", self.version));
+        }
+        if self
+            .expiration_time
+            .is_some_and(|expiration_time| expiration_time <= now)
+        {
+            return Err("the message has expired".to_string());
+        }
+        if self.not_before.is_some_and(|not_before| not_before > now) {
+            return Err("the message is not valid yet".to_string());
+        }
+        if recover_address(message, signature)? != self.address {
+            return Err("the signature does not match the address".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+const FIELDS: [&str; 8] = [
+    "URI",
+    "Version",
+    "Chain ID",
+    "Nonce",
+    "Issued At",
+    "Expiration Time",
+    "Not Before",
+    "Request ID",
+];
+
+fn is_address(address: &str) -> bool {
+    address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// the address that signed the message with personal_sign (EIP-191)
+pub fn recover_address(message: &str, signature: &str) -> Result<String, String> {
+    let signature = from_hex(signature)?;
+    if signature.len() != 65 {
+        return Err("the signature is not 65 bytes".to_string());
+    }
+
+    // wallets set v to 27 or 28, some libraries to 0 or 1
+    let v = match signature[64] {
+        v @ (27 | 28) => v - 27,
+        v @ (0 | 1) => v,
+        v => return Err(format!("{} is not a recovery id", v)),
+    };
+    let mut recovery_id = RecoveryId::from_byte(v).ok_or("the recovery id is not valid")?;
+    let mut rs = Signature::from_slice(&signature[..64]).map_err(|e| e.to_string())?;
+    // a signature with a high s is valid in Ethereum, but k256 only recovers the low one
+    if let Some(normalized) = rs.normalize_s() {
+        rs = normalized;
+        recovery_id = RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced());
+    }
+
+    let prefixed = format!("\x19Ethereum Signed Message:\n{}{}", message.len(), message);
+    let key = VerifyingKey::recover_from_prehash(
+        &Keccak256::digest(prefixed.as_bytes()),
+        &rs,
+        recovery_id,
+    )
+    .map_err(|_| "the signature could not be recovered".to_string())?;
+
+    let public_key = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&public_key.as_bytes()[1..]);
+    Ok(format!("0x{}", to_hex(&hash[12..])))
+}
+
+// the domain sign-in messages have to be meant for, i.e. the host of the frontend
+pub fn siwe_domain() -> &'static str {
+    &config().auth.siwe_domain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recover_address, SiweMessage};
+    use crate::utils::abi::{from_hex, to_hex};
+    use chrono::{Duration, SecondsFormat, Utc};
+    use k256::ecdsa::{Signature, SigningKey};
+    use sha3::{Digest, Keccak256};
+
+    // the key and the signature of "Some data" from the web3.js documentation
+    const KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const ADDRESS: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+    const SIGNATURE: &str = "0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c";
+
+    // personal_sign, with v as wallets set it
+    fn sign(message: &str) -> String {
+        let key = SigningKey::from_slice(&from_hex(KEY).unwrap()).unwrap();
+        let prefixed = format!("\x19Ethereum Signed Message:\n{}{}", message.len(), message);
+        let (signature, recovery_id) = key
+            .sign_prehash_recoverable(&Keccak256::digest(prefixed.as_bytes()))
+            .unwrap();
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        format!("0x{}", to_hex(&bytes))
+    }
+
+    fn with_v(signature: &str, v: u8) -> String {
+        format!("{}{}", &signature[..130], to_hex(&[v]))
+    }
+
+    fn message(domain: &str, expires_in: Duration) -> String {
+        let now = Utc::now();
+        format!(
+            "{domain} wants you to sign in with your Ethereum account:\n\
+             {ADDRESS}\n\
+             \n\
+             Sign in to the marketplace\n\
+             \n\
+             URI: https://{domain}\n\
+             Version: 1\n\
+             Chain ID: 1\n\
+             Nonce: abcdefghijklmnopq\n\
+             Issued At: {}\n\
+             Expiration Time: {}",
+            (now - Duration::minutes(1)).to_rfc3339_opts(SecondsFormat::Secs, true),
+            (now + expires_in).to_rfc3339_opts(SecondsFormat::Secs, true),
+        )
+    }
+
+    fn verify(message: &str, signature: &str, domain: &str) -> Result<(), String> {
+        SiweMessage::parse(message)?.verify(message, signature, domain)
+    }
+
+    #[test]
+    fn a_known_signature_recovers_its_address() {
+        assert_eq!(recover_address("Some data", SIGNATURE).unwrap(), ADDRESS);
+        assert_ne!(
+            recover_address("Some other data", SIGNATURE).unwrap(),
+            ADDRESS
+        );
+    }
+
+    #[test]
+    fn all_the_ways_of_writing_v_are_recovered() {
+        let v = from_hex(SIGNATURE).unwrap()[64];
+        for v in [v, v - 27] {
+            assert_eq!(
+                recover_address("Some data", &with_v(SIGNATURE, v)).unwrap(),
+                ADDRESS
+            );
+        }
+        // the other recovery id is another key
+        for v in [55 - v, 28 - v] {
+            assert_ne!(
+                recover_address("Some data", &with_v(SIGNATURE, v)).unwrap(),
+                ADDRESS
+            );
+        }
+        assert!(recover_address("Some data", &with_v(SIGNATURE, 2)).is_err());
+        assert!(recover_address("Some data", &SIGNATURE[..128]).is_err());
+    }
+
+    #[test]
+    fn a_signature_with_a_high_s_is_recovered() {
+        let bytes = from_hex(SIGNATURE).unwrap();
+        let signature = Signature::from_slice(&bytes[..64]).unwrap();
+        let (r, s) = signature.split_scalars();
+        let high = Signature::from_scalars(r, -s).unwrap();
+        assert!(high.normalize_s().is_some());
+
+        let mut high = high.to_bytes().to_vec();
+        high.push(55 - bytes[64]);
+        let high = format!("0x{}", to_hex(&high));
+        assert_eq!(recover_address("Some data", &high).unwrap(), ADDRESS);
+    }
+
+    #[test]
+    fn a_signed_message_is_verified() {
+        let message = message("example.com", Duration::minutes(5));
+        let signature = sign(&message);
+        assert_eq!(verify(&message, &signature, "example.com"), Ok(()));
+
+        let siwe = SiweMessage::parse(&message).unwrap();
+        assert_eq!(siwe.address, ADDRESS);
+        assert_eq!(siwe.chain_id, 1);
+        assert_eq!(
+            siwe.statement.as_deref(),
+            Some("Sign in to the marketplace")
+        );
+    }
+
+    #[test]
+    fn a_tampered_message_is_refused() {
+        let message = message("example.com", Duration::minutes(5));
+        let signature = sign(&message);
+        let tampered = message.replace("Chain ID: 1", "Chain ID: 5");
+        assert_eq!(
+            verify(&tampered, &signature, "example.com"),
+            Err("the signature does not match the address".to_string())
+        );
+    }
+
+    #[test]
+    fn a_message_for_another_domain_is_refused() {
+        let message = message("evil.example", Duration::minutes(5));
+        let signature = sign(&message);
+        assert_eq!(
+            verify(&message, &signature, "example.com"),
+            Err("the message is meant for evil.example".to_string())
+        );
+    }
+
+    #[test]
+    fn an_expired_message_is_refused() {
+        let message = message("example.com", Duration::seconds(-1));
+        let signature = sign(&message);
+        assert_eq!(
+            verify(&message, &signature, "example.com"),
+            Err("the message has expired".to_string())
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::env;
+use std::io::IsTerminal;
+
+use actix_http::header::HeaderMap;
+use diesel::connection::{set_default_instrumentation, Instrumentation, InstrumentationEvent};
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry::{global, Context};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Span;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+// the spans exported when OTEL_TRACES_FILTER is not set, the ones of the exporter are left out
+const DEFAULT_TRACES_FILTER: &str = "graphql_backend=info,async_graphql=info";
+
+// flushes the spans not exported yet on shutdown
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("exporting the last spans failed: {}", e);
+            }
+        }
+    }
+}
+
+// logs as RUST_LOG asks to stderr, and exports spans as OTEL_TRACES_EXPORTER asks:
+// otlp sends them to OTEL_EXPORTER_OTLP_ENDPOINT over http, console prints them to stdout
+// without OTEL_TRACES_EXPORTER spans are exported over otlp if an endpoint is set
+pub fn init() -> Telemetry {
+    let exporter = env::var("OTEL_TRACES_EXPORTER").ok().or_else(|| {
+        env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+            .ok()
+            .map(|_| "otlp".to_string())
+    });
+
+    let mut resource = Resource::builder();
+    if env::var("OTEL_SERVICE_NAME").is_err() {
+        resource = resource.with_service_name(env!("CARGO_PKG_NAME"));
+    }
+    let provider = match exporter.as_deref() {
+        Some("otlp") => match opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .build()
+        {
+            Ok(exporter) => Some(SdkTracerProvider::builder().with_batch_exporter(exporter)),
+            Err(e) => {
+                eprintln!(
+                    "spans are not exported, the otlp exporter failed to start: {}",
+                    e
+                );
+                None
+            }
+        },
+        Some("console") => Some(
+            SdkTracerProvider::builder()
+                .with_simple_exporter(opentelemetry_stdout::SpanExporter::default()),
+        ),
+        Some("none") | None => None,
+        Some(other) => {
+            eprintln!(
+                "spans are not exported, OTEL_TRACES_EXPORTER {} is not otlp, console or none",
+                other
+            );
+            None
+        }
+    }
+    .map(|builder| builder.with_resource(resource.build()).build());
+
+    let traces = provider.as_ref().map(|provider| {
+        let filter =
+            env::var("OTEL_TRACES_FILTER").unwrap_or_else(|_| DEFAULT_TRACES_FILTER.to_string());
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+            .with_filter(EnvFilter::new(filter))
+    });
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_ansi(std::io::stderr().is_terminal())
+                .with_filter(EnvFilter::from_default_env()),
+        )
+        .with(traces)
+        .init();
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    if let Some(ref provider) = provider {
+        global::set_tracer_provider(provider.clone());
+    }
+    set_default_instrumentation(|| Some(Box::<QuerySpans>::default())).ok();
+
+    Telemetry { provider }
+}
+
+// the trace a request continues, from its traceparent header
+pub fn parent_context(headers: &HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+// a span for every query a connection runs, within the span of the db message running it
+// the statement is recorded without its bind values, they can be passwords or tokens
+#[derive(Default)]
+struct QuerySpans {
+    spans: Vec<Span>,
+}
+
+impl Instrumentation for QuerySpans {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { query, .. } => {
+                let statement = query.to_string();
+                let statement = statement.split(" -- binds: ").next().unwrap_or_default();
+                self.spans.push(tracing::info_span!(
+                    "db query",
+                    db.system = "postgresql",
+                    db.statement = statement,
+                    otel.kind = "client",
+                    otel.status_code = tracing::field::Empty,
+                ));
+            }
+            InstrumentationEvent::FinishQuery { error, .. } => {
+                if let (Some(span), Some(error)) = (self.spans.pop(), error) {
+                    span.record("otel.status_code", "ERROR");
+                    tracing::debug!(parent: &span, "query failed: {}", error);
+                }
+            }
+            _ => {}
+        }
+    }
+}